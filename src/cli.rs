@@ -0,0 +1,475 @@
+use std::{path::{PathBuf, Path}, collections::BTreeMap, fmt::Display, io::Write};
+use serde::Serialize;
+use clap::{Parser, Subcommand, Args, ValueEnum};
+use crate::{Result, error::Error, data::{Game, GameLayers, GamePaths, Mod, json, validate::Severity}};
+
+/// Everything went fine
+pub const SUCCESS: i32 = 0;
+/// The command ran, but found problems (or differences, for `diff`)
+pub const FAILURE: i32 = 1;
+/// The command couldn't run (e.g. the game couldn't be loaded)
+pub const ERROR: i32 = 2;
+
+/// Victoria 3 mod editor. Starts the editor when no command is given
+#[derive(Debug, Parser)]
+#[command(version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Checks the references between entities, printing every problem found
+    Validate {
+        #[command(flatten)]
+        game: GameArgs,
+        /// Exit with a failure code on warnings too
+        #[arg(long)]
+        deny_warnings: bool
+    },
+    /// Prints the parsed entries of a category as JSON
+    Dump {
+        #[command(flatten)]
+        game: GameArgs,
+        category: Category
+    },
+    /// Prints the number of entries of every category, failing if any file couldn't be parsed
+    Stats {
+        #[command(flatten)]
+        game: GameArgs
+    },
+    /// Compares the entries of two game or mod folders
+    Diff {
+        a: PathBuf,
+        b: PathBuf,
+        /// Game folder that mod folders are loaded on top of
+        #[arg(long)]
+        game: Option<PathBuf>
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct GameArgs {
+    /// Root folder of the game (the one containing `common`)
+    pub game: PathBuf,
+    /// Mod folder to load on top of the game. Can be repeated, in load order
+    #[arg(long = "mod")]
+    pub mods: Vec<PathBuf>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "snake_case")]
+pub enum Category {
+    Religions,
+    Cultures,
+    PopTypes,
+    CountryRanks,
+    CountryTypes,
+    CountryDefinitions,
+    CountryHistory,
+    StateDefinitions,
+    StatePops,
+    StateRegions,
+    Goods,
+    Buildings,
+    ProductionMethods,
+    ProductionMethodGroups,
+    LawGroups,
+    Laws,
+    InterestGroups,
+    Ideologies,
+    PoliticalMovements,
+    Technologies,
+    CoatsOfArms,
+    FlagDefinitions,
+    NamedColors
+}
+
+impl Category {
+    #[inline]
+    pub fn name (self) -> &'static str {
+        return match self {
+            Self::Religions => "religions",
+            Self::Cultures => "cultures",
+            Self::PopTypes => "pop_types",
+            Self::CountryRanks => "country_ranks",
+            Self::CountryTypes => "country_types",
+            Self::CountryDefinitions => "country_definitions",
+            Self::CountryHistory => "country_history",
+            Self::StateDefinitions => "state_definitions",
+            Self::StatePops => "state_pops",
+            Self::StateRegions => "state_regions",
+            Self::Goods => "goods",
+            Self::Buildings => "buildings",
+            Self::ProductionMethods => "production_methods",
+            Self::ProductionMethodGroups => "production_method_groups",
+            Self::LawGroups => "law_groups",
+            Self::Laws => "laws",
+            Self::InterestGroups => "interest_groups",
+            Self::Ideologies => "ideologies",
+            Self::PoliticalMovements => "political_movements",
+            Self::Technologies => "technologies",
+            Self::CoatsOfArms => "coats_of_arms",
+            Self::FlagDefinitions => "flag_definitions",
+            Self::NamedColors => "named_colors"
+        }
+    }
+
+    #[inline]
+    pub fn len (self, game: &Game) -> usize {
+        return match self {
+            Self::Religions => game.religions.borrow().len(),
+            Self::Cultures => game.cultures.borrow().len(),
+            Self::PopTypes => game.pop_types.borrow().len(),
+            Self::CountryRanks => game.countries.ranks.borrow().len(),
+            Self::CountryTypes => game.countries.tys.borrow().len(),
+            Self::CountryDefinitions => game.countries.definitions.borrow().len(),
+            Self::CountryHistory => game.countries.history.borrow().len(),
+            Self::StateDefinitions => game.states.defs.borrow().len(),
+            Self::StatePops => game.states.pops.borrow().len(),
+            Self::StateRegions => game.states.regions.borrow().len(),
+            Self::Goods => game.economy.goods.borrow().len(),
+            Self::Buildings => game.economy.buildings.borrow().len(),
+            Self::ProductionMethods => game.economy.production_methods.borrow().len(),
+            Self::ProductionMethodGroups => game.economy.production_method_groups.borrow().len(),
+            Self::LawGroups => game.laws.groups.borrow().len(),
+            Self::Laws => game.laws.laws.borrow().len(),
+            Self::InterestGroups => game.politics.interest_groups.borrow().len(),
+            Self::Ideologies => game.politics.ideologies.borrow().len(),
+            Self::PoliticalMovements => game.politics.movements.borrow().len(),
+            Self::Technologies => game.technologies.borrow().len(),
+            Self::CoatsOfArms => game.flags.coats_of_arms.borrow().len(),
+            Self::FlagDefinitions => game.flags.definitions.borrow().len(),
+            Self::NamedColors => game.flags.named_colors.borrow().len()
+        }
+    }
+
+    /// Writes the entries as JSON, where the keys that an entry repeats (e.g. `create_pop`) have an array of their values
+    #[inline]
+    pub fn dump (self, game: &Game, w: impl Write) -> serde_json::Result<()> {
+        return match self {
+            Self::Religions => write_json(w, &*game.religions.borrow()),
+            Self::Cultures => write_json(w, &*game.cultures.borrow()),
+            Self::PopTypes => write_json(w, &*game.pop_types.borrow()),
+            Self::CountryRanks => write_json(w, &*game.countries.ranks.borrow()),
+            Self::CountryTypes => write_json(w, &*game.countries.tys.borrow()),
+            Self::CountryDefinitions => write_json(w, &*game.countries.definitions.borrow()),
+            Self::CountryHistory => write_json(w, &*game.countries.history.borrow()),
+            Self::StateDefinitions => write_json(w, &*game.states.defs.borrow()),
+            Self::StatePops => write_json(w, &*game.states.pops.borrow()),
+            Self::StateRegions => write_json(w, &*game.states.regions.borrow()),
+            Self::Goods => write_json(w, &*game.economy.goods.borrow()),
+            Self::Buildings => write_json(w, &*game.economy.buildings.borrow()),
+            Self::ProductionMethods => write_json(w, &*game.economy.production_methods.borrow()),
+            Self::ProductionMethodGroups => write_json(w, &*game.economy.production_method_groups.borrow()),
+            Self::LawGroups => write_json(w, &*game.laws.groups.borrow()),
+            Self::Laws => write_json(w, &*game.laws.laws.borrow()),
+            Self::InterestGroups => write_json(w, &*game.politics.interest_groups.borrow()),
+            Self::Ideologies => write_json(w, &*game.politics.ideologies.borrow()),
+            Self::PoliticalMovements => write_json(w, &*game.politics.movements.borrow()),
+            Self::Technologies => write_json(w, &*game.technologies.borrow()),
+            Self::CoatsOfArms => write_json(w, &*game.flags.coats_of_arms.borrow()),
+            Self::FlagDefinitions => write_json(w, &*game.flags.definitions.borrow()),
+            Self::NamedColors => write_json(w, &*game.flags.named_colors.borrow())
+        }
+    }
+
+    /// Writes the keys that were removed (`-`), added (`+`) or changed (`~`) from `a` to `b` to `out`, returning how many were found
+    #[inline]
+    pub fn diff (self, a: &Game, b: &Game, out: &mut impl Write) -> usize {
+        let name = self.name();
+        return match self {
+            Self::Religions => diff_maps(out, name, &*a.religions.borrow(), &*b.religions.borrow()),
+            Self::Cultures => diff_maps(out, name, &*a.cultures.borrow(), &*b.cultures.borrow()),
+            Self::PopTypes => diff_maps(out, name, &*a.pop_types.borrow(), &*b.pop_types.borrow()),
+            Self::CountryRanks => diff_maps(out, name, &*a.countries.ranks.borrow(), &*b.countries.ranks.borrow()),
+            Self::CountryTypes => diff_maps(out, name, &*a.countries.tys.borrow(), &*b.countries.tys.borrow()),
+            Self::CountryDefinitions => diff_maps(out, name, &*a.countries.definitions.borrow(), &*b.countries.definitions.borrow()),
+            Self::CountryHistory => diff_maps(out, name, &*a.countries.history.borrow(), &*b.countries.history.borrow()),
+            Self::StateDefinitions => diff_maps(out, name, &*a.states.defs.borrow(), &*b.states.defs.borrow()),
+            Self::StatePops => diff_maps(out, name, &*a.states.pops.borrow(), &*b.states.pops.borrow()),
+            Self::StateRegions => diff_maps(out, name, &*a.states.regions.borrow(), &*b.states.regions.borrow()),
+            Self::Goods => diff_maps(out, name, &*a.economy.goods.borrow(), &*b.economy.goods.borrow()),
+            Self::Buildings => diff_maps(out, name, &*a.economy.buildings.borrow(), &*b.economy.buildings.borrow()),
+            Self::ProductionMethods => diff_maps(out, name, &*a.economy.production_methods.borrow(), &*b.economy.production_methods.borrow()),
+            Self::ProductionMethodGroups => diff_maps(out, name, &*a.economy.production_method_groups.borrow(), &*b.economy.production_method_groups.borrow()),
+            Self::LawGroups => diff_maps(out, name, &*a.laws.groups.borrow(), &*b.laws.groups.borrow()),
+            Self::Laws => diff_maps(out, name, &*a.laws.laws.borrow(), &*b.laws.laws.borrow()),
+            Self::InterestGroups => diff_maps(out, name, &*a.politics.interest_groups.borrow(), &*b.politics.interest_groups.borrow()),
+            Self::Ideologies => diff_maps(out, name, &*a.politics.ideologies.borrow(), &*b.politics.ideologies.borrow()),
+            Self::PoliticalMovements => diff_maps(out, name, &*a.politics.movements.borrow(), &*b.politics.movements.borrow()),
+            Self::Technologies => diff_maps(out, name, &*a.technologies.borrow(), &*b.technologies.borrow()),
+            Self::CoatsOfArms => diff_maps(out, name, &*a.flags.coats_of_arms.borrow(), &*b.flags.coats_of_arms.borrow()),
+            Self::FlagDefinitions => diff_maps(out, name, &*a.flags.definitions.borrow(), &*b.flags.definitions.borrow()),
+            Self::NamedColors => diff_maps(out, name, &*a.flags.named_colors.borrow(), &*b.flags.named_colors.borrow())
+        }
+    }
+}
+
+impl Command {
+    /// Runs the command, returning the process' exit code
+    #[inline]
+    pub async fn run (self) -> i32 {
+        return self.run_with(&mut std::io::stdout(), &mut std::io::stderr()).await
+    }
+
+    /// Runs the command, writing its output to `out` and its errors to `err`
+    pub async fn run_with<O: Write, E: Write> (self, out: &mut O, err: &mut E) -> i32 {
+        match self {
+            Self::Validate { game, deny_warnings } => {
+                let (game, errors) = match game.load().await {
+                    Ok(x) => x,
+                    Err(e) => return fatal(err, e)
+                };
+
+                let diagnostics = game.validate();
+                for error in errors.iter() {
+                    let _ = writeln!(err, "error: {error}");
+                }
+                for diagnostic in diagnostics.iter() {
+                    let _ = writeln!(out, "{diagnostic}");
+                }
+
+                let error_count = errors.len() + diagnostics.iter().filter(|x| x.severity == Severity::Error).count();
+                let warning_count = diagnostics.len() + errors.len() - error_count;
+                let _ = writeln!(err, "{error_count} errors, {warning_count} warnings");
+
+                return match error_count > 0 || (deny_warnings && warning_count > 0) {
+                    true => FAILURE,
+                    false => SUCCESS
+                }
+            },
+
+            Self::Dump { game, category } => {
+                let game = match game.load_strict().await {
+                    Ok(x) => x,
+                    Err(e) => return fatal(err, e)
+                };
+
+                if let Err(e) = category.dump(&game, &mut *out).map_err(Error::from) {
+                    return fatal(err, e)
+                }
+                let _ = writeln!(out);
+                return SUCCESS
+            },
+
+            Self::Stats { game } => {
+                let (game, errors) = match game.load().await {
+                    Ok(x) => x,
+                    Err(e) => return fatal(err, e)
+                };
+
+                for category in Category::value_variants() {
+                    let _ = writeln!(out, "{}: {}", category.name(), category.len(&game));
+                }
+                for error in errors.iter() {
+                    let _ = writeln!(err, "error: {error}");
+                }
+
+                // the counts don't include the files that couldn't be parsed
+                return match errors.is_empty() {
+                    true => SUCCESS,
+                    false => FAILURE
+                }
+            },
+
+            Self::Diff { a, b, game } => {
+                let (a, b) = match futures::try_join!(open(&a, game.as_deref()), open(&b, game.as_deref())) {
+                    Ok(x) => x,
+                    Err(e) => return fatal(err, e)
+                };
+
+                let count = Category::value_variants().iter().map(|x| x.diff(&a, &b, out)).sum::<usize>();
+                return match count {
+                    0 => SUCCESS,
+                    _ => FAILURE
+                }
+            }
+        }
+    }
+}
+
+impl GameArgs {
+    /// Loads the game, skipping (and returning) the files that couldn't be parsed
+    #[inline]
+    pub async fn load (&self) -> Result<(Game, Vec<Error>)> {
+        let mods = futures::future::try_join_all(self.mods.iter().map(|x| Mod::open(x.clone()))).await?;
+        return Game::with_layers_lenient(GameLayers::new(GamePaths::new(self.game.clone()), mods)).await
+    }
+
+    #[inline]
+    pub async fn load_strict (&self) -> Result<Game> {
+        let mods = futures::future::try_join_all(self.mods.iter().map(|x| Mod::open(x.clone()))).await?;
+        return Game::with_mods(self.game.clone(), mods).await
+    }
+}
+
+/// Opens a game folder, or a mod folder on top of `base`
+async fn open (path: &Path, base: Option<&Path>) -> Result<Game> {
+    if !Mod::is_mod(path) {
+        return Game::new(path.to_path_buf()).await
+    }
+
+    let project = Mod::open(path.to_path_buf()).await?;
+    return match base {
+        Some(base) => Game::with_mods(base.to_path_buf(), [project]).await,
+        None => Err(Error::from(std::io::Error::new(std::io::ErrorKind::InvalidInput, "mod folders need a game to be loaded on top of (see `--game`)")).with_file(path))
+    }
+}
+
+fn diff_maps<K: Ord + Display, V: PartialEq> (out: &mut impl Write, category: &str, a: &BTreeMap<K, V>, b: &BTreeMap<K, V>) -> usize {
+    let mut count = 0;
+    for (key, value) in a.iter() {
+        let sign = match b.get(key) {
+            None => '-',
+            Some(other) if other != value => '~',
+            Some(_) => continue
+        };
+        let _ = writeln!(out, "{sign} {category} {key}");
+        count += 1;
+    }
+
+    for key in b.keys().filter(|x| !a.contains_key(x)) {
+        let _ = writeln!(out, "+ {category} {key}");
+        count += 1;
+    }
+
+    return count
+}
+
+/// Writes `value` as pretty JSON, see [`json::to_value`]
+#[inline]
+fn write_json<T: ?Sized + Serialize> (w: impl Write, value: &T) -> serde_json::Result<()> {
+    return serde_json::to_writer_pretty(w, &json::to_value(value)?)
+}
+
+#[inline]
+fn fatal (err: &mut impl Write, e: Error) -> i32 {
+    let _ = writeln!(err, "error: {e}");
+    return ERROR
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use clap::ValueEnum;
+    use crate::data::write_game;
+    use super::{Command, GameArgs, Category, SUCCESS, FAILURE, ERROR};
+
+    const RELIGIONS: (&str, &str) = ("common/religions/00_religions.txt", "protestant = { texture = a.dds traits = { christian } color = { 0.1 0.1 0.8 } }");
+    const CULTURES: &str = "common/cultures/00_cultures.txt";
+    const BRITISH: &str = "british = { color = { 0.7 0.1 0.1 } religion = protestant graphics = european ethnicities = { 1 = caucasian } }";
+
+    fn root (name: &str) -> PathBuf {
+        return std::env::temp_dir().join(format!("vicky3-mod-cli-{name}-{}", std::process::id()))
+    }
+
+    fn args (game: &Path) -> GameArgs {
+        return GameArgs { game: game.to_path_buf(), mods: Vec::new() }
+    }
+
+    /// Runs `command`, returning its exit code, output and errors
+    async fn run (command: Command) -> (i32, String, String) {
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let code = command.run_with(&mut out, &mut err).await;
+        return (code, String::from_utf8(out).unwrap(), String::from_utf8(err).unwrap())
+    }
+
+    #[tokio::test]
+    async fn validate () {
+        let game = root("validate");
+        write_game(&game, &[RELIGIONS, (CULTURES, BRITISH)]).await;
+        let result = run(Command::Validate { game: args(&game), deny_warnings: true }).await;
+        assert_eq!(result, (SUCCESS, String::new(), "0 errors, 0 warnings\n".to_string()));
+
+        // warnings only fail when they're denied
+        write_game(&game, &[RELIGIONS, (CULTURES, BRITISH), ("map_data/state_regions/00_regions.txt", "STATE_A = { id = 1 provinces = { x000001 } }")]).await;
+        assert_eq!(run(Command::Validate { game: args(&game), deny_warnings: false }).await.0, SUCCESS);
+        let (code, out, err) = run(Command::Validate { game: args(&game), deny_warnings: true }).await;
+        assert_eq!(code, FAILURE);
+        assert!(out.starts_with("warning: state region 'STATE_A' (provinces): province 'x000001' isn't owned by any country ["), "{out}");
+        assert_eq!(err, "0 errors, 1 warnings\n");
+
+        let french = "french = { color = { 0.1 0.1 0.7 } religion = catholic graphics = european ethnicities = { 1 = caucasian } }";
+        write_game(&game, &[RELIGIONS, (CULTURES, french)]).await;
+        let (code, out, err) = run(Command::Validate { game: args(&game), deny_warnings: false }).await;
+        assert_eq!(code, FAILURE);
+        assert!(out.starts_with("error: culture 'french' (religion): unknown religion 'catholic' ["), "{out}");
+        assert_eq!(err, "1 errors, 0 warnings\n");
+
+        tokio::fs::remove_dir_all(&game).await.unwrap();
+        let (code, _, err) = run(Command::Validate { game: args(&game), deny_warnings: false }).await;
+        assert_eq!(code, ERROR);
+        assert!(err.starts_with("error: "), "{err}");
+    }
+
+    #[tokio::test]
+    async fn stats () {
+        let game = root("stats");
+        write_game(&game, &[RELIGIONS, (CULTURES, BRITISH)]).await;
+        let (code, out, err) = run(Command::Stats { game: args(&game) }).await;
+        assert_eq!(code, SUCCESS);
+        assert!(out.starts_with("religions: 1\ncultures: 1\npop_types: 0\n"), "{out}");
+        assert_eq!(out.lines().count(), Category::value_variants().len());
+        assert_eq!(err, "");
+
+        // the entries of files that couldn't be parsed aren't counted
+        write_game(&game, &[RELIGIONS, (CULTURES, BRITISH), ("common/religions/01_religions.txt", "catholic = { texture = b.dds }")]).await;
+        let (code, out, err) = run(Command::Stats { game: args(&game) }).await;
+        assert_eq!(code, FAILURE);
+        assert!(out.starts_with("religions: 1\n"), "{out}");
+        assert!(err.starts_with("error: "), "{err}");
+
+        tokio::fs::remove_dir_all(&game).await.unwrap();
+        assert_eq!(run(Command::Stats { game: args(&game) }).await.0, ERROR);
+    }
+
+    #[tokio::test]
+    async fn dump_groups_repeated_keys () {
+        let game = root("dump");
+        write_game(&game, &[("common/history/pops/00_pops.txt", "
+            POPS = {
+                s:STATE_A = {
+                    region_state:GBR = {
+                        create_pop = { culture = british size = 500 }
+                        create_pop = { culture = welsh size = 100 }
+                    }
+                    region_state:FRA = {
+                        create_pop = { culture = french size = 200 }
+                    }
+                }
+            }
+        ")]).await;
+
+        let (code, out, _) = run(Command::Dump { game: args(&game), category: Category::StatePops }).await;
+        assert_eq!(code, SUCCESS);
+        let json = serde_json::from_str::<serde_json::Value>(&out).unwrap();
+        let state = &json["s:STATE_A"];
+        assert_eq!(state["region_state:GBR"]["create_pop"][1]["culture"], "welsh", "{out}");
+        assert_eq!(state["region_state:GBR"]["create_pop"].as_array().map(Vec::len), Some(2));
+        assert_eq!(state["region_state:FRA"]["create_pop"]["culture"], "french");
+
+        tokio::fs::remove_dir_all(&game).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn diff () {
+        let (a, b) = (root("diff-a"), root("diff-b"));
+        write_game(&a, &[RELIGIONS, (CULTURES, BRITISH)]).await;
+        write_game(&b, &[RELIGIONS, (CULTURES, BRITISH)]).await;
+        let diff = |b: &Path| Command::Diff { a: a.clone(), b: b.to_path_buf(), game: None };
+        assert_eq!(run(diff(&b)).await, (SUCCESS, String::new(), String::new()));
+
+        write_game(&b, &[(CULTURES, "
+            british = { color = { 0.7 0.1 0.1 } religion = protestant graphics = african ethnicities = { 1 = caucasian } }
+            french = { color = { 0.1 0.1 0.7 } religion = protestant graphics = european ethnicities = { 1 = caucasian } }
+        ")]).await;
+        let result = run(diff(&b)).await;
+        assert_eq!(result, (FAILURE, "- religions protestant\n~ cultures british\n+ cultures french\n".to_string(), String::new()));
+
+        tokio::fs::remove_dir_all(&b).await.unwrap();
+        assert_eq!(run(diff(&b)).await.0, ERROR);
+        tokio::fs::remove_dir_all(&a).await.unwrap();
+    }
+}
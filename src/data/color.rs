@@ -10,9 +10,9 @@ pub enum Color {
     RgbInt (RgbIntColor),
     #[serde(rename = "")]
     RgbFloat (RgbFloatColor),
-    #[serde(rename = "hsv", with = "hsv360_serde")]
+    #[serde(rename = "hsv360", with = "hsv360_serde")]
     HsvInt (HsvIntColor),
-    #[serde(rename = "hsv360", with = "hsv_serde")]
+    #[serde(rename = "hsv", with = "hsv_serde")]
     HsvFloat (HsvFloatColor),
 }
 
@@ -1,11 +1,10 @@
 use std::{path::Path, collections::HashMap};
-use futures::{Stream, TryStreamExt};
-use jomini::JominiDeserialize;
-use tokio::task::spawn_blocking;
+use eframe::egui::{Ui, ComboBox, RichText, CollapsingHeader};
+use serde::{Serialize, Deserialize, ser::SerializeMap, de::{Visitor, Error as _}};
 use super::{CountryTier};
-use crate::{Result, utils::{ReadDirStream, FlattenOkIter}, data::{Color, read_to_string, GamePaths, Ident}};
+use crate::{Result, utils::{list::ListEntry, attribute_bool, attribute_combo, serde_vec_map::VecMap}, data::{Color, ScriptValue, parse_path, write_to_path, Game, GameLayers, Loaded, Ident, IdentKind}};
 
-#[derive(Debug, Clone, PartialEq, JominiDeserialize)]
+#[derive(Debug, Clone, PartialEq)]
 #[non_exhaustive]
 pub struct CountryDefinition {
     pub color: Color,
@@ -13,30 +12,148 @@ pub struct CountryDefinition {
     pub tier: CountryTier,
     pub cultures: Box<[String]>,
     pub capital: Option<Ident>,
-    #[jomini(default)]
-    pub is_named_from_capital: bool
+    pub is_named_from_capital: bool,
+    pub other: Vec<(String, ScriptValue)>
+}
+
+impl ListEntry for CountryDefinition {
+    #[inline]
+    fn color (&self) -> Option<eframe::epaint::Color32> {
+        Some(self.color.into())
+    }
+
+    fn render_info (&mut self, ui: &mut Ui, game: &Game) {
+        let tys = game.countries.tys.borrow();
+        let cultures = game.cultures.borrow();
+        let states = game.states.defs.borrow();
+
+        self.color.render(ui);
+        attribute_combo(ui, "Country Type", &mut self.country_type, tys.keys().cloned());
+
+        ComboBox::from_label(RichText::new("Tier").strong())
+            .selected_text(self.tier.as_str())
+            .show_ui(ui, |ui| {
+                for tier in CountryTier::ALL {
+                    ui.selectable_value(&mut self.tier, tier, tier.as_str());
+                }
+            });
+
+        ComboBox::from_label(RichText::new("Capital").strong())
+            .selected_text(self.capital.as_ref().map_or("None", |x| x.value.as_str()))
+            .show_ui(ui, |ui| {
+                if ui.selectable_label(self.capital.is_none(), "None").clicked() {
+                    self.capital = None;
+                }
+                for state in states.keys() {
+                    let selected = self.capital.as_ref().map_or(false, |x| x.eq_name(state));
+                    // states are keyed as `s:STATE_X`, while capitals are written as bare names
+                    if ui.selectable_label(selected, state.value.as_str()).clicked() {
+                        self.capital = Some(Ident { value: state.value.clone(), kind: IdentKind::Unknown });
+                    }
+                }
+            });
+
+        attribute_bool(ui, "Named from capital", &mut self.is_named_from_capital);
+
+        CollapsingHeader::new(RichText::new(format!("Cultures ({})", self.cultures.len())).strong()).show(ui, |ui| {
+            let mut toggled = None;
+            for culture in cultures.keys() {
+                let mut checked = self.cultures.contains(culture);
+                if ui.checkbox(&mut checked, culture.as_str()).changed() {
+                    toggled = Some((culture, checked));
+                }
+            }
+
+            // the other cultures keep the order they're written in
+            match toggled {
+                Some((culture, true)) => self.cultures = self.cultures.iter().chain([culture]).cloned().collect(),
+                Some((culture, false)) => self.cultures = self.cultures.iter().filter(|x| *x != culture).cloned().collect(),
+                None => {}
+            }
+        });
+    }
 }
 
 impl CountryDefinition {
+    pub const PATH: &'static str = "common/country_definitions";
+
     #[inline]
     pub async fn from_path (path: impl AsRef<Path>) -> Result<HashMap<Ident, Self>> {
-        let contents = read_to_string(path).await?;
-        return spawn_blocking(move || jomini::text::de::from_utf8_slice(contents.as_bytes())).await.unwrap();
+        return parse_path(path).await
+    }
+
+    #[inline]
+    pub async fn to_path<'a> (path: impl AsRef<Path>, entries: impl IntoIterator<Item = (&'a Ident, &'a Self)>) -> Result<()> {
+        let entries = entries.into_iter().collect::<Vec<_>>();
+        return write_to_path(path, &VecMap(&entries)).await
     }
 
     #[inline]
-    pub async fn from_game (game: &GamePaths) -> Result<impl Stream<Item = Result<(Ident, Self)>>> {
-        let path = game.common().join("country_definitions");
-        let iter = ReadDirStream::new(tokio::fs::read_dir(path).await?)
-            .map_err(<jomini::Error as From<std::io::Error>>::from)
-            .try_filter_map(|x: tokio::fs::DirEntry| async move {
-                if x.metadata().await.map_err(jomini::Error::from)?.is_file() {
-                    return Ok(Some(Self::from_path(x.path()).await?))
-                } else {
-                    return Ok(None)
+    pub async fn from_game (game: &GameLayers) -> Result<Loaded<Ident, Self>> {
+        return game.load(Self::PATH, Self::from_path).await
+    }
+}
+
+impl Serialize for CountryDefinition {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> where S: serde::Serializer {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("color", &self.color)?;
+        map.serialize_entry("country_type", &self.country_type)?;
+        map.serialize_entry("tier", &self.tier)?;
+        map.serialize_entry("cultures", &self.cultures)?;
+        if let Some(ref capital) = self.capital {
+            map.serialize_entry("capital", capital)?;
+        }
+        if self.is_named_from_capital {
+            map.serialize_entry("is_named_from_capital", &true)?;
+        }
+        for (key, value) in self.other.iter() {
+            map.serialize_entry(key, value)?;
+        }
+        return map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for CountryDefinition {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error> where D: serde::Deserializer<'de> {
+        struct LocalVisitor;
+        impl<'de> Visitor<'de> for LocalVisitor {
+            type Value = CountryDefinition;
+
+            #[inline]
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "a country definition")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> std::result::Result<Self::Value, A::Error> where A: serde::de::MapAccess<'de> {
+                let (mut color, mut country_type, mut tier, mut cultures) = (None, None, None, None);
+                let (mut capital, mut is_named_from_capital) = (None, false);
+                let mut other = Vec::new();
+
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "color" => color = Some(map.next_value()?),
+                        "country_type" => country_type = Some(map.next_value()?),
+                        "tier" => tier = Some(map.next_value()?),
+                        "cultures" => cultures = Some(map.next_value()?),
+                        "capital" => capital = Some(map.next_value()?),
+                        "is_named_from_capital" => is_named_from_capital = map.next_value()?,
+                        _ => other.push((key, map.next_value()?))
+                    }
                 }
-            });
 
-        return Ok(FlattenOkIter::new(iter))
+                return Ok(CountryDefinition {
+                    color: color.ok_or_else(|| A::Error::missing_field("color"))?,
+                    country_type: country_type.ok_or_else(|| A::Error::missing_field("country_type"))?,
+                    tier: tier.ok_or_else(|| A::Error::missing_field("tier"))?,
+                    cultures: cultures.ok_or_else(|| A::Error::missing_field("cultures"))?,
+                    capital,
+                    is_named_from_capital,
+                    other
+                })
+            }
+        }
+
+        return deserializer.deserialize_map(LocalVisitor)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,342 @@
+use std::path::Path;
+use eframe::egui::{Ui, RichText, CollapsingHeader, Grid};
+use jomini::JominiDeserialize;
+use serde::{Serialize, Deserialize};
+use crate::{Result, utils::serde_vec_map::VecMap, data::{parse_path, write_to_path, GameLayers, Loaded, Ident, ScriptValue}};
+
+/// Starting setup of a country
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CountryHistory {
+    /// Effects, in the order they are applied
+    pub effects: Vec<CountryEffect>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum CountryEffect {
+    /// `activate_law = law_type:<law>`
+    ActivateLaw (String),
+    /// `add_technology_researched = <technology>`
+    AddTechnology (String),
+    /// `set_institution_investment_level = { institution = <institution> level = <level> }`
+    SetInstitution { institution: String, level: u32 },
+    /// `create_character = { ... }`
+    CreateCharacter (CharacterDefinition),
+    /// `set_variable = <name>` or `set_variable = { name = <name> value = <value> }`
+    SetVariable { name: String, value: Option<ScriptValue> },
+    /// Any other effect, kept as is
+    Other (String, ScriptValue)
+}
+
+/// A character created by a country's history
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CharacterDefinition {
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub ruler: bool,
+    pub heir: bool,
+    pub interest_group: Option<String>,
+    pub ideology: Option<String>,
+    pub traits: Vec<String>,
+    pub other: Vec<(String, ScriptValue)>
+}
+
+impl CountryEffect {
+    #[inline]
+    pub fn from_entry (key: String, value: ScriptValue) -> Self {
+        match (key.as_str(), &value) {
+            ("activate_law", ScriptValue::Scalar(law)) => {
+                return Self::ActivateLaw(law.strip_prefix("law_type:").unwrap_or(law).to_string())
+            },
+
+            ("add_technology_researched", ScriptValue::Scalar(tech)) => return Self::AddTechnology(tech.clone()),
+
+            ("set_institution_investment_level", ScriptValue::Block(_)) => {
+                let institution = value.get("institution").and_then(ScriptValue::as_str);
+                let level = value.get("level").and_then(ScriptValue::as_str).and_then(|x| x.parse().ok());
+                if let (Some(institution), Some(level)) = (institution, level) {
+                    return Self::SetInstitution { institution: institution.to_string(), level }
+                }
+            },
+
+            ("create_character", ScriptValue::Block(entries)) => {
+                return Self::CreateCharacter(CharacterDefinition::from_entries(entries.clone()))
+            },
+
+            ("set_variable", ScriptValue::Scalar(name)) => return Self::SetVariable { name: name.clone(), value: None },
+            ("set_variable", ScriptValue::Block(entries)) if entries.iter().all(|(k, _)| k == "name" || k == "value") => {
+                if let Some(name) = value.get("name").and_then(ScriptValue::as_str) {
+                    return Self::SetVariable { name: name.to_string(), value: value.get("value").cloned() }
+                }
+            },
+
+            _ => {}
+        }
+
+        return Self::Other(key, value)
+    }
+
+    #[inline]
+    pub fn to_entry (&self) -> (String, ScriptValue) {
+        return match self {
+            Self::ActivateLaw(law) => ("activate_law".to_string(), ScriptValue::Scalar(format!("law_type:{law}"))),
+            Self::AddTechnology(tech) => ("add_technology_researched".to_string(), ScriptValue::Scalar(tech.clone())),
+            Self::SetInstitution { institution, level } => ("set_institution_investment_level".to_string(), ScriptValue::Block(vec![
+                ("institution".to_string(), ScriptValue::Scalar(institution.clone())),
+                ("level".to_string(), ScriptValue::Scalar(level.to_string()))
+            ])),
+            Self::CreateCharacter(character) => ("create_character".to_string(), ScriptValue::Block(character.to_entries())),
+            Self::SetVariable { name, value: None } => ("set_variable".to_string(), ScriptValue::Scalar(name.clone())),
+            Self::SetVariable { name, value: Some(value) } => ("set_variable".to_string(), ScriptValue::Block(vec![
+                ("name".to_string(), ScriptValue::Scalar(name.clone())),
+                ("value".to_string(), value.clone())
+            ])),
+            Self::Other(key, value) => (key.clone(), value.clone())
+        }
+    }
+}
+
+impl CharacterDefinition {
+    pub fn from_entries (entries: Vec<(String, ScriptValue)>) -> Self {
+        let mut this = Self::default();
+        for (key, value) in entries {
+            match (key.as_str(), &value) {
+                ("first_name", ScriptValue::Scalar(x)) => this.first_name = Some(x.clone()),
+                ("last_name", ScriptValue::Scalar(x)) => this.last_name = Some(x.clone()),
+                ("interest_group", ScriptValue::Scalar(x)) => this.interest_group = Some(x.clone()),
+                ("ideology", ScriptValue::Scalar(x)) => this.ideology = Some(x.clone()),
+                ("ruler", x) if x.as_bool().is_some() => this.ruler = x.as_bool().unwrap(),
+                ("heir", x) if x.as_bool().is_some() => this.heir = x.as_bool().unwrap(),
+                ("traits", ScriptValue::List(traits)) => this.traits.extend(traits.iter().filter_map(ScriptValue::as_str).map(ToString::to_string)),
+                _ => this.other.push((key, value))
+            }
+        }
+        return this
+    }
+
+    pub fn to_entries (&self) -> Vec<(String, ScriptValue)> {
+        #[inline]
+        fn scalar (result: &mut Vec<(String, ScriptValue)>, key: &str, value: &Option<String>) {
+            if let Some(value) = value {
+                result.push((key.to_string(), ScriptValue::Scalar(value.clone())));
+            }
+        }
+
+        let mut result = Vec::with_capacity(self.other.len() + 7);
+        scalar(&mut result, "first_name", &self.first_name);
+        scalar(&mut result, "last_name", &self.last_name);
+        if self.ruler {
+            result.push(("ruler".to_string(), ScriptValue::bool(true)));
+        }
+        if self.heir {
+            result.push(("heir".to_string(), ScriptValue::bool(true)));
+        }
+        scalar(&mut result, "interest_group", &self.interest_group);
+        scalar(&mut result, "ideology", &self.ideology);
+
+        if !self.traits.is_empty() {
+            result.push(("traits".to_string(), ScriptValue::List(self.traits.iter().cloned().map(ScriptValue::Scalar).collect())));
+        }
+
+        result.extend(self.other.iter().cloned());
+        return result
+    }
+
+    /// Name of the character, as written on the files
+    #[inline]
+    pub fn name (&self) -> String {
+        return match (&self.first_name, &self.last_name) {
+            (Some(first), Some(last)) => format!("{first} {last}"),
+            (Some(name), None) | (None, Some(name)) => name.clone(),
+            (None, None) => "Unnamed character".to_string()
+        }
+    }
+}
+
+impl CountryHistory {
+    pub const PATH: &'static str = "common/history/countries";
+
+    #[inline]
+    pub fn laws (&self) -> impl Iterator<Item = &str> {
+        return self.effects.iter().filter_map(|x| match x {
+            CountryEffect::ActivateLaw(law) => Some(law.as_str()),
+            _ => None
+        })
+    }
+
+    #[inline]
+    pub fn technologies (&self) -> impl Iterator<Item = &str> {
+        return self.effects.iter().filter_map(|x| match x {
+            CountryEffect::AddTechnology(tech) => Some(tech.as_str()),
+            _ => None
+        })
+    }
+
+    #[inline]
+    pub fn characters (&self) -> impl Iterator<Item = &CharacterDefinition> {
+        return self.effects.iter().filter_map(|x| match x {
+            CountryEffect::CreateCharacter(character) => Some(character),
+            _ => None
+        })
+    }
+
+    #[inline]
+    pub async fn from_path (path: impl AsRef<Path>) -> Result<impl Iterator<Item = (Ident, Self)>> {
+        #[derive(Deserialize)]
+        struct Countries (
+            #[serde(deserialize_with = "crate::utils::serde_vec_map::deserialize")]
+            Vec<(Ident, CountryHistory)>
+        );
+
+        #[derive(JominiDeserialize)]
+        struct Inner {
+            #[jomini(alias = "COUNTRIES", duplicated)]
+            countries: Vec<Countries>
+        }
+
+        return parse_path::<Inner>(path).await.map(|x| x.countries.into_iter().flat_map(|x| x.0))
+    }
+
+    #[inline]
+    pub async fn to_path<'a> (path: impl AsRef<Path>, entries: impl IntoIterator<Item = (&'a Ident, &'a Self)>) -> Result<()> {
+        #[derive(Serialize)]
+        struct Inner<'a> {
+            #[serde(rename = "COUNTRIES", serialize_with = "crate::utils::serde_vec_map::serialize")]
+            countries: Vec<(&'a Ident, &'a CountryHistory)>
+        }
+
+        let inner = Inner { countries: entries.into_iter().collect() };
+        return write_to_path(path, &inner).await
+    }
+
+    #[inline]
+    pub async fn from_game (game: &GameLayers) -> Result<Loaded<Ident, Self>> {
+        return game.load(Self::PATH, Self::from_path).await
+    }
+
+    /// Shows the starting setup of the country
+    pub fn render (&self, ui: &mut Ui) {
+        let laws = self.laws().collect::<Vec<_>>();
+        CollapsingHeader::new(RichText::new(format!("Laws ({})", laws.len())).strong()).show(ui, |ui| {
+            for law in laws {
+                ui.label(law);
+            }
+        });
+
+        let techs = self.technologies().collect::<Vec<_>>();
+        CollapsingHeader::new(RichText::new(format!("Technologies ({})", techs.len())).strong()).show(ui, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                for tech in techs {
+                    ui.label(tech);
+                }
+            });
+        });
+
+        CollapsingHeader::new(RichText::new("Institutions").strong()).show(ui, |ui| {
+            Grid::new("history_institutions").striped(true).show(ui, |ui| {
+                for effect in self.effects.iter() {
+                    if let CountryEffect::SetInstitution { institution, level } = effect {
+                        ui.label(institution);
+                        ui.label(level.to_string());
+                        ui.end_row();
+                    }
+                }
+            });
+        });
+
+        CollapsingHeader::new(RichText::new("Characters").strong()).show(ui, |ui| {
+            for character in self.characters() {
+                let mut title = character.name();
+                if character.ruler {
+                    title.push_str(" (ruler)");
+                } else if character.heir {
+                    title.push_str(" (heir)");
+                }
+
+                ui.label(RichText::new(title).strong());
+                ui.horizontal_wrapped(|ui| {
+                    if let Some(ref ig) = character.interest_group {
+                        ui.label(ig);
+                    }
+                    if let Some(ref ideology) = character.ideology {
+                        ui.label(ideology);
+                    }
+                    for t in character.traits.iter() {
+                        ui.label(RichText::new(t).italics());
+                    }
+                });
+            }
+        });
+
+        CollapsingHeader::new(RichText::new("Variables").strong()).show(ui, |ui| {
+            for effect in self.effects.iter() {
+                if let CountryEffect::SetVariable { name, value } = effect {
+                    match value {
+                        Some(value) => ui.label(format!("{name} = {value}")),
+                        None => ui.label(name)
+                    };
+                }
+            }
+        });
+
+        CollapsingHeader::new(RichText::new("Other effects").strong()).show(ui, |ui| {
+            for effect in self.effects.iter() {
+                if let CountryEffect::Other(key, value) = effect {
+                    match value {
+                        ScriptValue::Compare(op, value) => ui.monospace(format!("{key} {} {value}", op.symbol())),
+                        value => ui.monospace(format!("{key} = {value}"))
+                    };
+                }
+            }
+        });
+    }
+}
+
+impl Serialize for CountryHistory {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> where S: serde::Serializer {
+        let entries = self.effects.iter().map(CountryEffect::to_entry).collect::<Vec<_>>();
+        return VecMap(&entries).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for CountryHistory {
+    #[inline]
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error> where D: serde::Deserializer<'de> {
+        // read as a script value so that the operators of the effects are kept (e.g. `c:FRA ?= { ... }`)
+        let entries = match ScriptValue::deserialize(deserializer)? {
+            ScriptValue::Block(x) => x,
+            _ => return Err(serde::de::Error::custom("expected a block of effects"))
+        };
+        let effects = entries.into_iter().map(|(k, v)| CountryEffect::from_entry(k, v)).collect();
+        return Ok(Self { effects })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use crate::data::{ser, Ident, Operator, ScriptValue};
+    use super::{CountryHistory, CountryEffect};
+
+    #[test]
+    fn keeps_operators () {
+        let data = b"c:GBR = {
+            activate_law = law_type:law_monarchy
+            c:FRA ?= { set_variable = allied }
+            if = { limit = { gdp >= 1000 population < 5 has_law != law_type:law_serfdom } add_technology_researched = railways }
+        }";
+
+        let history = jomini::text::de::from_utf8_slice::<BTreeMap<Ident, CountryHistory>>(data).unwrap();
+        let effects = &history[&Ident::from_str("c:GBR")].effects;
+        assert_eq!(effects[0], CountryEffect::ActivateLaw("law_monarchy".to_string()));
+        assert!(matches!(&effects[1], CountryEffect::Other(key, ScriptValue::Compare(Operator::Exists, _)) if key == "c:FRA"));
+
+        let text = ser::to_string(&history).unwrap();
+        assert!(text.contains("c:FRA ?= {"));
+        assert!(text.contains("gdp >= 1000"));
+        assert!(text.contains("population < 5"));
+        assert!(text.contains("has_law != law_type:law_serfdom"));
+        assert_eq!(jomini::text::de::from_utf8_slice::<BTreeMap<Ident, CountryHistory>>(text.as_bytes()).unwrap(), history);
+    }
+}
@@ -1,31 +1,21 @@
 use std::{collections::{BTreeMap}};
-use futures::{TryFutureExt, TryStreamExt};
-use crate::{Result, utils::refcell::RefCell};
-use super::{GamePaths, Ident};
+use crate::utils::refcell::{RefCell, Ref};
+use super::Ident;
 
-flat_mod! { def, ty, rank, tier }
+flat_mod! { def, ty, rank, tier, history }
 
 #[derive(Debug)]
 pub struct GameCountry {
     pub ranks: RefCell<BTreeMap<String, CountryRank>>,
     pub tys: RefCell<BTreeMap<String, CountryType>>,
     pub definitions: RefCell<BTreeMap<Ident, CountryDefinition>>,
-    // todo history
+    pub history: RefCell<BTreeMap<Ident, CountryHistory>>
 }
 
 impl GameCountry {
+    /// Returns the history of the country with the definition `tag` (e.g. `GBR`)
     #[inline]
-    pub async fn from_game (game: &GamePaths) -> Result<Self> {
-        let (ranks, tys, definitions) = futures::try_join! {
-            CountryRank::from_game(game).and_then(TryStreamExt::try_collect::<BTreeMap<_, _>>),
-            CountryType::from_game(game).and_then(TryStreamExt::try_collect::<BTreeMap<_, _>>),
-            CountryDefinition::from_game(game).and_then(TryStreamExt::try_collect::<BTreeMap<_, _>>)
-        }?;
-
-        return Ok(Self {
-            ranks: RefCell::new(ranks),
-            tys: RefCell::new(tys),
-            definitions: RefCell::new(definitions)
-        })
+    pub fn history_of (&self, tag: &str) -> Option<Ref<'_, CountryHistory>> {
+        return Ref::filter_map(self.history.borrow(), |history| history.iter().find(|(key, _)| key.value == tag).map(|(_, x)| x))
     }
 }
\ No newline at end of file
@@ -1,46 +1,51 @@
 use std::{path::Path, collections::HashMap};
-use futures::{Stream, TryStreamExt};
-use jomini::JominiDeserialize;
-use tokio::task::spawn_blocking;
-use crate::{data::{read_to_string, Game, GamePaths}, Result, utils::{ReadDirStream, FlattenOkIter, list::ListEntry}};
+use serde::{Serialize, Deserialize};
+use crate::{data::{parse_path, write_to_path, Game, GameLayers, Loaded, ScriptValue}, Result, utils::{list::ListEntry, serde_vec_map::VecMap}};
 
 pub type NamedCountryRank<'a> = (&'a String, &'a CountryRank);
 
-#[derive(Debug, Clone, PartialEq, JominiDeserialize)]
+#[derive(Debug, Clone, PartialEq)]
 #[non_exhaustive]
 pub struct CountryRank {
     /// higher value rank effects take priority over lower ones in being assigned, also determines icon index
     pub rank_value: u8,
     pub icon_index: u8,
     /// if yes, this rank is invalid for subjects with a subject type where overlord needs to have higher rank if overlord has that rank or higher
-    #[jomini(default)]
     pub enforce_subject_rank_check: bool,
     /// multiple of average country prestige
-    #[jomini(default)]
     pub prestige_average_threshold: f32,
     /// relative to highest prestige country
-    #[jomini(default)]
     pub prestige_relative_threshold: f32,
     /// minimum amount of generals in the country; if below, game will auto-generate
-    #[jomini(default)]
     pub min_generals: Option<u32>,
     /// max ranks when auto-generating commander rank
-    #[jomini(default)]
     pub max_commander_rank_random: Option<u32>,
     /// min ranks when auto-generating commander rank
-    #[jomini(default)]
     pub min_commander_rank_random: Option<u32>,
-    /*
-    /// {} must evaluate to true for rank to be able to be assigned
-    pub possible: bool,
-    */
     /// whether a country of this rank can colonize
-    #[jomini(default = "default_true")]
     pub can_colonize: bool,
     /// Diplomatic pacts with country of this rank have their cost multiplied by 1 + this amount
-    #[jomini(default)]
     pub diplo_pact_cost: f32,
+    pub other: Vec<(String, ScriptValue)>
+}
 
+impl Default for CountryRank {
+    #[inline]
+    fn default () -> Self {
+        return Self {
+            rank_value: 0,
+            icon_index: 0,
+            enforce_subject_rank_check: false,
+            prestige_average_threshold: 0.0,
+            prestige_relative_threshold: 0.0,
+            min_generals: None,
+            max_commander_rank_random: None,
+            min_commander_rank_random: None,
+            can_colonize: true,
+            diplo_pact_cost: 0.0,
+            other: Vec::new()
+        }
+    }
 }
 
 impl ListEntry for CountryRank {
@@ -56,28 +61,108 @@ impl ListEntry for CountryRank {
 }
 
 impl CountryRank {
+    pub const PATH: &'static str = "common/country_ranks";
+
+    pub fn from_entries (entries: Vec<(String, ScriptValue)>) -> Self {
+        let mut this = Self::default();
+        for (key, value) in entries {
+            match (key.as_str(), &value) {
+                ("rank_value", x) if x.parse::<u8>().is_some() => this.rank_value = x.parse().unwrap(),
+                ("icon_index", x) if x.parse::<u8>().is_some() => this.icon_index = x.parse().unwrap(),
+                ("enforce_subject_rank_check", x) if x.as_bool().is_some() => this.enforce_subject_rank_check = x.as_bool().unwrap(),
+                ("prestige_average_threshold", x) if x.parse::<f32>().is_some() => this.prestige_average_threshold = x.parse().unwrap(),
+                ("prestige_relative_threshold", x) if x.parse::<f32>().is_some() => this.prestige_relative_threshold = x.parse().unwrap(),
+                ("min_generals", x) if x.parse::<u32>().is_some() => this.min_generals = x.parse(),
+                ("max_commander_rank_random", x) if x.parse::<u32>().is_some() => this.max_commander_rank_random = x.parse(),
+                ("min_commander_rank_random", x) if x.parse::<u32>().is_some() => this.min_commander_rank_random = x.parse(),
+                ("can_colonize", x) if x.as_bool().is_some() => this.can_colonize = x.as_bool().unwrap(),
+                ("diplo_pact_cost", x) if x.parse::<f32>().is_some() => this.diplo_pact_cost = x.parse().unwrap(),
+                _ => this.other.push((key, value))
+            }
+        }
+        return this
+    }
+
+    pub fn to_entries (&self) -> Vec<(String, ScriptValue)> {
+        let mut result = vec![
+            ("rank_value".to_string(), ScriptValue::scalar(self.rank_value)),
+            ("icon_index".to_string(), ScriptValue::scalar(self.icon_index))
+        ];
+        if self.enforce_subject_rank_check {
+            result.push(("enforce_subject_rank_check".to_string(), ScriptValue::bool(true)));
+        }
+        for (key, value) in [("prestige_average_threshold", self.prestige_average_threshold), ("prestige_relative_threshold", self.prestige_relative_threshold)] {
+            if value != 0.0 {
+                result.push((key.to_string(), ScriptValue::scalar(value)));
+            }
+        }
+        for (key, value) in [("min_generals", self.min_generals), ("max_commander_rank_random", self.max_commander_rank_random), ("min_commander_rank_random", self.min_commander_rank_random)] {
+            if let Some(value) = value {
+                result.push((key.to_string(), ScriptValue::scalar(value)));
+            }
+        }
+        if !self.can_colonize {
+            result.push(("can_colonize".to_string(), ScriptValue::bool(false)));
+        }
+        if self.diplo_pact_cost != 0.0 {
+            result.push(("diplo_pact_cost".to_string(), ScriptValue::scalar(self.diplo_pact_cost)));
+        }
+        result.extend(self.other.iter().cloned());
+        return result
+    }
+
     #[inline]
     pub async fn from_path (path: impl AsRef<Path>) -> Result<HashMap<String, Self>> {
-        let data = read_to_string(path).await?;
-        return spawn_blocking(move || jomini::text::de::from_utf8_slice(data.as_bytes())).await.unwrap();
+        return parse_path(path).await
+    }
+
+    #[inline]
+    pub async fn to_path<'a> (path: impl AsRef<Path>, entries: impl IntoIterator<Item = (&'a String, &'a Self)>) -> Result<()> {
+        let entries = entries.into_iter().collect::<Vec<_>>();
+        return write_to_path(path, &VecMap(&entries)).await
     }
 
     #[inline]
-    pub async fn from_game (game: &GamePaths) -> Result<impl Stream<Item = Result<(String, Self)>>> {
-        let ranks = game.common().join("country_ranks");
-        let iter = ReadDirStream::new(tokio::fs::read_dir(ranks).await?)
-            .map_err(<jomini::Error as From<std::io::Error>>::from)
-            .try_filter_map(|x: tokio::fs::DirEntry| async move {
-                if x.metadata().await.map_err(jomini::Error::from)?.is_file() {
-                    return Ok(Some(Self::from_path(x.path()).await?))
-                } else {
-                    return Ok(None)
-                }
-            });
-
-        return Ok(FlattenOkIter::new(iter))
+    pub async fn from_game (game: &GameLayers) -> Result<Loaded<String, Self>> {
+        return game.load(Self::PATH, Self::from_path).await
     }
 }
 
-#[inline(always)]
-const fn default_true () -> bool { true }
\ No newline at end of file
+impl Serialize for CountryRank {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> where S: serde::Serializer {
+        return VecMap(&self.to_entries()).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for CountryRank {
+    #[inline]
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error> where D: serde::Deserializer<'de> {
+        let entries = crate::utils::serde_vec_map::deserialize::<'de, String, ScriptValue, _>(deserializer)?;
+        return Ok(Self::from_entries(entries))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{round_trip, keys};
+    use super::CountryRank;
+
+    #[test]
+    fn keeps_unknown_fields () {
+        let ranks = round_trip::<String, CountryRank>(b"
+            great_power = {
+                rank_value = 5
+                icon_index = 4
+                prestige_relative_threshold = 0.5
+                min_generals = 4
+                possible = { is_subject = no }
+            }
+        ");
+
+        let rank = &ranks["great_power"];
+        assert_eq!((rank.rank_value, rank.min_generals), (5, Some(4)));
+        assert!(rank.can_colonize);
+        assert_eq!(keys(&rank.other), ["possible"]);
+    }
+}
\ No newline at end of file
@@ -1,6 +1,7 @@
 use serde::{Serialize, Deserialize};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
 #[non_exhaustive]
 pub enum CountryTier {
     CityState,
@@ -12,6 +13,30 @@ pub enum CountryTier {
     Hegemony
 }
 
+impl CountryTier {
+    pub const ALL: [Self; 6] = [
+        Self::CityState,
+        Self::Principality,
+        Self::GrandPrincipality,
+        Self::Kingdom,
+        Self::Empire,
+        Self::Hegemony
+    ];
+
+    /// Name of the tier in the game files
+    #[inline]
+    pub fn as_str (self) -> &'static str {
+        return match self {
+            Self::CityState => "city_state",
+            Self::Principality => "principality",
+            Self::GrandPrincipality => "grand_principality",
+            Self::Kingdom => "kingdom",
+            Self::Empire => "empire",
+            Self::Hegemony => "hegemony"
+        }
+    }
+}
+
 // todo try implement deser manually
 impl<'de> Deserialize<'de> for CountryTier {
     #[inline]
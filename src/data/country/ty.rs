@@ -1,14 +1,11 @@
-use std::{path::Path, collections::{HashMap}};
-use futures::{TryStreamExt, Stream};
+use std::{path::Path, collections::HashMap};
 use serde::{Serialize, Deserialize};
-use tokio::task::spawn_blocking;
-use crate::{Result, utils::{ReadDirStream, FlattenOkIter, list::ListEntry, attribute_bool, attribute_combo}, data::{read_to_string, Game, GamePaths}};
+use crate::{Result, utils::{list::ListEntry, attribute_bool, attribute_combo, serde_vec_map::VecMap}, data::{parse_path, write_to_path, Game, GameLayers, Loaded, ScriptValue}};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq)]
 #[non_exhaustive]
 pub struct CountryType {
     pub is_colonizable: bool,
-    #[serde(rename = "is_unrecognized", with = "recognized_serde")]
     pub is_recognized: bool,
     pub uses_prestige: bool,
     pub has_events: bool,
@@ -16,7 +13,26 @@ pub struct CountryType {
     pub has_economy: bool,
     pub has_politics: bool,
     pub can_research: bool,
-    pub default_rank: String
+    pub default_rank: String,
+    pub other: Vec<(String, ScriptValue)>
+}
+
+impl Default for CountryType {
+    #[inline]
+    fn default () -> Self {
+        return Self {
+            is_colonizable: false,
+            is_recognized: true,
+            uses_prestige: false,
+            has_events: false,
+            has_military: false,
+            has_economy: false,
+            has_politics: false,
+            can_research: false,
+            default_rank: String::new(),
+            other: Vec::new()
+        }
+    }
 }
 
 impl ListEntry for CountryType {
@@ -39,40 +55,101 @@ impl ListEntry for CountryType {
 }
 
 impl CountryType {
+    pub const PATH: &'static str = "common/country_types";
+
+    pub fn from_entries (entries: Vec<(String, ScriptValue)>) -> Self {
+        let mut this = Self::default();
+        for (key, value) in entries {
+            match (key.as_str(), &value) {
+                ("is_colonizable", x) if x.as_bool().is_some() => this.is_colonizable = x.as_bool().unwrap(),
+                ("is_unrecognized", x) if x.as_bool().is_some() => this.is_recognized = !x.as_bool().unwrap(),
+                ("uses_prestige", x) if x.as_bool().is_some() => this.uses_prestige = x.as_bool().unwrap(),
+                ("has_events", x) if x.as_bool().is_some() => this.has_events = x.as_bool().unwrap(),
+                ("has_military", x) if x.as_bool().is_some() => this.has_military = x.as_bool().unwrap(),
+                ("has_economy", x) if x.as_bool().is_some() => this.has_economy = x.as_bool().unwrap(),
+                ("has_politics", x) if x.as_bool().is_some() => this.has_politics = x.as_bool().unwrap(),
+                ("can_research", x) if x.as_bool().is_some() => this.can_research = x.as_bool().unwrap(),
+                ("default_rank", x) if x.as_str().is_some() => this.default_rank = x.as_str().unwrap().to_string(),
+                _ => this.other.push((key, value))
+            }
+        }
+        return this
+    }
+
+    pub fn to_entries (&self) -> Vec<(String, ScriptValue)> {
+        let mut result = [
+            ("is_colonizable", self.is_colonizable),
+            ("is_unrecognized", !self.is_recognized),
+            ("uses_prestige", self.uses_prestige),
+            ("has_events", self.has_events),
+            ("has_military", self.has_military),
+            ("has_economy", self.has_economy),
+            ("has_politics", self.has_politics),
+            ("can_research", self.can_research)
+        ].into_iter().map(|(key, value)| (key.to_string(), ScriptValue::bool(value))).collect::<Vec<_>>();
+
+        result.push(("default_rank".to_string(), ScriptValue::scalar(&self.default_rank)));
+        result.extend(self.other.iter().cloned());
+        return result
+    }
+
     #[inline]
     pub async fn from_path (path: impl AsRef<Path>) -> Result<HashMap<String, Self>> {
-        let data = read_to_string(path).await?;
-        return spawn_blocking(move || jomini::text::de::from_utf8_slice(data.as_bytes())).await.unwrap()
+        return parse_path(path).await
     }
 
     #[inline]
-    pub async fn from_game (game: &GamePaths) -> Result<impl Stream<Item = Result<(String, Self)>>> {
-        let path = game.common().join("country_types");
-        let iter = ReadDirStream::new(tokio::fs::read_dir(path).await?)
-            .map_err(<jomini::Error as From<std::io::Error>>::from)
-            .try_filter_map(|x: tokio::fs::DirEntry| async move {
-                if x.metadata().await.map_err(jomini::Error::from)?.is_file() {
-                    return Ok(Some(Self::from_path(x.path()).await?))
-                } else {
-                    return Ok(None)
-                }
-            });
-
-        return Ok(FlattenOkIter::new(iter))
+    pub async fn to_path<'a> (path: impl AsRef<Path>, entries: impl IntoIterator<Item = (&'a String, &'a Self)>) -> Result<()> {
+        let entries = entries.into_iter().collect::<Vec<_>>();
+        return write_to_path(path, &VecMap(&entries)).await
     }
-}
 
-mod recognized_serde {
-    use std::ops::{Not};
-    use serde::*;
+    #[inline]
+    pub async fn from_game (game: &GameLayers) -> Result<Loaded<String, Self>> {
+        return game.load(Self::PATH, Self::from_path).await
+    }
+}
 
+impl Serialize for CountryType {
     #[inline]
-    pub fn serialize<S> (this: &bool, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
-        (!this).serialize(serializer)
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> where S: serde::Serializer {
+        return VecMap(&self.to_entries()).serialize(serializer)
     }
+}
 
+impl<'de> Deserialize<'de> for CountryType {
     #[inline]
-    pub fn deserialize<'de, D> (deserializer: D) -> Result<bool, D::Error> where D: Deserializer<'de> {
-        bool::deserialize(deserializer).map(Not::not)
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error> where D: serde::Deserializer<'de> {
+        let entries = crate::utils::serde_vec_map::deserialize::<'de, String, ScriptValue, _>(deserializer)?;
+        return Ok(Self::from_entries(entries))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{round_trip, keys};
+    use super::CountryType;
+
+    #[test]
+    fn keeps_unknown_fields () {
+        let types = round_trip::<String, CountryType>(b"
+            unrecognized = {
+                is_colonizable = yes
+                is_unrecognized = yes
+                uses_prestige = no
+                has_events = yes
+                has_military = yes
+                has_economy = yes
+                has_politics = yes
+                can_research = yes
+                default_rank = unrecognized_regional_power
+                status_explanation = country_type_unrecognized
+            }
+        ");
+
+        let ty = &types["unrecognized"];
+        assert!(!ty.is_recognized && ty.is_colonizable);
+        assert_eq!(ty.default_rank, "unrecognized_regional_power");
+        assert_eq!(keys(&ty.other), ["status_explanation"]);
     }
-}
\ No newline at end of file
+}
@@ -1,33 +1,25 @@
 use std::{collections::HashMap, path::Path};
-use futures::{Stream, TryStreamExt};
-use jomini::JominiDeserialize;
-use tokio::task::spawn_blocking;
+use serde::{Serialize, Deserialize, ser::SerializeMap, de::{Visitor, Error as _}};
 use crate::Result;
 use crate::utils::list::ListEntry;
-use crate::utils::{ReadDirStream, FlattenOkIter, attribute_text, attribute_list};
-use super::{Color, read_to_string, Game, GamePaths};
+use crate::utils::{attribute_text, attribute_list, serde_vec_map::VecMap};
+use super::{Color, ScriptValue, parse_path, write_to_path, Game, GameLayers, Loaded};
 
-#[derive(Debug, Clone, PartialEq, JominiDeserialize)]
+#[derive(Debug, Clone, PartialEq)]
 #[non_exhaustive]
 pub struct Culture {
     pub color: Color,
     pub religion: String,
-    #[jomini(default)]
     pub traits: Box<[String]>,
-    #[jomini(default)]
     pub male_common_first_names: Box<[String]>,
-    #[jomini(default)]
     pub female_common_first_names: Box<[String]>,
-    #[jomini(default)]
     pub noble_last_names: Box<[String]>,
-    #[jomini(default)]
     pub common_last_names: Box<[String]>,
-    #[jomini(default)]
     pub male_regal_first_names: Box<[String]>,
-    #[jomini(default)]
     pub female_regal_first_names: Box<[String]>,
     pub graphics: String,
-    pub ethnicities: HashMap<u32, String>
+    pub ethnicities: HashMap<u32, String>,
+    pub other: Vec<(String, ScriptValue)>
 }
 
 impl ListEntry for Culture {
@@ -46,25 +38,132 @@ impl ListEntry for Culture {
 }
 
 impl Culture {
+    pub const PATH: &'static str = "common/cultures";
+
+    /// Keys of the trait and name lists, in the order they're written
+    const NAME_LISTS: [&'static str; 7] = [
+        "traits", "male_common_first_names", "female_common_first_names", "noble_last_names",
+        "common_last_names", "male_regal_first_names", "female_regal_first_names"
+    ];
+
+    #[inline]
+    fn name_lists (&self) -> [(&'static str, &[String]); 7] {
+        let lists = [
+            &self.traits, &self.male_common_first_names, &self.female_common_first_names, &self.noble_last_names,
+            &self.common_last_names, &self.male_regal_first_names, &self.female_regal_first_names
+        ];
+        return std::array::from_fn(|i| (Self::NAME_LISTS[i], &*lists[i]))
+    }
+
     #[inline]
     pub async fn from_path (path: impl AsRef<Path>) -> Result<HashMap<String, Self>> {
-        let data = read_to_string(path).await?;
-        return spawn_blocking(move || jomini::text::de::from_utf8_slice(data.as_bytes())).await.unwrap()
+        return parse_path(path).await
+    }
+
+    #[inline]
+    pub async fn to_path<'a> (path: impl AsRef<Path>, entries: impl IntoIterator<Item = (&'a String, &'a Self)>) -> Result<()> {
+        let entries = entries.into_iter().collect::<Vec<_>>();
+        return write_to_path(path, &VecMap(&entries)).await
     }
 
     #[inline]
-    pub async fn from_game (game: &GamePaths) -> Result<impl Stream<Item = Result<(String, Self)>>> {
-        let path = game.common().join("cultures");
-        let iter = ReadDirStream::new(tokio::fs::read_dir(path).await?)
-            .map_err(<jomini::Error as From<std::io::Error>>::from)
-            .try_filter_map(|x: tokio::fs::DirEntry| async move {
-                if x.metadata().await.map_err(jomini::Error::from)?.is_file() {
-                    return Ok(Some(Self::from_path(x.path()).await?))
-                } else {
-                    return Ok(None)
+    pub async fn from_game (game: &GameLayers) -> Result<Loaded<String, Self>> {
+        return game.load(Self::PATH, Self::from_path).await
+    }
+}
+
+impl Serialize for Culture {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> where S: serde::Serializer {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("color", &self.color)?;
+        map.serialize_entry("religion", &self.religion)?;
+        for (key, list) in self.name_lists() {
+            if !list.is_empty() {
+                map.serialize_entry(key, list)?;
+            }
+        }
+        map.serialize_entry("graphics", &self.graphics)?;
+        map.serialize_entry("ethnicities", &self.ethnicities)?;
+        for (key, value) in self.other.iter() {
+            map.serialize_entry(key, value)?;
+        }
+        return map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Culture {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error> where D: serde::Deserializer<'de> {
+        struct LocalVisitor;
+        impl<'de> Visitor<'de> for LocalVisitor {
+            type Value = Culture;
+
+            #[inline]
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "a culture")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> std::result::Result<Self::Value, A::Error> where A: serde::de::MapAccess<'de> {
+                let (mut color, mut religion, mut graphics, mut ethnicities) = (None, None, None, None);
+                let mut lists: [Box<[String]>; 7] = Default::default();
+                let mut other = Vec::new();
+
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "color" => color = Some(map.next_value()?),
+                        "religion" => religion = Some(map.next_value()?),
+                        "graphics" => graphics = Some(map.next_value()?),
+                        "ethnicities" => ethnicities = Some(map.next_value()?),
+                        _ => match Culture::NAME_LISTS.iter().position(|x| *x == key) {
+                            Some(i) => lists[i] = map.next_value()?,
+                            None => other.push((key, map.next_value()?))
+                        }
+                    }
                 }
-            });
 
-        return Ok(FlattenOkIter::new(iter))
+                let [traits, male_common_first_names, female_common_first_names, noble_last_names, common_last_names, male_regal_first_names, female_regal_first_names] = lists;
+                return Ok(Culture {
+                    color: color.ok_or_else(|| A::Error::missing_field("color"))?,
+                    religion: religion.ok_or_else(|| A::Error::missing_field("religion"))?,
+                    traits,
+                    male_common_first_names,
+                    female_common_first_names,
+                    noble_last_names,
+                    common_last_names,
+                    male_regal_first_names,
+                    female_regal_first_names,
+                    graphics: graphics.ok_or_else(|| A::Error::missing_field("graphics"))?,
+                    ethnicities: ethnicities.ok_or_else(|| A::Error::missing_field("ethnicities"))?,
+                    other
+                })
+            }
+        }
+
+        return deserializer.deserialize_map(LocalVisitor)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{round_trip, keys};
+    use super::Culture;
+
+    #[test]
+    fn keeps_unknown_fields () {
+        let cultures = round_trip::<String, Culture>(b"
+            british = {
+                color = { 0.7 0.1 0.1 }
+                religion = protestant
+                traits = { anglophone european_heritage }
+                noble_last_names = { Churchill }
+                graphics = european
+                ethnicities = { 1 = caucasian }
+                obsessions = { tea }
+            }
+        ");
+
+        let british = &cultures["british"];
+        assert_eq!(british.noble_last_names.len(), 1);
+        assert!(british.male_common_first_names.is_empty());
+        assert_eq!(keys(&british.other), ["obsessions"]);
+    }
+}
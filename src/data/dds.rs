@@ -0,0 +1,499 @@
+use std::path::Path;
+use image::RgbaImage;
+use crate::{Result, error::{Error, ErrorKind}};
+
+const MAGIC: &[u8; 4] = b"DDS ";
+const HEADER_SIZE: usize = 128;
+const DX10_HEADER_SIZE: usize = 20;
+
+const DDPF_ALPHAPIXELS: u32 = 0x1;
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_RGB: u32 = 0x40;
+const DDPF_LUMINANCE: u32 = 0x20000;
+
+/// Pixel formats we know how to decode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Bc1,
+    Bc2,
+    Bc3,
+    Bc7,
+    /// Uncompressed pixels, with the mask of every channel
+    Masked { bits: u32, masks: [u32; 4], luminance: bool }
+}
+
+/// Decodes the first mipmap of a DDS texture
+pub fn decode (data: &[u8]) -> Result<RgbaImage> {
+    if data.len() < HEADER_SIZE || &data[..4] != MAGIC {
+        return Err(error("not a DDS file"))
+    }
+
+    let height = read_u32(data, 12);
+    let width = read_u32(data, 16);
+    let pf_flags = read_u32(data, 80);
+    let four_cc = &data[84..88];
+
+    let mut offset = HEADER_SIZE;
+    let format = if pf_flags & DDPF_FOURCC != 0 {
+        match four_cc {
+            b"DXT1" => Format::Bc1,
+            b"DXT2" | b"DXT3" => Format::Bc2,
+            b"DXT4" | b"DXT5" => Format::Bc3,
+            b"DX10" => {
+                if data.len() < HEADER_SIZE + DX10_HEADER_SIZE {
+                    return Err(error("truncated DX10 header"))
+                }
+                offset += DX10_HEADER_SIZE;
+
+                match read_u32(data, HEADER_SIZE) {
+                    70..=72 => Format::Bc1,
+                    73..=75 => Format::Bc2,
+                    76..=78 => Format::Bc3,
+                    97..=99 => Format::Bc7,
+                    27..=29 => Format::Masked { bits: 32, masks: [0xff, 0xff00, 0xff0000, 0xff000000], luminance: false },
+                    87 | 90 | 91 => Format::Masked { bits: 32, masks: [0xff0000, 0xff00, 0xff, 0xff000000], luminance: false },
+                    88 | 92 | 93 => Format::Masked { bits: 32, masks: [0xff0000, 0xff00, 0xff, 0], luminance: false },
+                    other => return Err(error(format!("unsupported DXGI format {other}")))
+                }
+            },
+            other => return Err(error(format!("unsupported format `{}`", String::from_utf8_lossy(other))))
+        }
+    } else if pf_flags & (DDPF_RGB | DDPF_LUMINANCE) != 0 {
+        let alpha = if pf_flags & DDPF_ALPHAPIXELS != 0 { read_u32(data, 104) } else { 0 };
+        Format::Masked {
+            bits: read_u32(data, 88),
+            masks: [read_u32(data, 92), read_u32(data, 96), read_u32(data, 100), alpha],
+            luminance: pf_flags & DDPF_LUMINANCE != 0
+        }
+    } else {
+        return Err(error("unsupported pixel format"))
+    };
+
+    let data = &data[offset..];
+    let pixels = match format {
+        Format::Bc1 => decode_blocks(data, width, height, 8, |block, out| decode_bc1(block, out, true))?,
+        Format::Bc2 => decode_blocks(data, width, height, 16, decode_bc2)?,
+        Format::Bc3 => decode_blocks(data, width, height, 16, decode_bc3)?,
+        Format::Bc7 => decode_blocks(data, width, height, 16, decode_bc7)?,
+        Format::Masked { bits, masks, luminance } => decode_masked(data, width, height, bits, masks, luminance)?
+    };
+
+    return RgbaImage::from_raw(width, height, pixels).ok_or_else(|| error("invalid texture size"))
+}
+
+#[inline]
+pub async fn from_path (path: impl AsRef<Path>) -> Result<RgbaImage> {
+    let path = path.as_ref().to_path_buf();
+    let data = match tokio::fs::read(&path).await {
+        Ok(x) => x,
+        Err(e) => return Err(Error::from(e).with_file(path))
+    };
+
+    // a panicking decoder is reported like any other malformed texture
+    let file = path.clone();
+    return match tokio::task::spawn_blocking(move || decode(&data).map_err(|e| e.with_file(path))).await {
+        Ok(result) => result,
+        Err(e) => Err(error(format!("decoding failed: {e}")).with_file(file))
+    }
+}
+
+#[inline]
+fn error (msg: impl Into<String>) -> Error {
+    return Error::new(ErrorKind::Texture(msg.into()))
+}
+
+#[inline]
+fn read_u32 (data: &[u8], offset: usize) -> u32 {
+    return u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+/// Decodes the 4x4 blocks of a block compressed texture, cropping the ones at the edges
+fn decode_blocks (data: &[u8], width: u32, height: u32, block_size: usize, decode: impl Fn(&[u8], &mut [[u8; 4]; 16])) -> Result<Vec<u8>> {
+    let (width, height) = (width as usize, height as usize);
+    let blocks_x = (width + 3) / 4;
+    let blocks_y = (height + 3) / 4;
+    if data.len() < blocks_x * blocks_y * block_size {
+        return Err(error("truncated texture data"))
+    }
+
+    let mut pixels = vec![0; width * height * 4];
+    let mut block = [[0; 4]; 16];
+    for by in 0..blocks_y {
+        for bx in 0..blocks_x {
+            let start = (by * blocks_x + bx) * block_size;
+            decode(&data[start..start + block_size], &mut block);
+
+            for (i, pixel) in block.iter().enumerate() {
+                let (x, y) = (bx * 4 + i % 4, by * 4 + i / 4);
+                if x < width && y < height {
+                    let offset = (y * width + x) * 4;
+                    pixels[offset..offset + 4].copy_from_slice(pixel);
+                }
+            }
+        }
+    }
+
+    return Ok(pixels)
+}
+
+fn decode_masked (data: &[u8], width: u32, height: u32, bits: u32, masks: [u32; 4], luminance: bool) -> Result<Vec<u8>> {
+    let bytes = match bits {
+        8 | 16 | 24 | 32 => bits as usize / 8,
+        _ => return Err(error(format!("unsupported bit count {bits}")))
+    };
+
+    let count = width as usize * height as usize;
+    if data.len() < count * bytes {
+        return Err(error("truncated texture data"))
+    }
+
+    let mut pixels = Vec::with_capacity(count * 4);
+    for pixel in data.chunks_exact(bytes).take(count) {
+        let mut raw = [0; 4];
+        raw[..bytes].copy_from_slice(pixel);
+        let value = u32::from_le_bytes(raw);
+
+        let r = channel(value, masks[0]);
+        let (g, b) = if luminance { (r, r) } else { (channel(value, masks[1]), channel(value, masks[2])) };
+        let a = if masks[3] == 0 { 255 } else { channel(value, masks[3]) };
+        pixels.extend_from_slice(&[r, g, b, a]);
+    }
+
+    return Ok(pixels)
+}
+
+/// Value of the channel under `mask`, scaled to 8 bits
+#[inline]
+fn channel (value: u32, mask: u32) -> u8 {
+    if mask == 0 {
+        return 0
+    }
+
+    let max = mask >> mask.trailing_zeros();
+    let value = (value & mask) >> mask.trailing_zeros();
+    return ((value as u64 * 255 + max as u64 / 2) / max as u64) as u8
+}
+
+#[inline]
+fn rgb565 (value: u16) -> [u8; 3] {
+    let r = ((value >> 11) & 0x1f) as u32;
+    let g = ((value >> 5) & 0x3f) as u32;
+    let b = (value & 0x1f) as u32;
+    return [((r << 3) | (r >> 2)) as u8, ((g << 2) | (g >> 4)) as u8, ((b << 3) | (b >> 2)) as u8]
+}
+
+/// Color block shared by BC1, BC2 and BC3. Only BC1 has a transparent color, when `c0 <= c1`
+fn decode_bc1 (block: &[u8], out: &mut [[u8; 4]; 16], transparency: bool) {
+    let c0 = u16::from_le_bytes([block[0], block[1]]);
+    let c1 = u16::from_le_bytes([block[2], block[3]]);
+    let (e0, e1) = (rgb565(c0), rgb565(c1));
+
+    // weighted average of both endpoints
+    let mix = |wa: u32, wb: u32| {
+        let channel = |a: u8, b: u8| ((a as u32 * wa + b as u32 * wb) / (wa + wb)) as u8;
+        [channel(e0[0], e1[0]), channel(e0[1], e1[1]), channel(e0[2], e1[2]), 255]
+    };
+
+    let colors = if c0 > c1 || !transparency {
+        [mix(1, 0), mix(0, 1), mix(2, 1), mix(1, 2)]
+    } else {
+        [mix(1, 0), mix(0, 1), mix(1, 1), [0; 4]]
+    };
+
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+    for (i, pixel) in out.iter_mut().enumerate() {
+        *pixel = colors[((indices >> (2 * i)) & 0b11) as usize];
+    }
+}
+
+fn decode_bc2 (block: &[u8], out: &mut [[u8; 4]; 16]) {
+    decode_bc1(&block[8..], out, false);
+    let alpha = u64::from_le_bytes(block[..8].try_into().unwrap());
+    for (i, pixel) in out.iter_mut().enumerate() {
+        pixel[3] = ((alpha >> (4 * i)) & 0xf) as u8 * 17;
+    }
+}
+
+fn decode_bc3 (block: &[u8], out: &mut [[u8; 4]; 16]) {
+    decode_bc1(&block[8..], out, false);
+
+    let (a0, a1) = (block[0] as u32, block[1] as u32);
+    let mut alphas = [a0 as u8, a1 as u8, 0, 0, 0, 0, 0, 255];
+    let steps = if a0 > a1 { 7 } else { 5 };
+    for (i, alpha) in (1..).zip(alphas[2..(steps + 1) as usize].iter_mut()) {
+        *alpha = (((steps - i) * a0 + i * a1) / steps) as u8;
+    }
+
+    let mut raw = [0; 8];
+    raw[..6].copy_from_slice(&block[2..8]);
+    let indices = u64::from_le_bytes(raw);
+    for (i, pixel) in out.iter_mut().enumerate() {
+        pixel[3] = alphas[((indices >> (3 * i)) & 0b111) as usize];
+    }
+}
+
+/// Layout of a BC7 block mode
+struct Bc7Mode {
+    subsets: usize,
+    partition_bits: u32,
+    rotation_bits: u32,
+    index_selection_bits: u32,
+    color_bits: u32,
+    alpha_bits: u32,
+    /// one p-bit per endpoint
+    endpoint_pbits: bool,
+    /// one p-bit per subset, shared by both of its endpoints
+    shared_pbits: bool,
+    index_bits: u32,
+    secondary_index_bits: u32
+}
+
+const BC7_MODES: [Bc7Mode; 8] = [
+    Bc7Mode { subsets: 3, partition_bits: 4, rotation_bits: 0, index_selection_bits: 0, color_bits: 4, alpha_bits: 0, endpoint_pbits: true, shared_pbits: false, index_bits: 3, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 6, alpha_bits: 0, endpoint_pbits: false, shared_pbits: true, index_bits: 3, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 3, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 5, alpha_bits: 0, endpoint_pbits: false, shared_pbits: false, index_bits: 2, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 7, alpha_bits: 0, endpoint_pbits: true, shared_pbits: false, index_bits: 2, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 2, index_selection_bits: 1, color_bits: 5, alpha_bits: 6, endpoint_pbits: false, shared_pbits: false, index_bits: 2, secondary_index_bits: 3 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 2, index_selection_bits: 0, color_bits: 7, alpha_bits: 8, endpoint_pbits: false, shared_pbits: false, index_bits: 2, secondary_index_bits: 2 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 0, index_selection_bits: 0, color_bits: 7, alpha_bits: 7, endpoint_pbits: true, shared_pbits: false, index_bits: 4, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 5, alpha_bits: 5, endpoint_pbits: true, shared_pbits: false, index_bits: 2, secondary_index_bits: 0 }
+];
+
+/// Subset of every pixel on the two subset partitions, as a bit per pixel
+const BC7_PARTITIONS_2: [u16; 64] = [
+    0xcccc, 0x8888, 0xeeee, 0xecc8, 0xc880, 0xfeec, 0xfec8, 0xec80,
+    0xc800, 0xffec, 0xfe80, 0xe800, 0xffe8, 0xff00, 0xfff0, 0xf000,
+    0xf710, 0x008e, 0x7100, 0x08ce, 0x008c, 0x7310, 0x3100, 0x8cce,
+    0x088c, 0x3110, 0x6666, 0x366c, 0x17e8, 0x0ff0, 0x718e, 0x399c,
+    0xaaaa, 0xf0f0, 0x5a5a, 0x33cc, 0x3c3c, 0x55aa, 0x9696, 0xa55a,
+    0x73ce, 0x13c8, 0x324c, 0x3bdc, 0x6996, 0xc33c, 0x9966, 0x0660,
+    0x0272, 0x04e4, 0x4e40, 0x2720, 0xc936, 0x936c, 0x39c6, 0x639c,
+    0x9336, 0x9cc6, 0x817e, 0xe718, 0xccf0, 0x0fcc, 0x7744, 0xee22
+];
+
+/// Subset of every pixel on the three subset partitions
+const BC7_PARTITIONS_3: [[u8; 16]; 64] = [
+    [0,0,1,1,0,0,1,1,0,2,2,1,2,2,2,2], [0,0,0,1,0,0,1,1,2,2,1,1,2,2,2,1], [0,0,0,0,2,0,0,1,2,2,1,1,2,2,1,1], [0,2,2,2,0,0,2,2,0,0,1,1,0,1,1,1],
+    [0,0,0,0,0,0,0,0,1,1,2,2,1,1,2,2], [0,0,1,1,0,0,1,1,0,0,2,2,0,0,2,2], [0,0,2,2,0,0,2,2,1,1,1,1,1,1,1,1], [0,0,1,1,0,0,1,1,2,2,1,1,2,2,1,1],
+    [0,0,0,0,0,0,0,0,1,1,1,1,2,2,2,2], [0,0,0,0,1,1,1,1,1,1,1,1,2,2,2,2], [0,0,0,0,1,1,1,1,2,2,2,2,2,2,2,2], [0,0,1,2,0,0,1,2,0,0,1,2,0,0,1,2],
+    [0,1,1,2,0,1,1,2,0,1,1,2,0,1,1,2], [0,1,2,2,0,1,2,2,0,1,2,2,0,1,2,2], [0,0,1,1,0,1,1,2,1,1,2,2,1,2,2,2], [0,0,1,1,2,0,0,1,2,2,0,0,2,2,2,0],
+    [0,0,0,1,0,0,1,1,0,1,1,2,1,1,2,2], [0,1,1,1,0,0,1,1,2,0,0,1,2,2,0,0], [0,0,0,0,1,1,2,2,1,1,2,2,1,1,2,2], [0,0,2,2,0,0,2,2,0,0,2,2,1,1,1,1],
+    [0,1,1,1,0,1,1,1,0,2,2,2,0,2,2,2], [0,0,0,1,0,0,0,1,2,2,2,1,2,2,2,1], [0,0,0,0,0,0,1,1,0,1,2,2,0,1,2,2], [0,0,0,0,1,1,0,0,2,2,1,0,2,2,1,0],
+    [0,1,2,2,0,1,2,2,0,0,1,1,0,0,0,0], [0,0,1,2,0,0,1,2,1,1,2,2,2,2,2,2], [0,1,1,0,1,2,2,1,1,2,2,1,0,1,1,0], [0,0,0,0,0,1,1,0,1,2,2,1,1,2,2,1],
+    [0,0,2,2,1,1,0,2,1,1,0,2,0,0,2,2], [0,1,1,0,0,1,1,0,2,0,0,2,2,2,2,2], [0,0,1,1,0,1,2,2,0,1,2,2,0,0,1,1], [0,0,0,0,2,0,0,0,2,2,1,1,2,2,2,1],
+    [0,0,0,0,0,0,0,2,1,1,2,2,1,2,2,2], [0,2,2,2,0,0,2,2,0,0,1,2,0,0,1,1], [0,0,1,1,0,0,1,2,0,0,2,2,0,2,2,2], [0,1,2,0,0,1,2,0,0,1,2,0,0,1,2,0],
+    [0,0,0,0,1,1,1,1,2,2,2,2,0,0,0,0], [0,1,2,0,1,2,0,1,2,0,1,2,0,1,2,0], [0,1,2,0,2,0,1,2,1,2,0,1,0,1,2,0], [0,0,1,1,2,2,0,0,1,1,2,2,0,0,1,1],
+    [0,0,1,1,1,1,2,2,2,2,0,0,0,0,1,1], [0,1,0,1,0,1,0,1,2,2,2,2,2,2,2,2], [0,0,0,0,0,0,0,0,2,1,2,1,2,1,2,1], [0,0,2,2,1,1,2,2,0,0,2,2,1,1,2,2],
+    [0,0,2,2,0,0,1,1,0,0,2,2,0,0,1,1], [0,2,2,0,1,2,2,1,0,2,2,0,1,2,2,1], [0,1,0,1,2,2,2,2,2,2,2,2,0,1,0,1], [0,0,0,0,2,1,2,1,2,1,2,1,2,1,2,1],
+    [0,1,0,1,0,1,0,1,0,1,0,1,2,2,2,2], [0,2,2,2,0,1,1,1,0,2,2,2,0,1,1,1], [0,0,0,2,1,1,1,2,0,0,0,2,1,1,1,2], [0,0,0,0,2,1,1,2,2,1,1,2,2,1,1,2],
+    [0,2,2,2,0,1,1,1,0,1,1,1,0,2,2,2], [0,0,0,2,1,1,1,2,1,1,1,2,0,0,0,2], [0,1,1,0,0,1,1,0,0,1,1,0,2,2,2,2], [0,0,0,0,0,0,0,0,2,1,1,2,2,1,1,2],
+    [0,1,1,0,0,1,1,0,2,2,2,2,2,2,2,2], [0,0,2,2,0,0,1,1,0,0,1,1,0,0,2,2], [0,0,2,2,1,1,2,2,1,1,2,2,0,0,2,2], [0,0,0,0,0,0,0,0,0,0,0,0,2,1,1,2],
+    [0,0,0,2,0,0,0,1,0,0,0,2,0,0,0,1], [0,2,2,2,1,2,2,2,0,2,2,2,1,2,2,2], [0,1,0,1,2,2,2,2,2,2,2,2,2,2,2,2], [0,1,1,1,2,0,1,1,2,2,0,1,2,2,2,0]
+];
+
+/// Pixel whose index has one bit less, for the second subset of the two subset partitions
+const BC7_ANCHORS_2: [u8; 64] = [
+    15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15,
+    15, 2, 8, 2, 2, 8, 8, 15, 2, 8, 2, 2, 8, 8, 2, 2,
+    15, 15, 6, 8, 2, 8, 15, 15, 2, 8, 2, 2, 2, 15, 15, 6,
+    6, 2, 6, 8, 15, 15, 2, 2, 15, 15, 15, 15, 15, 2, 2, 15
+];
+
+/// Pixels whose index has one bit less, for the second and third subsets of the three subset partitions
+const BC7_ANCHORS_3: [[u8; 64]; 2] = [
+    [
+        3, 3, 15, 15, 8, 3, 15, 15, 8, 8, 6, 6, 6, 5, 3, 3,
+        3, 3, 8, 15, 3, 3, 6, 10, 5, 8, 8, 6, 8, 5, 15, 15,
+        8, 15, 3, 5, 6, 10, 8, 15, 15, 3, 15, 5, 15, 15, 15, 15,
+        3, 15, 5, 5, 5, 8, 5, 10, 5, 10, 8, 13, 15, 12, 3, 3
+    ],
+    [
+        15, 8, 8, 3, 15, 15, 3, 8, 15, 15, 15, 15, 15, 15, 15, 8,
+        15, 8, 15, 3, 15, 8, 15, 8, 3, 15, 6, 10, 15, 15, 10, 8,
+        15, 3, 15, 10, 10, 8, 9, 10, 6, 15, 8, 15, 3, 6, 6, 8,
+        15, 3, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 3, 15, 15, 8
+    ]
+];
+
+const BC7_WEIGHTS_2: [u32; 4] = [0, 21, 43, 64];
+const BC7_WEIGHTS_3: [u32; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+const BC7_WEIGHTS_4: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+/// Reads the bits of a block, from the least significant one
+struct Bits (u128);
+
+impl Bits {
+    #[inline]
+    fn read (&mut self, count: u32) -> u32 {
+        let value = (self.0 & ((1 << count) - 1)) as u32;
+        self.0 >>= count;
+        return value
+    }
+}
+
+#[inline]
+fn bc7_subset (subsets: usize, partition: usize, pixel: usize) -> usize {
+    return match subsets {
+        2 => ((BC7_PARTITIONS_2[partition] >> pixel) & 1) as usize,
+        3 => BC7_PARTITIONS_3[partition][pixel] as usize,
+        _ => 0
+    }
+}
+
+#[inline]
+fn bc7_is_anchor (subsets: usize, partition: usize, pixel: usize) -> bool {
+    return pixel == 0 || match subsets {
+        2 => BC7_ANCHORS_2[partition] as usize == pixel,
+        3 => BC7_ANCHORS_3[0][partition] as usize == pixel || BC7_ANCHORS_3[1][partition] as usize == pixel,
+        _ => false
+    }
+}
+
+#[inline]
+fn bc7_interpolate (e0: u8, e1: u8, bits: u32, index: u32) -> u8 {
+    let weight = match bits {
+        2 => BC7_WEIGHTS_2[index as usize],
+        3 => BC7_WEIGHTS_3[index as usize],
+        _ => BC7_WEIGHTS_4[index as usize]
+    };
+    return (((64 - weight) * e0 as u32 + weight * e1 as u32 + 32) >> 6) as u8
+}
+
+/// Expands a `bits` wide value to 8 bits, repeating its highest bits on the lowest ones
+#[inline]
+fn bc7_expand (value: u32, bits: u32) -> u8 {
+    let value = value << (8 - bits);
+    return (value | (value >> bits)) as u8
+}
+
+fn decode_bc7 (block: &[u8], out: &mut [[u8; 4]; 16]) {
+    let mut bits = Bits(u128::from_le_bytes(block.try_into().unwrap()));
+    let mode = match (0..8).find(|_| bits.read(1) == 1) {
+        Some(x) => &BC7_MODES[x],
+        // reserved mode, which decodes to transparent black
+        None => {
+            *out = [[0; 4]; 16];
+            return
+        }
+    };
+
+    let partition = bits.read(mode.partition_bits) as usize;
+    let rotation = bits.read(mode.rotation_bits);
+    let index_selection = bits.read(mode.index_selection_bits);
+
+    let endpoints = mode.subsets * 2;
+    let mut raw = [[0u32; 4]; 6];
+    for channel in 0..3 {
+        for endpoint in raw.iter_mut().take(endpoints) {
+            endpoint[channel] = bits.read(mode.color_bits);
+        }
+    }
+    if mode.alpha_bits > 0 {
+        for endpoint in raw.iter_mut().take(endpoints) {
+            endpoint[3] = bits.read(mode.alpha_bits);
+        }
+    }
+
+    let mut pbits = [None; 6];
+    if mode.endpoint_pbits {
+        for pbit in pbits.iter_mut().take(endpoints) {
+            *pbit = Some(bits.read(1));
+        }
+    } else if mode.shared_pbits {
+        for subset in 0..mode.subsets {
+            let pbit = bits.read(1);
+            pbits[2 * subset] = Some(pbit);
+            pbits[2 * subset + 1] = Some(pbit);
+        }
+    }
+
+    let mut colors = [[0u8; 4]; 6];
+    for (i, color) in colors.iter_mut().enumerate().take(endpoints) {
+        for (channel, value) in color.iter_mut().enumerate() {
+            let width = if channel == 3 { mode.alpha_bits } else { mode.color_bits };
+            *value = match (width, pbits[i]) {
+                (0, _) => 255,
+                (width, Some(pbit)) => bc7_expand((raw[i][channel] << 1) | pbit, width + 1),
+                (width, None) => bc7_expand(raw[i][channel], width)
+            };
+        }
+    }
+
+    let mut indices = [0; 16];
+    for (pixel, index) in indices.iter_mut().enumerate() {
+        let anchor = bc7_is_anchor(mode.subsets, partition, pixel);
+        *index = bits.read(mode.index_bits - u32::from(anchor));
+    }
+
+    let mut secondary = [0; 16];
+    if mode.secondary_index_bits > 0 {
+        for (pixel, index) in secondary.iter_mut().enumerate() {
+            *index = bits.read(mode.secondary_index_bits - u32::from(pixel == 0));
+        }
+    }
+
+    for (pixel, out) in out.iter_mut().enumerate() {
+        let subset = bc7_subset(mode.subsets, partition, pixel);
+        let (e0, e1) = (colors[2 * subset], colors[2 * subset + 1]);
+
+        let ((color_index, color_bits), (alpha_index, alpha_bits)) = match (mode.secondary_index_bits, index_selection) {
+            (0, _) => ((indices[pixel], mode.index_bits), (indices[pixel], mode.index_bits)),
+            (_, 0) => ((indices[pixel], mode.index_bits), (secondary[pixel], mode.secondary_index_bits)),
+            _ => ((secondary[pixel], mode.secondary_index_bits), (indices[pixel], mode.index_bits))
+        };
+
+        for (channel, value) in out.iter_mut().take(3).enumerate() {
+            *value = bc7_interpolate(e0[channel], e1[channel], color_bits, color_index);
+        }
+        out[3] = bc7_interpolate(e0[3], e1[3], alpha_bits, alpha_index);
+
+        match rotation {
+            1 => out.swap(0, 3),
+            2 => out.swap(1, 3),
+            3 => out.swap(2, 3),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bc7_anchors_belong_to_their_subsets () {
+        for partition in 0..64 {
+            assert_eq!(bc7_subset(2, partition, BC7_ANCHORS_2[partition] as usize), 1);
+            assert_eq!(bc7_subset(3, partition, BC7_ANCHORS_3[0][partition] as usize), 1);
+            assert_eq!(bc7_subset(3, partition, BC7_ANCHORS_3[1][partition] as usize), 2);
+        }
+    }
+
+    #[test]
+    fn decode_bc1_and_uncompressed () {
+        let mut header = vec![0; HEADER_SIZE];
+        header[..4].copy_from_slice(MAGIC);
+        header[12..16].copy_from_slice(&2u32.to_le_bytes());
+        header[16..20].copy_from_slice(&2u32.to_le_bytes());
+
+        // a single red block, cropped to 2x2
+        let mut bc1 = header.clone();
+        bc1[80..84].copy_from_slice(&DDPF_FOURCC.to_le_bytes());
+        bc1[84..88].copy_from_slice(b"DXT1");
+        bc1.extend_from_slice(&[0x00, 0xf8, 0x00, 0x00, 0, 0, 0, 0]);
+        let image = decode(&bc1).unwrap();
+        assert_eq!(image.dimensions(), (2, 2));
+        assert!(image.pixels().all(|x| x.0 == [255, 0, 0, 255]));
+
+        // BGRA pixels
+        let mut bgra = header;
+        bgra[80..84].copy_from_slice(&(DDPF_RGB | DDPF_ALPHAPIXELS).to_le_bytes());
+        bgra[88..92].copy_from_slice(&32u32.to_le_bytes());
+        for (i, mask) in [0xff0000u32, 0xff00, 0xff, 0xff000000].into_iter().enumerate() {
+            bgra[92 + 4 * i..96 + 4 * i].copy_from_slice(&mask.to_le_bytes());
+        }
+        bgra.extend_from_slice(&[1, 2, 3, 4].repeat(4));
+        let image = decode(&bgra).unwrap();
+        assert!(image.pixels().all(|x| x.0 == [3, 2, 1, 4]));
+    }
+}
@@ -0,0 +1,180 @@
+use std::{path::Path, collections::HashMap};
+use eframe::egui::Ui;
+use serde::{Serialize, Deserialize};
+use crate::{Result, utils::{list::ListEntry, attribute_bool, attribute_text, attribute_list, serde_vec_map::VecMap}, data::{parse_path, write_to_path, Game, GameLayers, Loaded, ScriptValue}};
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Building {
+    pub building_group: String,
+    pub texture: Option<Box<Path>>,
+    pub city_type: Option<String>,
+    pub levels_per_mesh: Option<u32>,
+    /// technologies that must be researched before the building can be built
+    pub unlocking_technologies: Box<[String]>,
+    /// in order, one production method of each group is active at a time
+    pub production_method_groups: Box<[String]>,
+    /// construction points needed to build a level, as a script value (e.g. `construction_cost_medium`)
+    pub required_construction: Option<String>,
+    pub buildable: bool,
+    pub expandable: bool,
+    pub downsizeable: bool,
+    pub has_max_level: bool,
+    pub ownership_type: Option<String>,
+    pub other: Vec<(String, ScriptValue)>
+}
+
+impl Default for Building {
+    #[inline]
+    fn default () -> Self {
+        return Self {
+            building_group: String::new(),
+            texture: None,
+            city_type: None,
+            levels_per_mesh: None,
+            unlocking_technologies: Box::default(),
+            production_method_groups: Box::default(),
+            required_construction: None,
+            buildable: true,
+            expandable: true,
+            downsizeable: true,
+            has_max_level: false,
+            ownership_type: None,
+            other: Vec::new()
+        }
+    }
+}
+
+impl ListEntry for Building {
+    #[inline]
+    fn color (&self) -> Option<eframe::epaint::Color32> {
+        None
+    }
+
+    fn render_info (&mut self, ui: &mut Ui, game: &Game) {
+        attribute_text(ui, "Building group", &mut self.building_group);
+        attribute_list(ui, "Production method groups", self.production_method_groups.iter_mut());
+        attribute_list(ui, "Unlocking technologies", self.unlocking_technologies.iter_mut());
+        attribute_bool(ui, "Buildable", &mut self.buildable);
+        attribute_bool(ui, "Expandable", &mut self.expandable);
+        attribute_bool(ui, "Downsizeable", &mut self.downsizeable);
+        attribute_bool(ui, "Has max level", &mut self.has_max_level);
+    }
+}
+
+impl Building {
+    pub const PATH: &'static str = "common/buildings";
+
+    pub fn from_entries (entries: Vec<(String, ScriptValue)>) -> Self {
+        let mut this = Self::default();
+        for (key, value) in entries {
+            match (key.as_str(), &value) {
+                ("building_group", ScriptValue::Scalar(x)) => this.building_group = x.clone(),
+                ("texture", ScriptValue::Scalar(x)) => this.texture = Some(Path::new(x).into()),
+                ("city_type", ScriptValue::Scalar(x)) => this.city_type = Some(x.clone()),
+                ("levels_per_mesh", x) if x.parse::<u32>().is_some() => this.levels_per_mesh = x.parse(),
+                ("unlocking_technologies", x) if x.strings().is_some() => this.unlocking_technologies = x.strings().unwrap(),
+                ("production_method_groups", x) if x.strings().is_some() => this.production_method_groups = x.strings().unwrap(),
+                ("required_construction", ScriptValue::Scalar(x)) => this.required_construction = Some(x.clone()),
+                ("buildable", x) if x.as_bool().is_some() => this.buildable = x.as_bool().unwrap(),
+                ("expandable", x) if x.as_bool().is_some() => this.expandable = x.as_bool().unwrap(),
+                ("downsizeable", x) if x.as_bool().is_some() => this.downsizeable = x.as_bool().unwrap(),
+                ("has_max_level", x) if x.as_bool().is_some() => this.has_max_level = x.as_bool().unwrap(),
+                ("ownership_type", ScriptValue::Scalar(x)) => this.ownership_type = Some(x.clone()),
+                _ => this.other.push((key, value))
+            }
+        }
+        return this
+    }
+
+    pub fn to_entries (&self) -> Vec<(String, ScriptValue)> {
+        let mut result = vec![("building_group".to_string(), ScriptValue::scalar(&self.building_group))];
+        if let Some(ref texture) = self.texture {
+            result.push(("texture".to_string(), ScriptValue::scalar(texture.display())));
+        }
+        if let Some(ref city_type) = self.city_type {
+            result.push(("city_type".to_string(), ScriptValue::scalar(city_type)));
+        }
+        if let Some(levels) = self.levels_per_mesh {
+            result.push(("levels_per_mesh".to_string(), ScriptValue::scalar(levels)));
+        }
+        if !self.unlocking_technologies.is_empty() {
+            result.push(("unlocking_technologies".to_string(), ScriptValue::list(self.unlocking_technologies.iter())));
+        }
+        if !self.production_method_groups.is_empty() {
+            result.push(("production_method_groups".to_string(), ScriptValue::list(self.production_method_groups.iter())));
+        }
+        if let Some(ref construction) = self.required_construction {
+            result.push(("required_construction".to_string(), ScriptValue::scalar(construction)));
+        }
+        for (key, value) in [("buildable", self.buildable), ("expandable", self.expandable), ("downsizeable", self.downsizeable)] {
+            if !value {
+                result.push((key.to_string(), ScriptValue::bool(false)));
+            }
+        }
+        if self.has_max_level {
+            result.push(("has_max_level".to_string(), ScriptValue::bool(true)));
+        }
+        if let Some(ref ownership) = self.ownership_type {
+            result.push(("ownership_type".to_string(), ScriptValue::scalar(ownership)));
+        }
+        result.extend(self.other.iter().cloned());
+        return result
+    }
+
+    #[inline]
+    pub async fn from_path (path: impl AsRef<Path>) -> Result<HashMap<String, Self>> {
+        return parse_path(path).await
+    }
+
+    #[inline]
+    pub async fn to_path<'a> (path: impl AsRef<Path>, entries: impl IntoIterator<Item = (&'a String, &'a Self)>) -> Result<()> {
+        let entries = entries.into_iter().collect::<Vec<_>>();
+        return write_to_path(path, &VecMap(&entries)).await
+    }
+
+    #[inline]
+    pub async fn from_game (game: &GameLayers) -> Result<Loaded<String, Self>> {
+        return game.load(Self::PATH, Self::from_path).await
+    }
+}
+
+impl Serialize for Building {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> where S: serde::Serializer {
+        return VecMap(&self.to_entries()).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Building {
+    #[inline]
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error> where D: serde::Deserializer<'de> {
+        let entries = crate::utils::serde_vec_map::deserialize::<'de, String, ScriptValue, _>(deserializer)?;
+        return Ok(Self::from_entries(entries))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{round_trip, keys};
+    use super::Building;
+
+    #[test]
+    fn keeps_unknown_fields () {
+        let buildings = round_trip::<String, Building>(b"
+            building_test = {
+                building_group = bg_test
+                unlocking_technologies = { tech_a tech_b }
+                production_method_groups = { pmg_test }
+                expandable = no
+                possible = { is_coastal = yes }
+                ai_value = 200
+            }
+        ");
+
+        let building = &buildings["building_test"];
+        assert_eq!(&*building.unlocking_technologies, ["tech_a".to_string(), "tech_b".to_string()]);
+        assert!(building.buildable && !building.expandable);
+        assert_eq!(keys(&building.other), ["possible", "ai_value"]);
+    }
+}
@@ -0,0 +1,173 @@
+use std::{path::Path, collections::HashMap, str::FromStr};
+use eframe::egui::{Ui, ComboBox, RichText};
+use serde::{Serialize, Deserialize};
+use crate::{Result, utils::{list::ListEntry, attribute_bool, attribute_num, serde_vec_map::VecMap}, data::{parse_path, write_to_path, Game, GameLayers, Loaded, ScriptValue}};
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Goods {
+    pub texture: Box<Path>,
+    /// base price of the goods
+    pub cost: u32,
+    pub category: GoodsCategory,
+    /// prestige gained from being the top producer of the goods
+    pub prestige_factor: u32,
+    /// whether the goods can be traded between markets (e.g. services and electricity can't)
+    pub tradeable: bool,
+    /// if yes, the goods are only traded within a state
+    pub local: bool,
+    pub consumption_tax_cost: Option<u32>,
+    pub obsession_chance: Option<f32>,
+    pub other: Vec<(String, ScriptValue)>
+}
+
+impl Default for Goods {
+    #[inline]
+    fn default () -> Self {
+        return Self {
+            texture: Path::new("").into(),
+            cost: 0,
+            category: GoodsCategory::Staple,
+            prestige_factor: 0,
+            tradeable: true,
+            local: false,
+            consumption_tax_cost: None,
+            obsession_chance: None,
+            other: Vec::new()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GoodsCategory {
+    Staple,
+    Industrial,
+    Luxury,
+    Military
+}
+
+impl GoodsCategory {
+    pub const ALL: [Self; 4] = [Self::Staple, Self::Industrial, Self::Luxury, Self::Military];
+
+    /// Name of the category in the game files
+    #[inline]
+    pub fn as_str (self) -> &'static str {
+        return match self {
+            Self::Staple => "staple",
+            Self::Industrial => "industrial",
+            Self::Luxury => "luxury",
+            Self::Military => "military"
+        }
+    }
+}
+
+impl FromStr for GoodsCategory {
+    type Err = ();
+
+    #[inline]
+    fn from_str (s: &str) -> std::result::Result<Self, Self::Err> {
+        return Self::ALL.into_iter().find(|x| x.as_str() == s).ok_or(())
+    }
+}
+
+impl ListEntry for Goods {
+    #[inline]
+    fn color (&self) -> Option<eframe::epaint::Color32> {
+        None
+    }
+
+    fn render_info (&mut self, ui: &mut Ui, game: &Game) {
+        attribute_num(ui, "Cost", &mut self.cost, None);
+
+        ComboBox::from_label(RichText::new("Category").strong())
+            .selected_text(self.category.as_str())
+            .show_ui(ui, |ui| {
+                for category in GoodsCategory::ALL {
+                    ui.selectable_value(&mut self.category, category, category.as_str());
+                }
+            });
+
+        attribute_num(ui, "Prestige factor", &mut self.prestige_factor, None);
+        attribute_bool(ui, "Tradeable", &mut self.tradeable);
+        attribute_bool(ui, "Local", &mut self.local);
+    }
+}
+
+impl Goods {
+    pub const PATH: &'static str = "common/goods";
+
+    pub fn from_entries (entries: Vec<(String, ScriptValue)>) -> Self {
+        let mut this = Self::default();
+        for (key, value) in entries {
+            match (key.as_str(), &value) {
+                ("texture", ScriptValue::Scalar(x)) => this.texture = Path::new(x).into(),
+                ("cost", x) if x.parse::<u32>().is_some() => this.cost = x.parse().unwrap(),
+                ("category", x) if x.parse::<GoodsCategory>().is_some() => this.category = x.parse().unwrap(),
+                ("prestige_factor", x) if x.parse::<u32>().is_some() => this.prestige_factor = x.parse().unwrap(),
+                ("tradeable", x) if x.as_bool().is_some() => this.tradeable = x.as_bool().unwrap(),
+                ("local", x) if x.as_bool().is_some() => this.local = x.as_bool().unwrap(),
+                ("consumption_tax_cost", x) if x.parse::<u32>().is_some() => this.consumption_tax_cost = x.parse(),
+                ("obsession_chance", x) if x.parse::<f32>().is_some() => this.obsession_chance = x.parse(),
+                _ => this.other.push((key, value))
+            }
+        }
+        return this
+    }
+
+    pub fn to_entries (&self) -> Vec<(String, ScriptValue)> {
+        let mut result = vec![
+            ("texture".to_string(), ScriptValue::scalar(self.texture.display())),
+            ("cost".to_string(), ScriptValue::scalar(self.cost)),
+            ("category".to_string(), ScriptValue::scalar(self.category.as_str()))
+        ];
+        if self.prestige_factor != 0 {
+            result.push(("prestige_factor".to_string(), ScriptValue::scalar(self.prestige_factor)));
+        }
+        if !self.tradeable {
+            result.push(("tradeable".to_string(), ScriptValue::bool(false)));
+        }
+        if self.local {
+            result.push(("local".to_string(), ScriptValue::bool(true)));
+        }
+        if let Some(cost) = self.consumption_tax_cost {
+            result.push(("consumption_tax_cost".to_string(), ScriptValue::scalar(cost)));
+        }
+        if let Some(chance) = self.obsession_chance {
+            result.push(("obsession_chance".to_string(), ScriptValue::scalar(chance)));
+        }
+        result.extend(self.other.iter().cloned());
+        return result
+    }
+
+    #[inline]
+    pub async fn from_path (path: impl AsRef<Path>) -> Result<HashMap<String, Self>> {
+        return parse_path(path).await
+    }
+
+    #[inline]
+    pub async fn to_path<'a> (path: impl AsRef<Path>, entries: impl IntoIterator<Item = (&'a String, &'a Self)>) -> Result<()> {
+        let entries = entries.into_iter().collect::<Vec<_>>();
+        return write_to_path(path, &VecMap(&entries)).await
+    }
+
+    #[inline]
+    pub async fn from_game (game: &GameLayers) -> Result<Loaded<String, Self>> {
+        return game.load(Self::PATH, Self::from_path).await
+    }
+}
+
+impl Serialize for Goods {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> where S: serde::Serializer {
+        return VecMap(&self.to_entries()).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Goods {
+    #[inline]
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error> where D: serde::Deserializer<'de> {
+        let entries = crate::utils::serde_vec_map::deserialize::<'de, String, ScriptValue, _>(deserializer)?;
+        return Ok(Self::from_entries(entries))
+    }
+}
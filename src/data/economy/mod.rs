@@ -0,0 +1,12 @@
+use std::{collections::{BTreeMap}};
+use crate::utils::refcell::RefCell;
+
+flat_mod! { goods, building, production }
+
+#[derive(Debug)]
+pub struct GameEconomy {
+    pub goods: RefCell<BTreeMap<String, Goods>>,
+    pub buildings: RefCell<BTreeMap<String, Building>>,
+    pub production_methods: RefCell<BTreeMap<String, ProductionMethod>>,
+    pub production_method_groups: RefCell<BTreeMap<String, ProductionMethodGroup>>
+}
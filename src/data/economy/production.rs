@@ -0,0 +1,353 @@
+use std::{path::Path, collections::{HashMap, BTreeMap}};
+use eframe::egui::{Ui, RichText, Grid, CollapsingHeader, Color32};
+use jomini::JominiDeserialize;
+use serde::{Serialize, Deserialize, ser::SerializeMap, de::Visitor};
+use crate::{Result, utils::{list::ListEntry, attribute_list, serde_vec_map::VecMap}, data::{parse_path, write_to_path, Game, GameLayers, Loaded, ScriptValue}};
+use super::Goods;
+
+/// A way for a building to turn goods and labor into other goods.
+/// Like pop types, the typed fields are read and written with their own (de)serializers, while the rest are kept as is
+#[derive(Debug, Clone, PartialEq, Default)]
+#[non_exhaustive]
+pub struct ProductionMethod {
+    pub texture: Option<Box<Path>>,
+    pub building_modifiers: BuildingModifiers,
+    pub unlocking_technologies: Box<[String]>,
+    pub unlocking_production_methods: Box<[String]>,
+    pub country_modifiers: Option<ScriptValue>,
+    pub state_modifiers: Option<ScriptValue>,
+    pub other: Vec<(String, ScriptValue)>
+}
+
+/// Modifiers applied to the building that uses a production method, by how they scale
+#[derive(Debug, Clone, PartialEq, Default, Serialize, JominiDeserialize)]
+pub struct BuildingModifiers {
+    /// Scaled by the employment of the building (goods inputs and outputs)
+    #[jomini(default, deserialize_with = "crate::utils::serde_vec_map::deserialize")]
+    #[serde(serialize_with = "crate::utils::serde_vec_map::serialize")]
+    pub workforce_scaled: Vec<(String, f32)>,
+    /// Scaled by the level of the building (employment)
+    #[jomini(default, deserialize_with = "crate::utils::serde_vec_map::deserialize")]
+    #[serde(serialize_with = "crate::utils::serde_vec_map::serialize")]
+    pub level_scaled: Vec<(String, f32)>,
+    #[jomini(default, deserialize_with = "crate::utils::serde_vec_map::deserialize")]
+    #[serde(serialize_with = "crate::utils::serde_vec_map::serialize")]
+    pub unscaled: Vec<(String, f32)>
+}
+
+/// Production methods between which a building can choose
+#[derive(Debug, Clone, PartialEq, Default)]
+#[non_exhaustive]
+pub struct ProductionMethodGroup {
+    pub texture: Option<Box<Path>>,
+    pub production_methods: Box<[String]>,
+    pub ai_selection: Option<String>,
+    pub other: Vec<(String, ScriptValue)>
+}
+
+/// Goods balance of a production method, at base prices. Wages aren't counted, so it isn't the building's profit
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ProductionBalance {
+    /// Net amount of every goods, negative for inputs
+    pub goods: BTreeMap<String, f32>,
+    /// Cost of the inputs
+    pub input_cost: f32,
+    /// Value of the outputs
+    pub output_value: f32,
+    /// Goods without a known price, which are left out of the totals
+    pub unknown: Vec<String>
+}
+
+impl BuildingModifiers {
+    #[inline]
+    fn all (&self) -> impl Iterator<Item = &(String, f32)> {
+        return self.workforce_scaled.iter().chain(self.level_scaled.iter()).chain(self.unscaled.iter())
+    }
+
+    /// Goods consumed by the building, as `(goods, amount)`
+    #[inline]
+    pub fn inputs (&self) -> impl Iterator<Item = (&str, f32)> {
+        return self.all().filter_map(|(key, value)| Some((key.strip_prefix("goods_input_")?.strip_suffix("_add")?, *value)))
+    }
+
+    /// Goods produced by the building, as `(goods, amount)`
+    #[inline]
+    pub fn outputs (&self) -> impl Iterator<Item = (&str, f32)> {
+        return self.all().filter_map(|(key, value)| Some((key.strip_prefix("goods_output_")?.strip_suffix("_add")?, *value)))
+    }
+
+    /// Pops employed by the building, as `(pop type, amount)`
+    #[inline]
+    pub fn employment (&self) -> impl Iterator<Item = (&str, f32)> {
+        return self.all().filter_map(|(key, value)| Some((key.strip_prefix("building_employment_")?.strip_suffix("_add")?, *value)))
+    }
+}
+
+impl ProductionBalance {
+    pub fn new (modifiers: &BuildingModifiers, goods: &BTreeMap<String, Goods>) -> Self {
+        let mut this = Self::default();
+        for (sign, entries) in [(-1f32, modifiers.inputs().collect::<Vec<_>>()), (1f32, modifiers.outputs().collect::<Vec<_>>())] {
+            for (name, amount) in entries {
+                *this.goods.entry(name.to_string()).or_default() += sign * amount;
+                match goods.get(name) {
+                    Some(goods) if sign < 0.0 => this.input_cost += amount * goods.cost as f32,
+                    Some(goods) => this.output_value += amount * goods.cost as f32,
+                    None => this.unknown.push(name.to_string())
+                }
+            }
+        }
+        return this
+    }
+
+    /// Value of the outputs minus the cost of the inputs
+    #[inline]
+    pub fn net_value (&self) -> f32 {
+        return self.output_value - self.input_cost
+    }
+}
+
+impl ListEntry for ProductionMethod {
+    #[inline]
+    fn color (&self) -> Option<eframe::epaint::Color32> {
+        None
+    }
+
+    fn render_info (&mut self, ui: &mut Ui, game: &Game) {
+        let goods = game.economy.goods.borrow();
+        let modifiers = &self.building_modifiers;
+        let balance = ProductionBalance::new(modifiers, &goods);
+
+        Grid::new("production_method_goods").striped(true).show(ui, |ui| {
+            ui.label(RichText::new("Goods").strong());
+            ui.label(RichText::new("Net amount").strong());
+            ui.label(RichText::new("Value").strong());
+            ui.end_row();
+
+            for (name, amount) in balance.goods.iter() {
+                ui.label(name);
+                ui.label(format!("{amount:+}"));
+                match goods.get(name) {
+                    Some(goods) => ui.label(format!("{:+}", amount * goods.cost as f32)),
+                    None => ui.label(RichText::new("unknown goods").color(Color32::LIGHT_RED))
+                };
+                ui.end_row();
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("Goods balance at base prices").strong()).on_hover_text("Value of the outputs minus the cost of the inputs, without wages");
+            let net_value = balance.net_value();
+            let color = if net_value < 0.0 { Color32::LIGHT_RED } else { Color32::LIGHT_GREEN };
+            ui.label(RichText::new(format!("{net_value:+} ({} - {})", balance.output_value, balance.input_cost)).color(color));
+        });
+
+        CollapsingHeader::new(RichText::new("Employment").strong()).show(ui, |ui| {
+            Grid::new("production_method_employment").striped(true).show(ui, |ui| {
+                for (pop_type, amount) in modifiers.employment() {
+                    ui.label(pop_type);
+                    ui.label(amount.to_string());
+                    ui.end_row();
+                }
+            });
+        });
+
+        attribute_list(ui, "Unlocking technologies", self.unlocking_technologies.iter_mut());
+    }
+}
+
+impl ListEntry for ProductionMethodGroup {
+    #[inline]
+    fn color (&self) -> Option<eframe::epaint::Color32> {
+        None
+    }
+
+    #[inline]
+    fn render_info (&mut self, ui: &mut Ui, game: &Game) {
+        attribute_list(ui, "Production methods", self.production_methods.iter_mut());
+    }
+}
+
+impl ProductionMethod {
+    pub const PATH: &'static str = "common/production_methods";
+
+    #[inline]
+    pub async fn from_path (path: impl AsRef<Path>) -> Result<HashMap<String, Self>> {
+        return parse_path(path).await
+    }
+
+    #[inline]
+    pub async fn to_path<'a> (path: impl AsRef<Path>, entries: impl IntoIterator<Item = (&'a String, &'a Self)>) -> Result<()> {
+        let entries = entries.into_iter().collect::<Vec<_>>();
+        return write_to_path(path, &VecMap(&entries)).await
+    }
+
+    #[inline]
+    pub async fn from_game (game: &GameLayers) -> Result<Loaded<String, Self>> {
+        return game.load(Self::PATH, Self::from_path).await
+    }
+}
+
+impl ProductionMethodGroup {
+    pub const PATH: &'static str = "common/production_method_groups";
+
+    pub fn from_entries (entries: Vec<(String, ScriptValue)>) -> Self {
+        let mut this = Self::default();
+        for (key, value) in entries {
+            match (key.as_str(), value) {
+                ("texture", ScriptValue::Scalar(x)) => this.texture = Some(Path::new(&x).into()),
+                ("production_methods", x) if x.strings().is_some() => this.production_methods = x.strings().unwrap(),
+                ("ai_selection", ScriptValue::Scalar(x)) => this.ai_selection = Some(x),
+                (_, value) => this.other.push((key, value))
+            }
+        }
+        return this
+    }
+
+    pub fn to_entries (&self) -> Vec<(String, ScriptValue)> {
+        let mut result = Vec::with_capacity(self.other.len() + 3);
+        if let Some(ref texture) = self.texture {
+            result.push(("texture".to_string(), ScriptValue::scalar(texture.display())));
+        }
+        result.push(("production_methods".to_string(), ScriptValue::list(self.production_methods.iter())));
+        if let Some(ref selection) = self.ai_selection {
+            result.push(("ai_selection".to_string(), ScriptValue::scalar(selection)));
+        }
+        result.extend(self.other.iter().cloned());
+        return result
+    }
+
+    #[inline]
+    pub async fn from_path (path: impl AsRef<Path>) -> Result<HashMap<String, Self>> {
+        return parse_path(path).await
+    }
+
+    #[inline]
+    pub async fn to_path<'a> (path: impl AsRef<Path>, entries: impl IntoIterator<Item = (&'a String, &'a Self)>) -> Result<()> {
+        let entries = entries.into_iter().collect::<Vec<_>>();
+        return write_to_path(path, &VecMap(&entries)).await
+    }
+
+    #[inline]
+    pub async fn from_game (game: &GameLayers) -> Result<Loaded<String, Self>> {
+        return game.load(Self::PATH, Self::from_path).await
+    }
+}
+
+impl Serialize for ProductionMethod {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> where S: serde::Serializer {
+        let mut map = serializer.serialize_map(None)?;
+        if let Some(ref texture) = self.texture {
+            map.serialize_entry("texture", texture)?;
+        }
+        map.serialize_entry("building_modifiers", &self.building_modifiers)?;
+        for (key, list) in [("unlocking_technologies", &self.unlocking_technologies), ("unlocking_production_methods", &self.unlocking_production_methods)] {
+            if !list.is_empty() {
+                map.serialize_entry(key, list)?;
+            }
+        }
+        for (key, modifiers) in [("country_modifiers", &self.country_modifiers), ("state_modifiers", &self.state_modifiers)] {
+            if let Some(modifiers) = modifiers {
+                map.serialize_entry(key, modifiers)?;
+            }
+        }
+        for (key, value) in self.other.iter() {
+            map.serialize_entry(key, value)?;
+        }
+        return map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for ProductionMethod {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error> where D: serde::Deserializer<'de> {
+        struct LocalVisitor;
+        impl<'de> Visitor<'de> for LocalVisitor {
+            type Value = ProductionMethod;
+
+            #[inline]
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "a production method")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> std::result::Result<Self::Value, A::Error> where A: serde::de::MapAccess<'de> {
+                let mut this = ProductionMethod::default();
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "texture" => this.texture = Some(map.next_value()?),
+                        "building_modifiers" => this.building_modifiers = map.next_value()?,
+                        "unlocking_technologies" => this.unlocking_technologies = map.next_value()?,
+                        "unlocking_production_methods" => this.unlocking_production_methods = map.next_value()?,
+                        "country_modifiers" => this.country_modifiers = Some(map.next_value()?),
+                        "state_modifiers" => this.state_modifiers = Some(map.next_value()?),
+                        _ => this.other.push((key, map.next_value()?))
+                    }
+                }
+                return Ok(this)
+            }
+        }
+
+        return deserializer.deserialize_map(LocalVisitor)
+    }
+}
+
+impl Serialize for ProductionMethodGroup {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> where S: serde::Serializer {
+        return VecMap(&self.to_entries()).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ProductionMethodGroup {
+    #[inline]
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error> where D: serde::Deserializer<'de> {
+        let entries = crate::utils::serde_vec_map::deserialize::<'de, String, ScriptValue, _>(deserializer)?;
+        return Ok(Self::from_entries(entries))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use crate::data::{parse_slice, round_trip, keys};
+    use super::{ProductionMethod, ProductionBalance, Goods};
+
+    #[test]
+    fn balance_at_base_prices () {
+        let goods = parse_slice::<BTreeMap<String, Goods>>(b"
+            grain = { texture = a.dds cost = 20 category = staple }
+            fertilizer = { texture = b.dds cost = 30 category = industrial }
+        ").unwrap();
+
+        let methods = parse_slice::<BTreeMap<String, ProductionMethod>>(b"
+            pm_test = {
+                building_modifiers = {
+                    workforce_scaled = { goods_input_fertilizer_add = 5 goods_output_grain_add = 30 goods_output_spice_add = 1 }
+                    level_scaled = { building_employment_farmers_add = 4500 }
+                }
+            }
+        ").unwrap();
+
+        let modifiers = &methods["pm_test"].building_modifiers;
+        let balance = ProductionBalance::new(modifiers, &goods);
+        assert_eq!(balance.goods["fertilizer"], -5.0);
+        assert_eq!(balance.net_value(), 30.0 * 20.0 - 5.0 * 30.0);
+        assert_eq!(balance.unknown, vec!["spice".to_string()]);
+        assert_eq!(modifiers.employment().collect::<Vec<_>>(), vec![("farmers", 4500.0)]);
+    }
+
+    #[test]
+    fn keeps_unknown_fields () {
+        let methods = round_trip::<String, ProductionMethod>(b"
+            pm_test = {
+                texture = \"gfx/pm_test.dds\"
+                building_modifiers = { workforce_scaled = { goods_output_grain_add = 30 } }
+                unlocking_technologies = { tech_test }
+                unlocking_laws = { law_test }
+                timed_modifiers = { modifier_test }
+            }
+        ");
+
+        let method = &methods["pm_test"];
+        assert_eq!(method.building_modifiers.outputs().collect::<Vec<_>>(), vec![("grain", 30.0)]);
+        assert_eq!(keys(&method.other), ["unlocking_laws", "timed_modifiers"]);
+    }
+}
@@ -0,0 +1,347 @@
+use std::{path::{Path, PathBuf}};
+use serde::{Serialize, Deserialize};
+use crate::{Result, utils::serde_vec_map::VecMap, data::{GameLayers, Loaded, ScriptValue}};
+use super::{parse_entries, floats, float_list};
+
+/// Colors of a coat of arms or emblem (`color1` to `color3`), either named (e.g. `"blue"`) or literal (e.g. `rgb { 0 0 255 }`)
+pub type CoaColors = [Option<ScriptValue>; 3];
+
+#[derive(Debug, Clone, PartialEq, Default)]
+#[non_exhaustive]
+pub struct CoatOfArms {
+    /// Background texture, relative to [`CoatOfArms::PATTERNS`]
+    pub pattern: Option<String>,
+    pub colors: CoaColors,
+    pub colored_emblems: Vec<ColoredEmblem>,
+    pub textured_emblems: Vec<TexturedEmblem>,
+    /// Other coats of arms drawn on top of this one (e.g. a canton)
+    pub subs: Vec<SubCoatOfArms>,
+    pub other: Vec<(String, ScriptValue)>
+}
+
+/// Emblem whose texture is a mask, tinted with the colors of the emblem or of its coat of arms
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ColoredEmblem {
+    /// Texture, relative to [`CoatOfArms::COLORED_EMBLEMS`]
+    pub texture: String,
+    pub colors: CoaColors,
+    pub instances: Vec<EmblemInstance>,
+    pub other: Vec<(String, ScriptValue)>
+}
+
+/// Emblem drawn with the colors of its texture
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TexturedEmblem {
+    /// Texture, relative to [`CoatOfArms::TEXTURED_EMBLEMS`]
+    pub texture: String,
+    pub instances: Vec<EmblemInstance>,
+    pub other: Vec<(String, ScriptValue)>
+}
+
+/// Placement of an emblem, relative to its coat of arms. Emblems without instances are drawn once, with the default one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EmblemInstance {
+    /// Center of the emblem, from `0` to `1`
+    pub position: [f32; 2],
+    /// Size of the emblem, where negative values mirror it
+    pub scale: [f32; 2],
+    /// Clockwise, in degrees
+    pub rotation: f32,
+    /// Emblems with a higher depth are drawn behind the rest
+    pub depth: f32
+}
+
+/// Coat of arms drawn inside another one (`sub = { parent = ... }`)
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SubCoatOfArms {
+    pub parent: String,
+    /// Colors that replace the ones of `parent`
+    pub colors: CoaColors,
+    pub instances: Vec<SubInstance>,
+    pub other: Vec<(String, ScriptValue)>
+}
+
+/// Area of a sub coat of arms, relative to the coat of arms it's drawn on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SubInstance {
+    /// Top left corner, from `0` to `1`
+    pub offset: [f32; 2],
+    pub scale: [f32; 2]
+}
+
+impl Default for EmblemInstance {
+    #[inline]
+    fn default() -> Self {
+        return Self { position: [0.5, 0.5], scale: [1.0, 1.0], rotation: 0.0, depth: 0.0 }
+    }
+}
+
+impl Default for SubInstance {
+    #[inline]
+    fn default() -> Self {
+        return Self { offset: [0.0, 0.0], scale: [1.0, 1.0] }
+    }
+}
+
+impl CoatOfArms {
+    pub const PATH: &'static str = "common/coat_of_arms/coat_of_arms";
+    pub const PATTERNS: &'static str = "gfx/coat_of_arms/patterns";
+    pub const COLORED_EMBLEMS: &'static str = "gfx/coat_of_arms/colored_emblems";
+    pub const TEXTURED_EMBLEMS: &'static str = "gfx/coat_of_arms/textured_emblems";
+
+    /// Textures of the pattern and emblems, relative to the game's root. Textures of the sub coats of arms aren't included
+    pub fn textures (&self) -> Vec<PathBuf> {
+        let mut result = Vec::with_capacity(1 + self.colored_emblems.len() + self.textured_emblems.len());
+        result.extend(self.pattern.iter().map(|x| Path::new(Self::PATTERNS).join(x)));
+        result.extend(self.colored_emblems.iter().map(|x| Path::new(Self::COLORED_EMBLEMS).join(&x.texture)));
+        result.extend(self.textured_emblems.iter().map(|x| Path::new(Self::TEXTURED_EMBLEMS).join(&x.texture)));
+        return result
+    }
+
+    pub fn from_entries (entries: Vec<(String, ScriptValue)>) -> Self {
+        let mut this = Self::default();
+        for (key, value) in entries {
+            if let Some(i) = color_index(&key) {
+                this.colors[i] = Some(value);
+                continue
+            }
+
+            match (key.as_str(), value) {
+                ("pattern", ScriptValue::Scalar(x)) => this.pattern = Some(x),
+                ("colored_emblem", ScriptValue::Block(x)) => this.colored_emblems.push(ColoredEmblem::from_entries(x)),
+                ("textured_emblem", ScriptValue::Block(x)) => this.textured_emblems.push(TexturedEmblem::from_entries(x)),
+                ("sub", ScriptValue::Block(x)) => this.subs.push(SubCoatOfArms::from_entries(x)),
+                (_, value) => this.other.push((key.clone(), value))
+            }
+        }
+        return this
+    }
+
+    pub fn to_entries (&self) -> Vec<(String, ScriptValue)> {
+        let mut result = Vec::new();
+        if let Some(ref pattern) = self.pattern {
+            result.push(("pattern".to_string(), ScriptValue::Scalar(pattern.clone())));
+        }
+        push_colors(&mut result, &self.colors);
+        result.extend(self.colored_emblems.iter().map(|x| ("colored_emblem".to_string(), ScriptValue::Block(x.to_entries()))));
+        result.extend(self.textured_emblems.iter().map(|x| ("textured_emblem".to_string(), ScriptValue::Block(x.to_entries()))));
+        result.extend(self.subs.iter().map(|x| ("sub".to_string(), ScriptValue::Block(x.to_entries()))));
+        result.extend(self.other.iter().cloned());
+        return result
+    }
+
+    #[inline]
+    pub async fn from_path (path: impl AsRef<Path>) -> Result<Vec<(String, Self)>> {
+        let entries = parse_entries(path).await?;
+        return Ok(entries.into_iter()
+            .filter_map(|(key, value)| match value {
+                ScriptValue::Block(x) => Some((key, Self::from_entries(x))),
+                _ => None
+            })
+            .collect())
+    }
+
+    #[inline]
+    pub async fn from_game (game: &GameLayers) -> Result<Loaded<String, Self>> {
+        return game.load(Self::PATH, Self::from_path).await
+    }
+}
+
+impl ColoredEmblem {
+    pub fn from_entries (entries: Vec<(String, ScriptValue)>) -> Self {
+        let mut this = Self::default();
+        for (key, value) in entries {
+            if let Some(i) = color_index(&key) {
+                this.colors[i] = Some(value);
+                continue
+            }
+
+            match (key.as_str(), value) {
+                ("texture", ScriptValue::Scalar(x)) => this.texture = x,
+                ("instance", ScriptValue::Block(x)) => this.instances.push(EmblemInstance::from_entries(&x)),
+                (_, value) => this.other.push((key.clone(), value))
+            }
+        }
+        return this
+    }
+
+    pub fn to_entries (&self) -> Vec<(String, ScriptValue)> {
+        let mut result = vec![("texture".to_string(), ScriptValue::Scalar(self.texture.clone()))];
+        push_colors(&mut result, &self.colors);
+        result.extend(self.instances.iter().map(|x| ("instance".to_string(), ScriptValue::Block(x.to_entries()))));
+        result.extend(self.other.iter().cloned());
+        return result
+    }
+}
+
+impl TexturedEmblem {
+    pub fn from_entries (entries: Vec<(String, ScriptValue)>) -> Self {
+        let mut this = Self::default();
+        for (key, value) in entries {
+            match (key.as_str(), value) {
+                ("texture", ScriptValue::Scalar(x)) => this.texture = x,
+                ("instance", ScriptValue::Block(x)) => this.instances.push(EmblemInstance::from_entries(&x)),
+                (_, value) => this.other.push((key.clone(), value))
+            }
+        }
+        return this
+    }
+
+    pub fn to_entries (&self) -> Vec<(String, ScriptValue)> {
+        let mut result = vec![("texture".to_string(), ScriptValue::Scalar(self.texture.clone()))];
+        result.extend(self.instances.iter().map(|x| ("instance".to_string(), ScriptValue::Block(x.to_entries()))));
+        result.extend(self.other.iter().cloned());
+        return result
+    }
+}
+
+impl EmblemInstance {
+    /// Parses an instance, keeping the default of the fields that aren't plain numbers (e.g. script variables)
+    pub fn from_entries (entries: &[(String, ScriptValue)]) -> Self {
+        let mut this = Self::default();
+        for (key, value) in entries {
+            match key.as_str() {
+                "position" => this.position = floats(value).unwrap_or(this.position),
+                "scale" => this.scale = floats(value).unwrap_or(this.scale),
+                "rotation" => this.rotation = value.as_str().and_then(|x| x.parse().ok()).unwrap_or(this.rotation),
+                "depth" => this.depth = value.as_str().and_then(|x| x.parse().ok()).unwrap_or(this.depth),
+                _ => {}
+            }
+        }
+        return this
+    }
+
+    pub fn to_entries (&self) -> Vec<(String, ScriptValue)> {
+        let default = Self::default();
+        let mut result = Vec::new();
+        if self.position != default.position {
+            result.push(("position".to_string(), float_list(&self.position)));
+        }
+        if self.scale != default.scale {
+            result.push(("scale".to_string(), float_list(&self.scale)));
+        }
+        if self.rotation != default.rotation {
+            result.push(("rotation".to_string(), ScriptValue::Scalar(self.rotation.to_string())));
+        }
+        if self.depth != default.depth {
+            result.push(("depth".to_string(), ScriptValue::Scalar(self.depth.to_string())));
+        }
+        return result
+    }
+}
+
+impl SubCoatOfArms {
+    pub fn from_entries (entries: Vec<(String, ScriptValue)>) -> Self {
+        let mut this = Self::default();
+        for (key, value) in entries {
+            if let Some(i) = color_index(&key) {
+                this.colors[i] = Some(value);
+                continue
+            }
+
+            match (key.as_str(), value) {
+                ("parent", ScriptValue::Scalar(x)) => this.parent = x,
+                ("instance", ScriptValue::Block(x)) => this.instances.push(SubInstance::from_entries(&x)),
+                (_, value) => this.other.push((key.clone(), value))
+            }
+        }
+        return this
+    }
+
+    pub fn to_entries (&self) -> Vec<(String, ScriptValue)> {
+        let mut result = vec![("parent".to_string(), ScriptValue::Scalar(self.parent.clone()))];
+        push_colors(&mut result, &self.colors);
+        result.extend(self.instances.iter().map(|x| ("instance".to_string(), ScriptValue::Block(x.to_entries()))));
+        result.extend(self.other.iter().cloned());
+        return result
+    }
+}
+
+impl SubInstance {
+    pub fn from_entries (entries: &[(String, ScriptValue)]) -> Self {
+        let mut this = Self::default();
+        for (key, value) in entries {
+            match key.as_str() {
+                "offset" => this.offset = floats(value).unwrap_or(this.offset),
+                "scale" => this.scale = floats(value).unwrap_or(this.scale),
+                _ => {}
+            }
+        }
+        return this
+    }
+
+    pub fn to_entries (&self) -> Vec<(String, ScriptValue)> {
+        return vec![
+            ("offset".to_string(), float_list(&self.offset)),
+            ("scale".to_string(), float_list(&self.scale))
+        ]
+    }
+}
+
+impl Serialize for CoatOfArms {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> where S: serde::Serializer {
+        return VecMap(&self.to_entries()).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for CoatOfArms {
+    #[inline]
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error> where D: serde::Deserializer<'de> {
+        let entries = crate::utils::serde_vec_map::deserialize::<'de, String, ScriptValue, _>(deserializer)?;
+        return Ok(Self::from_entries(entries))
+    }
+}
+
+#[inline]
+fn color_index (key: &str) -> Option<usize> {
+    return match key {
+        "color1" => Some(0),
+        "color2" => Some(1),
+        "color3" => Some(2),
+        _ => None
+    }
+}
+
+#[inline]
+fn push_colors (entries: &mut Vec<(String, ScriptValue)>, colors: &CoaColors) {
+    for (i, color) in colors.iter().enumerate() {
+        if let Some(color) = color {
+            entries.push((format!("color{}", i + 1), color.clone()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{round_trip, ScriptValue};
+    use super::CoatOfArms;
+
+    #[test]
+    fn emblems_round_trip () {
+        let coats = round_trip::<String, CoatOfArms>(b"GBR = {
+            pattern = \"pattern_solid.dds\"
+            color1 = \"blue\"
+            color2 = rgb { 255 255 255 }
+            colored_emblem = {
+                texture = \"ce_cross.dds\"
+                color1 = \"red\"
+                instance = { scale = { 0.2 1.0 } }
+                instance = { scale = { 1.0 0.2 } rotation = 90 }
+            }
+            colored_emblem = { texture = \"ce_star.dds\" }
+            sub = { parent = \"GBR_canton\" instance = { offset = { 0.0 0.0 } scale = { 0.5 0.5 } } }
+        }");
+
+        let coat = &coats["GBR"];
+        assert_eq!(coat.pattern.as_deref(), Some("pattern_solid.dds"));
+        assert_eq!(coat.colors[0], Some(ScriptValue::Scalar("blue".to_string())));
+        assert_eq!(coat.colored_emblems.len(), 2);
+        assert_eq!(coat.colored_emblems[0].instances.len(), 2);
+        assert_eq!(coat.colored_emblems[0].instances[1].rotation, 90.0);
+        assert!(coat.colored_emblems[1].instances.is_empty());
+        assert_eq!(coat.subs[0].parent, "GBR_canton");
+        assert_eq!(coat.subs[0].instances[0].scale, [0.5, 0.5]);
+        assert!(coat.other.is_empty());
+    }
+}
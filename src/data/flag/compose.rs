@@ -0,0 +1,283 @@
+use std::{path::{Path, PathBuf}, collections::{BTreeMap, BTreeSet, HashMap}, ops::Range};
+use eframe::epaint::{Color32, Hsva};
+use image::{RgbaImage, Rgba, imageops::FilterType};
+use crate::data::{dds, Color, GameLayers, ScriptValue};
+use super::{CoatOfArms, EmblemInstance, SubInstance, floats};
+
+/// Size flags are previewed at, with the 3:2 ratio of the game's flags
+pub const FLAG_SIZE: [u32; 2] = [192, 128];
+/// Sub coats of arms drawn at most inside each other, which stops cycles between them
+const MAX_DEPTH: usize = 4;
+/// Flags are drawn this many times bigger and then scaled down, to smooth the edges of the emblems
+const SUPERSAMPLING: u32 = 2;
+const DEFAULT_COLORS: [Color32; 3] = [Color32::BLACK, Color32::WHITE, Color32::WHITE];
+
+/// Draws coats of arms on the CPU, away from the game data so it can run on the background
+#[derive(Debug, Clone, Default)]
+pub struct FlagComposer {
+    coats: BTreeMap<String, CoatOfArms>,
+    colors: BTreeMap<String, Color32>,
+    /// Textures to decode, by path relative to the game's root and file of the topmost layer
+    files: Vec<(PathBuf, PathBuf)>,
+    textures: HashMap<PathBuf, RgbaImage>
+}
+
+/// Pixel area a coat of arms is drawn on
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Area {
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32
+}
+
+impl FlagComposer {
+    /// Collects `coa` and every coat of arms it includes, along with the colors and textures they need
+    pub fn new (coa: &str, coats: &BTreeMap<String, CoatOfArms>, named_colors: &BTreeMap<String, Color>, layers: &GameLayers) -> Self {
+        let mut this = Self {
+            colors: named_colors.iter().map(|(name, color)| (name.clone(), (*color).into())).collect(),
+            ..Default::default()
+        };
+
+        let mut pending = vec![(coa.to_string(), 0)];
+        while let Some((name, depth)) = pending.pop() {
+            if depth > MAX_DEPTH || this.coats.contains_key(&name) {
+                continue
+            }
+            if let Some(coat) = coats.get(&name) {
+                pending.extend(coat.subs.iter().map(|x| (x.parent.clone(), depth + 1)));
+                this.coats.insert(name, coat.clone());
+            }
+        }
+
+        let textures = this.coats.values().flat_map(CoatOfArms::textures).collect::<BTreeSet<_>>();
+        this.files = textures.into_iter()
+            .map(|path| {
+                let file = layers.resolve(&path);
+                (path, file)
+            })
+            .collect();
+
+        return this
+    }
+
+    /// Decodes every texture the coats of arms use. Textures that can't be decoded (e.g. missing files) are left out of the flag
+    pub async fn load_textures (&mut self) {
+        let files = std::mem::take(&mut self.files);
+        let decoded = futures::future::join_all(files.iter().map(|(_, file)| dds::from_path(file))).await;
+        for ((path, _), image) in files.into_iter().zip(decoded) {
+            if let Ok(image) = image {
+                self.textures.insert(path, image);
+            }
+        }
+    }
+
+    /// Draws `coa` as a flag of `width` by `height` pixels
+    pub fn compose (&self, coa: &str, width: u32, height: u32) -> RgbaImage {
+        let mut image = RgbaImage::new(width * SUPERSAMPLING, height * SUPERSAMPLING);
+        let area = Area { x: 0.0, y: 0.0, width: image.width() as f32, height: image.height() as f32 };
+        self.draw(&mut image, coa, area, &[None, None, None], 0);
+        return image::imageops::resize(&image, width, height, FilterType::Triangle)
+    }
+
+    /// Color of a coat of arms: either a named color or a literal one (e.g. `rgb { 255 0 0 }`, `hsv { 0.5 1 1 }`)
+    pub fn color (&self, value: &ScriptValue) -> Option<Color32> {
+        let list = match value {
+            ScriptValue::Scalar(name) => return self.colors.get(name).copied(),
+            ScriptValue::List(x) => x,
+            ScriptValue::Block(_) | ScriptValue::Compare(..) => return None
+        };
+
+        let (kind, [a, b, c]) = match list.as_slice() {
+            [ScriptValue::Scalar(kind), values] => (kind.as_str(), floats::<3>(values)?),
+            _ => ("", floats::<3>(value)?)
+        };
+
+        return match kind {
+            "rgb" => Some(Color32::from_rgb(a as u8, b as u8, c as u8)),
+            "hsv" => Some(Hsva::new(a, b, c, 1.0).into()),
+            "hsv360" => Some(Hsva::new(a / 360.0, b / 100.0, c / 100.0, 1.0).into()),
+            "" => Some(Color32::from_rgb((a * 255.0) as u8, (b * 255.0) as u8, (c * 255.0) as u8)),
+            _ => None
+        }
+    }
+
+    /// Draws the pattern, the emblems and the sub coats of arms of `coa`, in that order. `overrides` replace the coat's own colors
+    fn draw (&self, image: &mut RgbaImage, coa: &str, area: Area, overrides: &[Option<Color32>; 3], depth: usize) {
+        let coat = match self.coats.get(coa) {
+            Some(x) => x,
+            None => return
+        };
+
+        let colors: [Color32; 3] = std::array::from_fn(|i| overrides[i]
+            .or_else(|| coat.colors[i].as_ref().and_then(|x| self.color(x)))
+            .unwrap_or(DEFAULT_COLORS[i]));
+
+        match coat.pattern.as_ref().and_then(|x| self.textures.get(&Path::new(CoatOfArms::PATTERNS).join(x))) {
+            Some(texture) => fill(image, area, |u, v| tint(sample(texture, u, v), &colors)),
+            None => fill(image, area, |_, _| tint(Rgba([255, 0, 0, 255]), &colors))
+        }
+
+        let mut emblems = Vec::new();
+        for emblem in coat.colored_emblems.iter() {
+            let texture = match self.textures.get(&Path::new(CoatOfArms::COLORED_EMBLEMS).join(&emblem.texture)) {
+                Some(x) => x,
+                None => continue
+            };
+
+            let tints: [Color32; 3] = std::array::from_fn(|i| emblem.colors[i].as_ref().and_then(|x| self.color(x)).unwrap_or(colors[i]));
+            emblems.extend(instances(&emblem.instances).into_iter().map(|x| (x, texture, Some(tints))));
+        }
+
+        for emblem in coat.textured_emblems.iter() {
+            if let Some(texture) = self.textures.get(&Path::new(CoatOfArms::TEXTURED_EMBLEMS).join(&emblem.texture)) {
+                emblems.extend(instances(&emblem.instances).into_iter().map(|x| (x, texture, None)));
+            }
+        }
+
+        // higher depths are further back
+        emblems.sort_by(|(lhs, ..), (rhs, ..)| rhs.depth.total_cmp(&lhs.depth));
+        for (instance, texture, tints) in emblems {
+            draw_emblem(image, area, &instance, texture, |texel| match tints {
+                Some(ref tints) => tint(texel, tints),
+                None => texel.0.map(|x| x as f32 / 255.0)
+            });
+        }
+
+        if depth >= MAX_DEPTH {
+            return
+        }
+
+        for sub in coat.subs.iter() {
+            let overrides = std::array::from_fn(|i| sub.colors[i].as_ref().and_then(|x| self.color(x)));
+            for instance in instances::<SubInstance>(&sub.instances) {
+                let sub_area = Area {
+                    x: area.x + instance.offset[0] * area.width,
+                    y: area.y + instance.offset[1] * area.height,
+                    width: instance.scale[0] * area.width,
+                    height: instance.scale[1] * area.height
+                };
+                self.draw(image, &sub.parent, sub_area, &overrides, depth + 1);
+            }
+        }
+    }
+}
+
+/// Instances to draw, which is a single default one when none are given
+#[inline]
+fn instances<T: Copy + Default> (list: &[T]) -> Vec<T> {
+    return match list.is_empty() {
+        true => vec![T::default()],
+        false => list.to_vec()
+    }
+}
+
+/// Draws every pixel of `area` with the color `f` gives to its relative coordinates
+fn fill (image: &mut RgbaImage, area: Area, f: impl Fn(f32, f32) -> [f32; 4]) {
+    for y in pixels(area.y, area.y + area.height, image.height()) {
+        for x in pixels(area.x, area.x + area.width, image.width()) {
+            let u = (x as f32 + 0.5 - area.x) / area.width;
+            let v = (y as f32 + 0.5 - area.y) / area.height;
+            blend(image.get_pixel_mut(x, y), f(u, v));
+        }
+    }
+}
+
+/// Draws `texture` rotated and scaled by `instance`, clipped to `area`
+fn draw_emblem (image: &mut RgbaImage, area: Area, instance: &EmblemInstance, texture: &RgbaImage, color: impl Fn(Rgba<u8>) -> [f32; 4]) {
+    let center = [area.x + instance.position[0] * area.width, area.y + instance.position[1] * area.height];
+    let size = [instance.scale[0] * area.width, instance.scale[1] * area.height];
+    if size[0] == 0.0 || size[1] == 0.0 {
+        return
+    }
+
+    let (sin, cos) = instance.rotation.to_radians().sin_cos();
+    let radius = 0.5 * size[0].hypot(size[1]);
+    let rows = pixels((center[1] - radius).max(area.y), (center[1] + radius).min(area.y + area.height), image.height());
+    let columns = pixels((center[0] - radius).max(area.x), (center[0] + radius).min(area.x + area.width), image.width());
+
+    for y in rows {
+        for x in columns.clone() {
+            let dx = x as f32 + 0.5 - center[0];
+            let dy = y as f32 + 0.5 - center[1];
+
+            // undo the rotation, and the scale (mirroring the emblem if negative)
+            let u = (dx * cos + dy * sin) / size[0] + 0.5;
+            let v = (dy * cos - dx * sin) / size[1] + 0.5;
+            if (0.0..1.0).contains(&u) && (0.0..1.0).contains(&v) {
+                blend(image.get_pixel_mut(x, y), color(sample(texture, u, v)));
+            }
+        }
+    }
+}
+
+/// Pixels between `from` and `to`, within an image of `len` pixels
+#[inline]
+fn pixels (from: f32, to: f32, len: u32) -> Range<u32> {
+    let start = (from.floor().max(0.0) as u32).min(len);
+    let end = (to.ceil().max(0.0) as u32).min(len);
+    return start..end.max(start)
+}
+
+#[inline]
+fn sample (texture: &RgbaImage, u: f32, v: f32) -> Rgba<u8> {
+    let x = ((u * texture.width() as f32) as u32).min(texture.width().saturating_sub(1));
+    let y = ((v * texture.height() as f32) as u32).min(texture.height().saturating_sub(1));
+    return *texture.get_pixel(x, y)
+}
+
+/// Colors a mask texel, where red is the first color, yellow the second and white the third
+#[inline]
+fn tint (texel: Rgba<u8>, colors: &[Color32; 3]) -> [f32; 4] {
+    let green = texel[1] as f32 / 255.0;
+    let blue = texel[2] as f32 / 255.0;
+    let channel = |f: fn(&Color32) -> u8| {
+        let [first, second, third] = colors.map(|x| f(&x) as f32 / 255.0);
+        let mixed = first + (second - first) * green;
+        mixed + (third - mixed) * blue
+    };
+    return [channel(Color32::r), channel(Color32::g), channel(Color32::b), texel[3] as f32 / 255.0]
+}
+
+/// Draws a color with straight alpha over `pixel`
+#[inline]
+fn blend (pixel: &mut Rgba<u8>, [red, green, blue, alpha]: [f32; 4]) {
+    if alpha <= 0.0 {
+        return
+    }
+
+    let below = pixel[3] as f32 / 255.0 * (1.0 - alpha);
+    let result = alpha + below;
+    for (channel, value) in pixel.0.iter_mut().zip([red, green, blue]) {
+        let color = (value * alpha + *channel as f32 / 255.0 * below) / result;
+        *channel = (color * 255.0).round() as u8;
+    }
+    pixel[3] = (result * 255.0).round() as u8;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use eframe::epaint::Color32;
+    use crate::data::{parse_slice, Color, GameLayers, GamePaths, flag::CoatOfArms};
+    use super::FlagComposer;
+
+    #[test]
+    fn draws_colors_and_subs_without_textures () {
+        let data = b"flag = {
+            color1 = \"blue\"
+            sub = { parent = canton color1 = rgb { 255 0 0 } instance = { scale = { 0.5 0.5 } } }
+        }
+        canton = { color1 = \"blue\" }";
+
+        let coats = parse_slice::<BTreeMap<String, CoatOfArms>>(data).unwrap();
+        let named = BTreeMap::from([("blue".to_string(), Color::from(Color32::BLUE))]);
+        let layers = GameLayers::new(GamePaths::new("."), std::iter::empty());
+
+        let composer = FlagComposer::new("flag", &coats, &named, &layers);
+        let image = composer.compose("flag", 30, 20);
+        assert_eq!(image.dimensions(), (30, 20));
+        assert_eq!(image.get_pixel(2, 2).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(27, 17).0, [0, 0, 255, 255]);
+    }
+}
@@ -0,0 +1,120 @@
+use std::path::Path;
+use serde::{Serialize, Deserialize};
+use crate::{Result, utils::serde_vec_map::VecMap, data::{GameLayers, Loaded, ScriptValue}};
+use super::parse_entries;
+
+/// Flags a country can have (`flag_definition = { ... }`), of which the game uses the one with the highest priority whose trigger is met
+#[derive(Debug, Clone, PartialEq, Default)]
+#[non_exhaustive]
+pub struct FlagDefinitionList {
+    pub definitions: Vec<FlagDefinition>,
+    pub other: Vec<(String, ScriptValue)>
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+#[non_exhaustive]
+pub struct FlagDefinition {
+    /// Coat of arms drawn as the flag
+    pub coa: String,
+    /// Coat of arms that the country's subjects show on their canton
+    pub subject_canton: Option<String>,
+    pub priority: i32,
+    /// Conditions for the flag to be used. Flags without one can always be used
+    pub trigger: Option<ScriptValue>,
+    pub other: Vec<(String, ScriptValue)>
+}
+
+impl FlagDefinitionList {
+    pub const PATH: &'static str = "common/flag_definitions";
+
+    /// Index of the flag the country starts with when no trigger is met: the one with the highest priority among the unconditional ones
+    #[inline]
+    pub fn default_flag (&self) -> Option<usize> {
+        return self.definitions.iter()
+            .enumerate()
+            .filter(|(_, x)| x.trigger.is_none())
+            .max_by_key(|(_, x)| x.priority)
+            .map(|(i, _)| i)
+    }
+
+    pub fn from_entries (entries: Vec<(String, ScriptValue)>) -> Self {
+        let mut this = Self::default();
+        for (key, value) in entries {
+            match (key.as_str(), value) {
+                ("flag_definition", ScriptValue::Block(x)) => this.definitions.push(FlagDefinition::from_entries(x)),
+                (_, value) => this.other.push((key.clone(), value))
+            }
+        }
+        return this
+    }
+
+    pub fn to_entries (&self) -> Vec<(String, ScriptValue)> {
+        let mut result = self.definitions.iter()
+            .map(|x| ("flag_definition".to_string(), ScriptValue::Block(x.to_entries())))
+            .collect::<Vec<_>>();
+        result.extend(self.other.iter().cloned());
+        return result
+    }
+
+    #[inline]
+    pub async fn from_path (path: impl AsRef<Path>) -> Result<Vec<(String, Self)>> {
+        let entries = parse_entries(path).await?;
+        return Ok(entries.into_iter()
+            .filter_map(|(key, value)| match value {
+                ScriptValue::Block(x) => Some((key, Self::from_entries(x))),
+                _ => None
+            })
+            .collect())
+    }
+
+    #[inline]
+    pub async fn from_game (game: &GameLayers) -> Result<Loaded<String, Self>> {
+        return game.load(Self::PATH, Self::from_path).await
+    }
+}
+
+impl FlagDefinition {
+    pub fn from_entries (entries: Vec<(String, ScriptValue)>) -> Self {
+        let mut this = Self::default();
+        for (key, value) in entries {
+            match (key.as_str(), value) {
+                ("coa", ScriptValue::Scalar(x)) => this.coa = x,
+                ("subject_canton", ScriptValue::Scalar(x)) => this.subject_canton = Some(x),
+                ("priority", ScriptValue::Scalar(x)) if x.parse::<i32>().is_ok() => this.priority = x.parse().unwrap(),
+                ("trigger", value) => this.trigger = Some(value),
+                (_, value) => this.other.push((key.clone(), value))
+            }
+        }
+        return this
+    }
+
+    pub fn to_entries (&self) -> Vec<(String, ScriptValue)> {
+        let mut result = vec![("coa".to_string(), ScriptValue::Scalar(self.coa.clone()))];
+        if let Some(ref canton) = self.subject_canton {
+            result.push(("subject_canton".to_string(), ScriptValue::Scalar(canton.clone())));
+        }
+        if self.priority != 0 {
+            result.push(("priority".to_string(), ScriptValue::Scalar(self.priority.to_string())));
+        }
+        if let Some(ref trigger) = self.trigger {
+            result.push(("trigger".to_string(), trigger.clone()));
+        }
+        result.extend(self.other.iter().cloned());
+        return result
+    }
+}
+
+impl Serialize for FlagDefinitionList {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> where S: serde::Serializer {
+        return VecMap(&self.to_entries()).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for FlagDefinitionList {
+    #[inline]
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error> where D: serde::Deserializer<'de> {
+        let entries = crate::utils::serde_vec_map::deserialize::<'de, String, ScriptValue, _>(deserializer)?;
+        return Ok(Self::from_entries(entries))
+    }
+}
@@ -0,0 +1,39 @@
+use std::{path::Path, collections::BTreeMap};
+use crate::{Result, utils::refcell::RefCell, data::{parse_path, Color, ScriptValue}};
+
+flat_mod! { coat_of_arms, definition, named_color, compose }
+
+#[derive(Debug)]
+pub struct GameFlags {
+    pub coats_of_arms: RefCell<BTreeMap<String, CoatOfArms>>,
+    /// Flags that every country can have, by country tag
+    pub definitions: RefCell<BTreeMap<String, FlagDefinitionList>>,
+    pub named_colors: RefCell<BTreeMap<String, Color>>
+}
+
+/// Top-level entries of a script file, skipping its variables (e.g. `@canton_scale = 0.5`)
+async fn parse_entries (path: impl AsRef<Path>) -> Result<Vec<(String, ScriptValue)>> {
+    return match parse_path::<ScriptValue>(path).await? {
+        ScriptValue::Block(entries) => Ok(entries.into_iter().filter(|(key, _)| !key.starts_with('@')).collect()),
+        _ => Ok(Vec::new())
+    }
+}
+
+/// Parses a list of numbers (e.g. `{ 0.5 0.5 }`)
+fn floats<const N: usize> (value: &ScriptValue) -> Option<[f32; N]> {
+    let list = match value {
+        ScriptValue::List(x) if x.len() == N => x,
+        _ => return None
+    };
+
+    let mut result = [0.0; N];
+    for (slot, value) in result.iter_mut().zip(list) {
+        *slot = value.as_str()?.parse().ok()?;
+    }
+    return Some(result)
+}
+
+#[inline]
+fn float_list (values: &[f32]) -> ScriptValue {
+    return ScriptValue::List(values.iter().map(|x| ScriptValue::Scalar(x.to_string())).collect())
+}
@@ -0,0 +1,24 @@
+use std::{path::Path, collections::HashMap};
+use jomini::JominiDeserialize;
+use crate::{Result, data::{parse_path, Color, GameLayers, Loaded}};
+
+/// Contents of a named colors file (`colors = { red = rgb { 255 0 0 } }`), whose colors coats of arms refer to by name
+#[derive(Debug, Clone, PartialEq, Default, JominiDeserialize)]
+pub struct NamedColors {
+    #[jomini(default)]
+    pub colors: HashMap<String, Color>
+}
+
+impl NamedColors {
+    pub const PATH: &'static str = "common/named_colors";
+
+    #[inline]
+    pub async fn from_path (path: impl AsRef<Path>) -> Result<HashMap<String, Color>> {
+        return Ok(parse_path::<Self>(path).await?.colors)
+    }
+
+    #[inline]
+    pub async fn from_game (game: &GameLayers) -> Result<Loaded<String, Color>> {
+        return game.load(Self::PATH, Self::from_path).await
+    }
+}
@@ -1,3 +1,4 @@
+use std::fmt::Display;
 use serde::{Serialize, Deserialize};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
@@ -67,6 +68,19 @@ impl PartialEq<Ident> for str {
     }
 }
 
+impl Display for Ident {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let value = &self.value;
+        match self.kind {
+            IdentKind::Country => write!(f, "c:{value}"),
+            IdentKind::State => write!(f, "s:{value}"),
+            IdentKind::RegionState => write!(f, "region_state:{value}"),
+            IdentKind::Unknown => f.write_str(value)
+        }
+    }
+}
+
 impl Serialize for Ident {
     #[inline]
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: serde::Serializer {
@@ -0,0 +1,272 @@
+use std::collections::HashSet;
+use serde::{Serialize, ser::{self, Error as _}};
+use serde_json::{Value, Map, Error};
+
+/// Converts `value` to JSON. Unlike [`serde_json::to_value`], keys that are repeated on a map
+/// (e.g. every `create_pop` of a region) are written once, with an array of all their values
+#[inline]
+pub fn to_value<T: ?Sized + Serialize> (value: &T) -> Result<Value, Error> {
+    return value.serialize(Serializer)
+}
+
+struct Serializer;
+
+macro_rules! forward {
+    ($($f:ident: $t:ty),+) => {
+        $(
+            #[inline]
+            fn $f (self, v: $t) -> Result<Value, Error> {
+                return ser::Serializer::$f(serde_json::value::Serializer, v)
+            }
+        )+
+    };
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = Value;
+    type Error = Error;
+    type SerializeSeq = Seq;
+    type SerializeTuple = Seq;
+    type SerializeTupleStruct = Seq;
+    type SerializeTupleVariant = Variant<Seq>;
+    type SerializeMap = Entries;
+    type SerializeStruct = Entries;
+    type SerializeStructVariant = Variant<Entries>;
+
+    forward! {
+        serialize_bool: bool,
+        serialize_i8: i8, serialize_i16: i16, serialize_i32: i32, serialize_i64: i64,
+        serialize_u8: u8, serialize_u16: u16, serialize_u32: u32, serialize_u64: u64,
+        serialize_f32: f32, serialize_f64: f64,
+        serialize_char: char, serialize_str: &str, serialize_bytes: &[u8]
+    }
+
+    #[inline]
+    fn serialize_none(self) -> Result<Value, Error> {
+        return Ok(Value::Null)
+    }
+
+    #[inline]
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Value, Error> {
+        return value.serialize(self)
+    }
+
+    #[inline]
+    fn serialize_unit(self) -> Result<Value, Error> {
+        return Ok(Value::Null)
+    }
+
+    #[inline]
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, Error> {
+        return Ok(Value::Null)
+    }
+
+    #[inline]
+    fn serialize_unit_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str) -> Result<Value, Error> {
+        return Ok(Value::String(variant.to_string()))
+    }
+
+    #[inline]
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _name: &'static str, value: &T) -> Result<Value, Error> {
+        return value.serialize(self)
+    }
+
+    #[inline]
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, _name: &'static str, _variant_index: u32, variant: &'static str, value: &T) -> Result<Value, Error> {
+        let mut map = Map::new();
+        map.insert(variant.to_string(), to_value(value)?);
+        return Ok(Value::Object(map))
+    }
+
+    #[inline]
+    fn serialize_seq(self, len: Option<usize>) -> Result<Seq, Error> {
+        return Ok(Seq(Vec::with_capacity(len.unwrap_or_default())))
+    }
+
+    #[inline]
+    fn serialize_tuple(self, len: usize) -> Result<Seq, Error> {
+        return self.serialize_seq(Some(len))
+    }
+
+    #[inline]
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<Seq, Error> {
+        return self.serialize_seq(Some(len))
+    }
+
+    #[inline]
+    fn serialize_tuple_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str, len: usize) -> Result<Variant<Seq>, Error> {
+        return Ok(Variant { variant, inner: Seq(Vec::with_capacity(len)) })
+    }
+
+    #[inline]
+    fn serialize_map(self, _len: Option<usize>) -> Result<Entries, Error> {
+        return Ok(Entries::default())
+    }
+
+    #[inline]
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Entries, Error> {
+        return Ok(Entries::default())
+    }
+
+    #[inline]
+    fn serialize_struct_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str, _len: usize) -> Result<Variant<Entries>, Error> {
+        return Ok(Variant { variant, inner: Entries::default() })
+    }
+}
+
+struct Seq (Vec<Value>);
+
+impl ser::SerializeSeq for Seq {
+    type Ok = Value;
+    type Error = Error;
+
+    #[inline]
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.0.push(to_value(value)?);
+        return Ok(())
+    }
+
+    #[inline]
+    fn end(self) -> Result<Value, Error> {
+        return Ok(Value::Array(self.0))
+    }
+}
+
+impl ser::SerializeTuple for Seq {
+    type Ok = Value;
+    type Error = Error;
+
+    #[inline]
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        return ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    #[inline]
+    fn end(self) -> Result<Value, Error> {
+        return ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for Seq {
+    type Ok = Value;
+    type Error = Error;
+
+    #[inline]
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        return ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    #[inline]
+    fn end(self) -> Result<Value, Error> {
+        return ser::SerializeSeq::end(self)
+    }
+}
+
+#[derive(Default)]
+struct Entries {
+    map: Map<String, Value>,
+    /// Keys whose value is already the array of all of their values
+    repeated: HashSet<String>,
+    key: Option<String>
+}
+
+impl Entries {
+    fn insert (&mut self, key: String, value: Value) {
+        match self.map.get_mut(&key) {
+            Some(Value::Array(values)) if self.repeated.contains(&key) => values.push(value),
+            Some(first) => {
+                *first = Value::Array(vec![first.take(), value]);
+                self.repeated.insert(key);
+            },
+            None => {
+                self.map.insert(key, value);
+            }
+        }
+    }
+}
+
+impl ser::SerializeMap for Entries {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Error> {
+        self.key = Some(match to_value(key)? {
+            Value::String(x) => x,
+            x @ (Value::Number(_) | Value::Bool(_)) => x.to_string(),
+            _ => return Err(Error::custom("map keys must be scalar values"))
+        });
+        return Ok(())
+    }
+
+    #[inline]
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self.key.take().ok_or_else(|| Error::custom("value serialized before its key"))?;
+        self.insert(key, to_value(value)?);
+        return Ok(())
+    }
+
+    #[inline]
+    fn end(self) -> Result<Value, Error> {
+        return Ok(Value::Object(self.map))
+    }
+}
+
+impl ser::SerializeStruct for Entries {
+    type Ok = Value;
+    type Error = Error;
+
+    #[inline]
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        self.insert(key.to_string(), to_value(value)?);
+        return Ok(())
+    }
+
+    #[inline]
+    fn end(self) -> Result<Value, Error> {
+        return Ok(Value::Object(self.map))
+    }
+}
+
+struct Variant<T> {
+    variant: &'static str,
+    inner: T
+}
+
+impl<T> Variant<T> {
+    #[inline]
+    fn wrap (variant: &'static str, value: Value) -> Value {
+        let mut map = Map::new();
+        map.insert(variant.to_string(), value);
+        return Value::Object(map)
+    }
+}
+
+impl ser::SerializeTupleVariant for Variant<Seq> {
+    type Ok = Value;
+    type Error = Error;
+
+    #[inline]
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        return ser::SerializeSeq::serialize_element(&mut self.inner, value)
+    }
+
+    #[inline]
+    fn end(self) -> Result<Value, Error> {
+        return Ok(Self::wrap(self.variant, ser::SerializeSeq::end(self.inner)?))
+    }
+}
+
+impl ser::SerializeStructVariant for Variant<Entries> {
+    type Ok = Value;
+    type Error = Error;
+
+    #[inline]
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        return ser::SerializeStruct::serialize_field(&mut self.inner, key, value)
+    }
+
+    #[inline]
+    fn end(self) -> Result<Value, Error> {
+        return Ok(Self::wrap(self.variant, ser::SerializeStruct::end(self.inner)?))
+    }
+}
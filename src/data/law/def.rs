@@ -0,0 +1,171 @@
+use std::{path::Path, collections::{HashMap, BTreeMap}};
+use eframe::egui::{Ui, CollapsingHeader, RichText, Grid};
+use serde::{Serialize, Deserialize};
+use crate::{Result, utils::{list::ListEntry, attribute_combo, attribute_num, attribute_list, serde_vec_map::VecMap}, data::{parse_path, write_to_path, Game, GameLayers, Loaded, ScriptValue}};
+
+#[derive(Debug, Clone, PartialEq, Default)]
+#[non_exhaustive]
+pub struct Law {
+    pub group: String,
+    pub icon: Option<Box<Path>>,
+    /// how progressive the law is compared to the others of its group, used to rank movements and ideologies
+    pub progressiveness: i32,
+    pub unlocking_technologies: Box<[String]>,
+    /// at least one of these laws must be active to enact this one
+    pub unlocking_laws: Box<[String]>,
+    /// this law can't be enacted while any of these is active
+    pub disallowing_laws: Box<[String]>,
+    pub modifier: Vec<(String, ScriptValue)>,
+    pub other: Vec<(String, ScriptValue)>
+}
+
+impl Law {
+    pub const PATH: &'static str = "common/laws";
+
+    /// Whether `lhs` and `rhs` can't be active at the same time, because either of them disallows the other
+    #[inline]
+    pub fn excludes ((lhs_name, lhs): (&str, &Self), (rhs_name, rhs): (&str, &Self)) -> bool {
+        return lhs.disallowing_laws.iter().any(|x| x == rhs_name) || rhs.disallowing_laws.iter().any(|x| x == lhs_name)
+    }
+
+    /// Laws of `group`, from least to most progressive
+    pub fn of_group<'a> (laws: &'a BTreeMap<String, Self>, group: &str) -> Vec<(&'a String, &'a Self)> {
+        let mut result = laws.iter().filter(|(_, x)| x.group == group).collect::<Vec<_>>();
+        result.sort_by_key(|(_, x)| x.progressiveness);
+        return result
+    }
+
+    pub fn from_entries (entries: Vec<(String, ScriptValue)>) -> Self {
+        let mut this = Self::default();
+        for (key, value) in entries {
+            match (key.as_str(), value) {
+                ("group", ScriptValue::Scalar(x)) => this.group = x,
+                ("icon", ScriptValue::Scalar(x)) => this.icon = Some(Path::new(&x).into()),
+                ("progressiveness", x) if x.parse::<i32>().is_some() => this.progressiveness = x.parse().unwrap(),
+                ("unlocking_technologies", x) if x.strings().is_some() => this.unlocking_technologies = x.strings().unwrap(),
+                ("unlocking_laws", x) if x.strings().is_some() => this.unlocking_laws = x.strings().unwrap(),
+                ("disallowing_laws", x) if x.strings().is_some() => this.disallowing_laws = x.strings().unwrap(),
+                ("modifier", ScriptValue::Block(x)) => this.modifier = x,
+                (_, value) => this.other.push((key, value))
+            }
+        }
+        return this
+    }
+
+    pub fn to_entries (&self) -> Vec<(String, ScriptValue)> {
+        let mut result = vec![("group".to_string(), ScriptValue::scalar(&self.group))];
+        if let Some(ref icon) = self.icon {
+            result.push(("icon".to_string(), ScriptValue::scalar(icon.display())));
+        }
+        if self.progressiveness != 0 {
+            result.push(("progressiveness".to_string(), ScriptValue::scalar(self.progressiveness)));
+        }
+        for (key, list) in [("unlocking_technologies", &self.unlocking_technologies), ("unlocking_laws", &self.unlocking_laws), ("disallowing_laws", &self.disallowing_laws)] {
+            if !list.is_empty() {
+                result.push((key.to_string(), ScriptValue::list(list.iter())));
+            }
+        }
+        if !self.modifier.is_empty() {
+            result.push(("modifier".to_string(), ScriptValue::Block(self.modifier.clone())));
+        }
+        result.extend(self.other.iter().cloned());
+        return result
+    }
+
+    #[inline]
+    pub async fn from_path (path: impl AsRef<Path>) -> Result<HashMap<String, Self>> {
+        return parse_path(path).await
+    }
+
+    #[inline]
+    pub async fn to_path<'a> (path: impl AsRef<Path>, entries: impl IntoIterator<Item = (&'a String, &'a Self)>) -> Result<()> {
+        let entries = entries.into_iter().collect::<Vec<_>>();
+        return write_to_path(path, &VecMap(&entries)).await
+    }
+
+    #[inline]
+    pub async fn from_game (game: &GameLayers) -> Result<Loaded<String, Self>> {
+        return game.load(Self::PATH, Self::from_path).await
+    }
+}
+
+impl ListEntry for Law {
+    #[inline]
+    fn color (&self) -> Option<eframe::epaint::Color32> {
+        None
+    }
+
+    fn render_info (&mut self, ui: &mut Ui, game: &Game) {
+        let groups = game.laws.groups.borrow();
+        attribute_combo(ui, "Group", &mut self.group, groups.keys().cloned());
+        attribute_num(ui, "Progressiveness", &mut self.progressiveness, None);
+        attribute_list(ui, "Unlocking technologies", self.unlocking_technologies.iter_mut());
+        attribute_list(ui, "Unlocking laws", self.unlocking_laws.iter_mut());
+        attribute_list(ui, "Disallowing laws", self.disallowing_laws.iter_mut());
+
+        CollapsingHeader::new(RichText::new(format!("Modifiers ({})", self.modifier.len())).strong()).show(ui, |ui| {
+            Grid::new("law_modifiers").striped(true).show(ui, |ui| {
+                for (key, value) in self.modifier.iter() {
+                    ui.label(key);
+                    ui.monospace(value.to_string());
+                    ui.end_row();
+                }
+            });
+        });
+    }
+}
+
+impl Serialize for Law {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> where S: serde::Serializer {
+        return VecMap(&self.to_entries()).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Law {
+    #[inline]
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error> where D: serde::Deserializer<'de> {
+        let entries = crate::utils::serde_vec_map::deserialize::<'de, String, ScriptValue, _>(deserializer)?;
+        return Ok(Self::from_entries(entries))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use crate::data::{parse_slice, round_trip, keys};
+    use super::Law;
+
+    #[test]
+    fn exclusions_are_symmetric () {
+        let laws = parse_slice::<BTreeMap<String, Law>>(b"
+            law_monarchy = { group = lawgroup_governance_principles progressiveness = 0 modifier = { country_legitimacy_add = 10 } }
+            law_presidential_republic = { group = lawgroup_governance_principles progressiveness = 50 disallowing_laws = { law_monarchy } }
+            law_council_republic = { group = lawgroup_governance_principles progressiveness = 100 }
+        ").unwrap();
+
+        let get = |name: &'static str| (name, &laws[name]);
+        assert!(Law::excludes(get("law_monarchy"), get("law_presidential_republic")));
+        assert!(Law::excludes(get("law_presidential_republic"), get("law_monarchy")));
+        assert!(!Law::excludes(get("law_monarchy"), get("law_council_republic")));
+
+        let group = Law::of_group(&laws, "lawgroup_governance_principles");
+        assert_eq!(group.iter().map(|(x, _)| x.as_str()).collect::<Vec<_>>(), ["law_monarchy", "law_presidential_republic", "law_council_republic"]);
+        assert_eq!(laws["law_monarchy"].modifier.len(), 1);
+    }
+
+    #[test]
+    fn keeps_unknown_fields () {
+        let laws = round_trip::<String, Law>(b"
+            law_test = {
+                group = lawgroup_test
+                progressiveness = 25
+                modifier = { country_legitimacy_add = 10 }
+                possible = { has_technology_researched = tech_test }
+                on_activate = { add_radicals = { value = 0.1 } }
+            }
+        ");
+
+        assert_eq!(keys(&laws["law_test"].other), ["possible", "on_activate"]);
+    }
+}
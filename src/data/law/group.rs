@@ -0,0 +1,133 @@
+use std::{path::Path, collections::{HashMap, BTreeMap}};
+use eframe::egui::{Ui, RichText, Grid, Color32};
+use serde::{Serialize, Deserialize};
+use crate::{Result, utils::{list::ListEntry, attribute_text, attribute_num, refcell::RefCell, serde_vec_map::VecMap}, data::{parse_path, write_to_path, Game, GameLayers, Loaded, ScriptValue}};
+use super::Law;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+#[non_exhaustive]
+pub struct LawGroup {
+    /// e.g. `power_structure`, `economy` or `human_rights`
+    pub law_group_category: String,
+    pub base_enactment_days: Option<u32>,
+    pub other: Vec<(String, ScriptValue)>
+}
+
+/// Laws of a group, from least to most progressive, along with the ones that exclude each of them.
+/// Finding the exclusions goes through every law, so they're only found again when the group or the laws change
+#[derive(Debug, Default)]
+pub struct GroupLaws {
+    /// Group and generation of the laws the rows were computed for
+    source: Option<(String, u64)>,
+    /// Name, progressiveness and excluded laws of every law of the group
+    rows: Vec<(String, i32, Vec<String>)>
+}
+
+impl LawGroup {
+    pub const PATH: &'static str = "common/law_groups";
+
+    pub fn from_entries (entries: Vec<(String, ScriptValue)>) -> Self {
+        let mut this = Self::default();
+        for (key, value) in entries {
+            match (key.as_str(), value) {
+                ("law_group_category", ScriptValue::Scalar(x)) => this.law_group_category = x,
+                ("base_enactment_days", x) if x.parse::<u32>().is_some() => this.base_enactment_days = x.parse(),
+                (_, value) => this.other.push((key, value))
+            }
+        }
+        return this
+    }
+
+    pub fn to_entries (&self) -> Vec<(String, ScriptValue)> {
+        let mut result = vec![("law_group_category".to_string(), ScriptValue::scalar(&self.law_group_category))];
+        if let Some(days) = self.base_enactment_days {
+            result.push(("base_enactment_days".to_string(), ScriptValue::scalar(days)));
+        }
+        result.extend(self.other.iter().cloned());
+        return result
+    }
+
+    #[inline]
+    pub async fn from_path (path: impl AsRef<Path>) -> Result<HashMap<String, Self>> {
+        return parse_path(path).await
+    }
+
+    #[inline]
+    pub async fn to_path<'a> (path: impl AsRef<Path>, entries: impl IntoIterator<Item = (&'a String, &'a Self)>) -> Result<()> {
+        let entries = entries.into_iter().collect::<Vec<_>>();
+        return write_to_path(path, &VecMap(&entries)).await
+    }
+
+    #[inline]
+    pub async fn from_game (game: &GameLayers) -> Result<Loaded<String, Self>> {
+        return game.load(Self::PATH, Self::from_path).await
+    }
+}
+
+impl ListEntry for LawGroup {
+    #[inline]
+    fn color (&self) -> Option<eframe::epaint::Color32> {
+        None
+    }
+
+    #[inline]
+    fn render_info (&mut self, ui: &mut Ui, game: &Game) {
+        attribute_text(ui, "Category", &mut self.law_group_category);
+        if let Some(ref mut days) = self.base_enactment_days {
+            attribute_num(ui, "Base enactment days", days, None);
+        }
+    }
+}
+
+impl GroupLaws {
+    /// Shows the laws of `group`, finding them again if it or the laws changed since the last call
+    pub fn update (&mut self, ui: &mut Ui, laws: &RefCell<BTreeMap<String, Law>>, group: &str) {
+        let source = (group.to_string(), laws.generation());
+        if self.source.as_ref() != Some(&source) {
+            let laws = laws.borrow();
+            self.rows = Law::of_group(&laws, group).into_iter()
+                .map(|(name, law)| {
+                    let excluded = laws.iter()
+                        .filter(|(other_name, other)| Law::excludes((name.as_str(), law), (other_name.as_str(), *other)))
+                        .map(|(x, _)| x.clone())
+                        .collect();
+                    (name.clone(), law.progressiveness, excluded)
+                })
+                .collect();
+            self.source = Some(source);
+        }
+
+        Grid::new(("group_laws", group)).striped(true).show(ui, |ui| {
+            ui.label(RichText::new("Law").strong());
+            ui.label(RichText::new("Progressiveness").strong());
+            ui.label(RichText::new("Excludes").strong());
+            ui.end_row();
+
+            for (name, progressiveness, excluded) in self.rows.iter() {
+                ui.label(name.as_str());
+                ui.label(progressiveness.to_string());
+
+                match excluded.is_empty() {
+                    true => ui.label(RichText::new("None").weak()),
+                    false => ui.label(RichText::new(excluded.join(", ")).color(Color32::LIGHT_RED))
+                };
+                ui.end_row();
+            }
+        });
+    }
+}
+
+impl Serialize for LawGroup {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> where S: serde::Serializer {
+        return VecMap(&self.to_entries()).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for LawGroup {
+    #[inline]
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error> where D: serde::Deserializer<'de> {
+        let entries = crate::utils::serde_vec_map::deserialize::<'de, String, ScriptValue, _>(deserializer)?;
+        return Ok(Self::from_entries(entries))
+    }
+}
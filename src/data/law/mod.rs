@@ -0,0 +1,10 @@
+use std::{collections::{BTreeMap}};
+use crate::utils::refcell::RefCell;
+
+flat_mod! { def, group }
+
+#[derive(Debug)]
+pub struct GameLaws {
+    pub groups: RefCell<BTreeMap<String, LawGroup>>,
+    pub laws: RefCell<BTreeMap<String, Law>>
+}
@@ -0,0 +1,330 @@
+use std::{path::{PathBuf, Path}, collections::BTreeMap, ffi::OsString, io::ErrorKind};
+use futures::{Future, TryStreamExt};
+use crate::{Result, error::Error, utils::ReadDirStream};
+use super::{GamePaths, Ident, Mod, ModMetadata};
+
+/// A file of one of the layers
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Origin {
+    /// Index of the layer in load order, where `0` is the base game
+    pub layer: usize,
+    pub file: PathBuf
+}
+
+impl Origin {
+    #[inline]
+    pub fn file_name (&self) -> Option<&std::ffi::OsStr> {
+        return self.file.file_name()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layer {
+    pub path: GamePaths,
+    /// `None` for the base game
+    pub metadata: Option<ModMetadata>
+}
+
+impl Layer {
+    #[inline]
+    pub fn name (&self) -> &str {
+        return match self.metadata {
+            Some(ref metadata) => &metadata.name,
+            None => "Base game"
+        }
+    }
+
+    /// Whether this layer hides the contents of `dir` in the layers below it
+    #[inline]
+    pub fn replaces (&self, dir: &str) -> bool {
+        let metadata = match self.metadata {
+            Some(ref metadata) => metadata,
+            None => return false
+        };
+
+        let dir = normalize(dir);
+        return metadata.replace_paths().iter().any(|x| normalize(x) == dir)
+    }
+}
+
+impl From<Mod> for Layer {
+    #[inline]
+    fn from(value: Mod) -> Self {
+        return Self { path: value.path, metadata: Some(value.metadata) }
+    }
+}
+
+/// The base game plus a list of mods, in load order
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameLayers {
+    pub layers: Vec<Layer>
+}
+
+impl GameLayers {
+    #[inline]
+    pub fn new (base: GamePaths, mods: impl IntoIterator<Item = Mod>) -> Self {
+        let mut layers = vec![Layer { path: base, metadata: None }];
+        layers.extend(mods.into_iter().map(Layer::from));
+        return Self { layers }
+    }
+
+    #[inline]
+    pub fn base (&self) -> &GamePaths {
+        return &self.layers[0].path
+    }
+
+    #[inline]
+    pub fn get (&self, layer: usize) -> Option<&Layer> {
+        return self.layers.get(layer)
+    }
+
+    /// Index of the layer rooted at `path`, if any
+    #[inline]
+    pub fn position (&self, path: &Path) -> Option<usize> {
+        return self.layers.iter().position(|x| x.path.game() == path)
+    }
+
+    /// Returns the layers without the one at `index`, keeping the rest in order
+    #[inline]
+    pub fn without (&self, index: usize) -> Self {
+        let mut layers = self.layers.clone();
+        layers.remove(index);
+        return Self { layers }
+    }
+
+    /// Path of the file at `path` (relative to each layer's root) of the topmost layer that has it, or of the base game if none does
+    #[inline]
+    pub fn resolve (&self, path: &Path) -> PathBuf {
+        return self.layers.iter().rev()
+            .map(|x| x.path.game().join(path))
+            .find(|x| x.is_file())
+            .unwrap_or_else(|| self.base().game().join(path))
+    }
+
+    /// Returns the files of `dir` (relative to each layer's root) that will be loaded, in load order.
+    ///
+    /// A file replaces any file with the same name of a previous layer, and layers that list `dir` on their `replace_paths`
+    /// hide every file of the previous ones.
+    pub async fn files (&self, dir: &str) -> Result<Vec<Origin>> {
+        let mut files = BTreeMap::<OsString, Origin>::new();
+        for (layer, info) in self.layers.iter().enumerate() {
+            if info.replaces(dir) {
+                files.clear();
+            }
+
+            let read_dir = match tokio::fs::read_dir(info.path.game().join(dir)).await {
+                Ok(x) => x,
+                // mods only contain the directories they change
+                Err(e) if layer > 0 && e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into())
+            };
+
+            let mut entries = ReadDirStream::new(read_dir);
+            while let Some(entry) = entries.try_next().await? {
+                if entry.metadata().await?.is_file() {
+                    files.insert(entry.file_name(), Origin { layer, file: entry.path() });
+                }
+            }
+        }
+
+        // files are loaded in alphabetical order, regardless of their layer
+        return Ok(files.into_values().collect())
+    }
+
+    /// Like [`files`](Self::files), but also returns the files of the subdirectories of `dir`,
+    /// where a file replaces the one at the same path relative to `dir` of a previous layer.
+    pub async fn files_recursive (&self, dir: &str) -> Result<Vec<Origin>> {
+        let mut files = BTreeMap::<PathBuf, Origin>::new();
+        for (layer, info) in self.layers.iter().enumerate() {
+            if info.replaces(dir) {
+                files.clear();
+            }
+
+            let root = info.path.game().join(dir);
+            let mut pending = vec![root.clone()];
+            while let Some(path) = pending.pop() {
+                let read_dir = match tokio::fs::read_dir(&path).await {
+                    Ok(x) => x,
+                    Err(e) if layer > 0 && e.kind() == ErrorKind::NotFound => continue,
+                    Err(e) => return Err(Error::from(e).with_file(path))
+                };
+
+                let mut entries = ReadDirStream::new(read_dir);
+                while let Some(entry) = entries.try_next().await? {
+                    let metadata = entry.metadata().await?;
+                    if metadata.is_dir() {
+                        pending.push(entry.path());
+                    } else if metadata.is_file() {
+                        let relative = entry.path().strip_prefix(&root).map(Path::to_path_buf).unwrap_or_else(|_| entry.path());
+                        files.insert(relative, Origin { layer, file: entry.path() });
+                    }
+                }
+            }
+        }
+
+        return Ok(files.into_values().collect())
+    }
+
+    /// Parses every file of `dir`, where later keys override earlier ones, recording which file every entry came from.
+    ///
+    /// Files that fail to parse are skipped, and their errors are collected on the result.
+    pub async fn load<K, V, I, F, Fut> (&self, dir: &str, parse: F) -> Result<Loaded<K, V>> where
+        K: Ord + Clone,
+        I: IntoIterator<Item = (K, V)>,
+        F: Fn(PathBuf) -> Fut,
+        Fut: Future<Output = Result<I>>
+    {
+        let files = self.files(dir).await?;
+        let parsed = futures::future::join_all(files.iter().map(|x| parse(x.file.clone()))).await;
+
+        let mut result = Loaded {
+            entries: BTreeMap::new(),
+            origins: BTreeMap::new(),
+            errors: Vec::new()
+        };
+
+        for (origin, parsed) in files.into_iter().zip(parsed) {
+            let parsed = match parsed {
+                Ok(x) => x,
+                Err(e) => {
+                    let file = self.relative(&origin).to_path_buf();
+                    result.errors.push(e.with_file(file));
+                    continue
+                }
+            };
+
+            for (key, value) in parsed {
+                result.origins.insert(key.clone(), origin.clone());
+                result.entries.insert(key, value);
+            }
+        }
+
+        return Ok(result)
+    }
+
+    /// Path of the file relative to the `common` folder of its layer (e.g. `cultures/00_french.txt`)
+    #[inline]
+    pub fn relative<'a> (&self, origin: &'a Origin) -> &'a Path {
+        let layer = match self.get(origin.layer) {
+            Some(x) => x,
+            None => return &origin.file
+        };
+
+        return origin.file.strip_prefix(layer.path.common())
+            .or_else(|_| origin.file.strip_prefix(layer.path.game()))
+            .unwrap_or(&origin.file)
+    }
+}
+
+/// Entries of a directory, merged across layers
+#[derive(Debug)]
+pub struct Loaded<K, V> {
+    pub entries: BTreeMap<K, V>,
+    pub origins: BTreeMap<K, Origin>,
+    /// Errors of the files that couldn't be parsed
+    pub errors: Vec<Error>
+}
+
+/// Layer that every loaded entry came from
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct Origins {
+    pub religions: BTreeMap<String, Origin>,
+    pub cultures: BTreeMap<String, Origin>,
+    pub pop_types: BTreeMap<String, Origin>,
+    pub country_ranks: BTreeMap<String, Origin>,
+    pub country_types: BTreeMap<String, Origin>,
+    pub country_definitions: BTreeMap<Ident, Origin>,
+    pub country_history: BTreeMap<Ident, Origin>,
+    pub state_definitions: BTreeMap<Ident, Origin>,
+    pub state_pops: BTreeMap<Ident, Origin>,
+    pub state_regions: BTreeMap<String, Origin>,
+    pub goods: BTreeMap<String, Origin>,
+    pub buildings: BTreeMap<String, Origin>,
+    pub production_methods: BTreeMap<String, Origin>,
+    pub production_method_groups: BTreeMap<String, Origin>,
+    pub law_groups: BTreeMap<String, Origin>,
+    pub laws: BTreeMap<String, Origin>,
+    pub interest_groups: BTreeMap<String, Origin>,
+    pub ideologies: BTreeMap<String, Origin>,
+    pub movements: BTreeMap<String, Origin>,
+    pub technologies: BTreeMap<String, Origin>,
+    pub coats_of_arms: BTreeMap<String, Origin>,
+    pub flag_definitions: BTreeMap<String, Origin>,
+    pub named_colors: BTreeMap<String, Origin>
+}
+
+#[inline]
+fn normalize (dir: &str) -> String {
+    return dir.trim_matches(|c| c == '/' || c == '\\').replace('\\', "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::{Path, PathBuf}, collections::BTreeMap};
+    use crate::data::{parse_path, GamePaths, Mod, ModMetadata};
+    use super::{GameLayers, Origin};
+
+    const DIR: &str = "common/test";
+
+    async fn write (root: &Path, files: &[(&str, &str)]) {
+        tokio::fs::create_dir_all(root.join(DIR)).await.unwrap();
+        for (name, data) in files {
+            tokio::fs::write(root.join(DIR).join(name), data).await.unwrap();
+        }
+    }
+
+    /// A game whose `DIR` has `a.txt` (`x = 1 y = 1`) and `b.txt` (`z = 1`), with a mod that replaces `a.txt` and adds `c.txt`
+    async fn layers (root: &Path) -> (GameLayers, PathBuf, PathBuf) {
+        let _ = tokio::fs::remove_dir_all(root).await;
+        let (game, project) = (root.join("game"), root.join("mod"));
+        write(&game, &[("a.txt", "x = 1 y = 1"), ("b.txt", "z = 1")]).await;
+        write(&project, &[("a.txt", "x = 2"), ("c.txt", "y = 3")]).await;
+
+        let layers = GameLayers::new(GamePaths::new(game.clone()), [Mod::new(project.clone(), ModMetadata::new("Test", "test"))]);
+        return (layers, game, project)
+    }
+
+    #[tokio::test]
+    async fn mods_override_files_and_keys () {
+        let root = std::env::temp_dir().join(format!("vicky3-mod-layers-{}", std::process::id()));
+        let (layers, game, project) = layers(&root).await;
+
+        assert_eq!(layers.files(DIR).await.unwrap(), vec![
+            Origin { layer: 1, file: project.join(DIR).join("a.txt") },
+            Origin { layer: 0, file: game.join(DIR).join("b.txt") },
+            Origin { layer: 1, file: project.join(DIR).join("c.txt") }
+        ]);
+
+        // the game's `y` is gone with its file, and `c.txt` is loaded after `a.txt`
+        let loaded = layers.load(DIR, |path| parse_path::<BTreeMap<String, u32>>(path)).await.unwrap();
+        assert_eq!(loaded.entries, BTreeMap::from([("x".to_string(), 2), ("y".to_string(), 3), ("z".to_string(), 1)]));
+        assert_eq!(loaded.origins["x"].layer, 1);
+        assert_eq!(loaded.origins["z"].layer, 0);
+
+        // a later mod with `replace_paths` hides the files of every previous layer
+        let mut metadata = ModMetadata::new("Replacer", "replacer");
+        metadata.game_custom_data.replace_paths.push(format!("{DIR}/"));
+        let replacer = root.join("replacer");
+        write(&replacer, &[("d.txt", "w = 4")]).await;
+
+        let mut layers = layers;
+        layers.layers.push(Mod::new(replacer.clone(), metadata).into());
+        assert_eq!(layers.files(DIR).await.unwrap(), vec![Origin { layer: 2, file: replacer.join(DIR).join("d.txt") }]);
+
+        tokio::fs::remove_dir_all(&root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn resolves_topmost_file () {
+        let root = std::env::temp_dir().join(format!("vicky3-mod-resolve-{}", std::process::id()));
+        let (layers, game, project) = layers(&root).await;
+
+        assert_eq!(layers.resolve(&Path::new(DIR).join("a.txt")), project.join(DIR).join("a.txt"));
+        assert_eq!(layers.resolve(&Path::new(DIR).join("b.txt")), game.join(DIR).join("b.txt"));
+        // missing files resolve to the base game
+        assert_eq!(layers.resolve(&Path::new(DIR).join("e.txt")), game.join(DIR).join("e.txt"));
+
+        tokio::fs::remove_dir_all(&root).await.unwrap();
+    }
+}
@@ -0,0 +1,318 @@
+use std::{path::Path, collections::{BTreeMap, BTreeSet}, fmt::Display};
+use crate::{Result, error::{Error, ErrorKind, Span}};
+use super::{read_to_string, Game, GameLayers, Origin};
+
+/// Text of a localization key (e.g. `key:0 "text"`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalizedText {
+    /// Number after the colon, ignored by the game
+    pub version: Option<u32>,
+    /// Text between the quotes, with its escape sequences and formatting as written on the file
+    pub text: String
+}
+
+/// Contents of a single `.yml` localization file
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct LocalizationFile {
+    /// Language of the `l_<language>:` header (e.g. `english`)
+    pub language: String,
+    pub entries: Vec<(String, LocalizedText)>
+}
+
+impl LocalizationFile {
+    /// Parses a localization file, which isn't really YAML but the subset of it that the game understands.
+    ///
+    /// Lines that can't be parsed are skipped, and their errors are returned alongside the file.
+    /// Only a missing language header fails the whole file.
+    pub fn parse (data: &str) -> Result<(Self, Vec<Error>)> {
+        let data = data.strip_prefix('\u{feff}').unwrap_or(data);
+        let mut language = None;
+        let mut entries = Vec::new();
+        let mut errors = Vec::new();
+
+        for (i, line) in data.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue
+            }
+
+            let span = Span { line: i + 1, column: 1 + line.len() - line.trim_start().len() };
+            if language.is_none() {
+                match trimmed.strip_prefix("l_").and_then(|x| x.strip_suffix(':')) {
+                    Some(x) if !x.is_empty() => language = Some(x.to_string()),
+                    _ => return Err(Error::new(ErrorKind::Localization(format!("expected a language header (e.g. `l_english:`), found `{trimmed}`"))).with_span(span))
+                }
+                continue
+            }
+
+            match Self::parse_entry(trimmed) {
+                Ok(entry) => entries.push(entry),
+                Err(msg) => errors.push(Error::new(ErrorKind::Localization(msg)).with_span(span))
+            }
+        }
+
+        return match language {
+            Some(language) => Ok((Self { language, entries }, errors)),
+            None => Err(Error::new(ErrorKind::Localization("missing language header (e.g. `l_english:`)".to_string())))
+        }
+    }
+
+    /// Parses a `key:0 "text"` line, without its indentation
+    fn parse_entry (line: &str) -> std::result::Result<(String, LocalizedText), String> {
+        let (key, rest) = match line.split_once(':') {
+            Some((key, rest)) if !key.is_empty() && !key.contains(char::is_whitespace) => (key, rest),
+            _ => return Err(format!("expected `key: \"text\"`, found `{line}`"))
+        };
+
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        let version = match &rest[..digits] {
+            "" => None,
+            x => Some(x.parse::<u32>().map_err(|_| format!("invalid version `{x}` of `{key}`"))?)
+        };
+
+        let value = match rest[digits..].trim_start().strip_prefix('"') {
+            Some(x) => x,
+            None => return Err(format!("missing opening quote on the text of `{key}`"))
+        };
+
+        // quotes inside the text don't need to be escaped, so the text ends at the first unescaped quote
+        // followed by nothing but a comment, even if the comment has quotes of its own
+        let mut escaped = false;
+        let end = value.char_indices().find(|&(i, c)| {
+            let is_end = c == '"' && !escaped && {
+                let trailing = value[i + 1..].trim_start();
+                trailing.is_empty() || trailing.starts_with('#')
+            };
+            escaped = c == '\\' && !escaped;
+            is_end
+        });
+
+        return match end {
+            Some((end, _)) => Ok((key.to_string(), LocalizedText { version, text: value[..end].to_string() })),
+            None if value.contains('"') => Err(format!("unexpected text after the closing quote of `{key}`")),
+            None => Err(format!("missing closing quote on the text of `{key}`"))
+        }
+    }
+
+    #[inline]
+    pub async fn from_path (path: impl AsRef<Path>) -> Result<(Self, Vec<Error>)> {
+        let path = path.as_ref();
+        let data = match read_to_string(path).await {
+            Ok(x) => x,
+            Err(e) => return Err(Error::from(e).with_file(path))
+        };
+
+        let (file, errors) = Self::parse(&data).map_err(|e| e.with_file(path))?;
+        return Ok((file, errors.into_iter().map(|e| e.with_file(path)).collect()))
+    }
+
+    #[inline]
+    pub async fn to_path (&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        // the game ignores localization files without BOM
+        let mut data = String::from('\u{feff}');
+        data.push_str(&self.to_string());
+        tokio::fs::write(path, data).await?;
+        return Ok(())
+    }
+}
+
+/// Writes the file back in the format the game expects
+impl Display for LocalizationFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "l_{}:", self.language)?;
+        for (key, value) in self.entries.iter() {
+            match value.version {
+                Some(version) => writeln!(f, " {key}:{version} \"{}\"", value.text)?,
+                None => writeln!(f, " {key}: \"{}\"", value.text)?
+            }
+        }
+        return Ok(())
+    }
+}
+
+/// Localized texts of every language, merged across layers
+#[derive(Debug)]
+pub struct Localization {
+    /// Language whose texts are shown on the editor
+    pub language: String,
+    /// Texts by language (e.g. `english`) and key
+    pub languages: BTreeMap<String, BTreeMap<String, LocalizedText>>,
+    /// File that every text came from, by language and key
+    pub origins: BTreeMap<String, BTreeMap<String, Origin>>,
+    /// Keys that mods add instead of overriding them, by language
+    pub mod_keys: BTreeMap<String, BTreeSet<String>>
+}
+
+impl Default for Localization {
+    #[inline]
+    fn default() -> Self {
+        return Self {
+            language: "english".to_string(),
+            languages: BTreeMap::new(),
+            origins: BTreeMap::new(),
+            mod_keys: BTreeMap::new()
+        }
+    }
+}
+
+impl Localization {
+    pub const PATH: &'static str = "localization";
+
+    #[inline]
+    pub fn get (&self, language: &str, key: &str) -> Option<&LocalizedText> {
+        return self.languages.get(language)?.get(key)
+    }
+
+    /// Text of `key` on the current language. Prefixed identifiers (e.g. `c:GBR`) fall back to their unprefixed key
+    #[inline]
+    pub fn name (&self, key: &str) -> Option<&str> {
+        let texts = self.languages.get(&self.language)?;
+        let text = match texts.get(key) {
+            Some(x) => x,
+            None => texts.get(key.rsplit_once(':')?.1)?
+        };
+        return Some(&text.text)
+    }
+
+    /// Keys whose text is the name of an entity, with the category of the entity
+    pub fn named_keys (game: &Game) -> Vec<(&'static str, String)> {
+        let mut result = Vec::new();
+        result.extend(game.religions.borrow().keys().map(|x| ("Religion", x.clone())));
+        result.extend(game.cultures.borrow().keys().map(|x| ("Culture", x.clone())));
+        result.extend(game.countries.ranks.borrow().keys().map(|x| ("Country rank", x.clone())));
+        result.extend(game.countries.tys.borrow().keys().map(|x| ("Country type", x.clone())));
+        result.extend(game.countries.definitions.borrow().keys().map(|x| ("Country", x.value.clone())));
+        return result
+    }
+
+    /// Keys of every loaded entity, which their texts (e.g. `GBR_ADJ`, `law_monarchy_desc`) are derived from
+    pub fn used_keys (game: &Game) -> BTreeSet<String> {
+        let mut result = Self::named_keys(game).into_iter().map(|(_, key)| key).collect::<BTreeSet<_>>();
+        result.extend(game.pop_types.borrow().keys().cloned());
+        result.extend(game.states.regions.borrow().keys().cloned());
+        result.extend(game.economy.goods.borrow().keys().cloned());
+        result.extend(game.economy.buildings.borrow().keys().cloned());
+        result.extend(game.economy.production_methods.borrow().keys().cloned());
+        result.extend(game.economy.production_method_groups.borrow().keys().cloned());
+        result.extend(game.laws.groups.borrow().keys().cloned());
+        result.extend(game.laws.laws.borrow().keys().cloned());
+        result.extend(game.politics.interest_groups.borrow().keys().cloned());
+        result.extend(game.politics.ideologies.borrow().keys().cloned());
+        result.extend(game.politics.movements.borrow().keys().cloned());
+        result.extend(game.technologies.borrow().keys().cloned());
+        return result
+    }
+
+    /// Keys that mods add to `language` but no entity of `used` refers to, even through a suffix (e.g. `_desc`)
+    pub fn orphaned<'a> (&'a self, language: &str, used: &BTreeSet<String>) -> Vec<&'a str> {
+        const SUFFIXES: [&str; 4] = ["_ADJ", "_desc", "_name", "_short"];
+        let keys = match self.mod_keys.get(language) {
+            Some(x) => x,
+            None => return Vec::new()
+        };
+
+        return keys.iter()
+            .map(String::as_str)
+            .filter(|key| !used.contains(*key) && !SUFFIXES.iter().any(|x| key.strip_suffix(x).map_or(false, |x| used.contains(x))))
+            .collect()
+    }
+
+    #[inline]
+    pub async fn from_game (game: &GameLayers) -> Result<(Self, Vec<Error>)> {
+        return Self::load(game, |_| false).await
+    }
+
+    /// Loads every `.yml` file of `localization` except the ones to `skip`, including the ones on each language's subfolders.
+    ///
+    /// Files of a `replace` folder are applied after every other one, like the game does.
+    /// Files that fail to parse are skipped, and their errors are returned alongside the result.
+    pub async fn load (game: &GameLayers, skip: impl Fn(&Origin) -> bool) -> Result<(Self, Vec<Error>)> {
+        let mut files = game.files_recursive(Self::PATH).await?;
+        files.retain(|x| x.file.extension().map_or(false, |x| x.eq_ignore_ascii_case("yml")) && !skip(x));
+        files.sort_by_key(|x| x.file.components().any(|x| x.as_os_str() == "replace"));
+
+        let parsed = futures::future::join_all(files.iter().map(|x| LocalizationFile::from_path(&x.file))).await;
+        let mut result = Self::default();
+        let mut base_keys = BTreeMap::<String, BTreeSet<String>>::new();
+        let mut errors = Vec::new();
+
+        for (origin, parsed) in files.into_iter().zip(parsed) {
+            let file = game.relative(&origin).to_path_buf();
+            let parsed = match parsed {
+                Ok((x, line_errors)) => {
+                    errors.extend(line_errors.into_iter().map(|e| e.with_file(file.clone())));
+                    x
+                },
+                Err(e) => {
+                    errors.push(e.with_file(file));
+                    continue
+                }
+            };
+
+            let texts = result.languages.entry(parsed.language.clone()).or_default();
+            let origins = result.origins.entry(parsed.language.clone()).or_default();
+            let base = base_keys.entry(parsed.language).or_default();
+            for (key, value) in parsed.entries {
+                if origin.layer == 0 {
+                    base.insert(key.clone());
+                }
+                origins.insert(key.clone(), origin.clone());
+                texts.insert(key, value);
+            }
+        }
+
+        for (language, origins) in result.origins.iter() {
+            let base = base_keys.get(language);
+            let keys = origins.iter()
+                .filter(|(key, origin)| origin.layer > 0 && !base.map_or(false, |x| x.contains(*key)))
+                .map(|(key, _)| key.clone())
+                .collect::<BTreeSet<_>>();
+
+            if !keys.is_empty() {
+                result.mod_keys.insert(language.clone(), keys);
+            }
+        }
+
+        return Ok((result, errors))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LocalizationFile, LocalizedText};
+
+    #[test]
+    fn parse_file () {
+        let data = "\u{feff}l_english:\n # comment\n GBR:0 \"Great Britain\"\n GBR_ADJ: \"British\" # trailing comment\n quote:12 \"He said \"hi\"\"\n\n";
+        let (file, errors) = LocalizationFile::parse(data).unwrap();
+        assert!(errors.is_empty());
+
+        assert_eq!(file.language, "english");
+        assert_eq!(file.entries, vec![
+            ("GBR".to_string(), LocalizedText { version: Some(0), text: "Great Britain".to_string() }),
+            ("GBR_ADJ".to_string(), LocalizedText { version: None, text: "British".to_string() }),
+            ("quote".to_string(), LocalizedText { version: Some(12), text: "He said \"hi\"".to_string() })
+        ]);
+        assert_eq!(LocalizationFile::parse(&file.to_string()).unwrap().0, file);
+
+        let error = LocalizationFile::parse("key: \"text\"").unwrap_err();
+        assert_eq!(error.span().map(|x| x.line), Some(1));
+    }
+
+    #[test]
+    fn skips_broken_lines () {
+        let data = "l_english:\n key \"text\"\n escaped: \"a \\\" b\" # \"quoted\" comment\n open: \"no end\n after: \"text\"";
+        let (file, errors) = LocalizationFile::parse(data).unwrap();
+
+        assert_eq!(file.entries, vec![
+            ("escaped".to_string(), LocalizedText { version: None, text: "a \\\" b".to_string() }),
+            ("after".to_string(), LocalizedText { version: None, text: "text".to_string() })
+        ]);
+        assert_eq!(errors.iter().map(|x| x.span().map(|x| x.line)).collect::<Vec<_>>(), [Some(2), Some(4)]);
+    }
+}
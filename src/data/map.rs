@@ -0,0 +1,104 @@
+use std::{path::Path, collections::HashMap};
+use tokio::task::spawn_blocking;
+use crate::{Result, error::Error};
+use super::{GameLayers, ProvinceId};
+
+/// Decoded `provinces.png`, where every pixel is mapped to the province of its color
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProvinceMap {
+    width: u32,
+    height: u32,
+    /// Index of the province of every pixel, row by row
+    pixels: Vec<u32>,
+    /// Id of every province, by index
+    provinces: Vec<ProvinceId>,
+    indices: HashMap<ProvinceId, u32>
+}
+
+impl ProvinceMap {
+    /// Path of the province map, relative to the game's root
+    pub const PATH: &'static str = "map_data/provinces.png";
+
+    #[inline]
+    pub async fn from_path (path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        return spawn_blocking(move || {
+            let image = image::open(&path).map_err(|e| Error::from(e).with_file(&path))?;
+            Ok(Self::from_rgb(image.width(), image.height(), image.into_rgb8().as_raw()))
+        }).await.unwrap()
+    }
+
+    /// Loads the province map of the topmost layer that has one
+    #[inline]
+    pub async fn from_game (game: &GameLayers) -> Result<Self> {
+        return Self::from_path(game.resolve(Path::new(Self::PATH))).await
+    }
+
+    /// Builds the map from packed RGB pixels
+    pub fn from_rgb (width: u32, height: u32, data: &[u8]) -> Self {
+        let mut provinces = Vec::new();
+        let mut indices = HashMap::<ProvinceId, u32>::new();
+        let mut pixels = Vec::with_capacity((width * height) as usize);
+
+        for rgb in data.chunks_exact(3) {
+            let id = ProvinceId::from_rgb([rgb[0], rgb[1], rgb[2]]);
+            let idx = *indices.entry(id).or_insert_with(|| {
+                provinces.push(id);
+                (provinces.len() - 1) as u32
+            });
+            pixels.push(idx);
+        }
+
+        return Self { width, height, pixels, provinces, indices }
+    }
+
+    #[inline]
+    pub fn width (&self) -> u32 {
+        return self.width
+    }
+
+    #[inline]
+    pub fn height (&self) -> u32 {
+        return self.height
+    }
+
+    /// Ids of the provinces, by index
+    #[inline]
+    pub fn provinces (&self) -> &[ProvinceId] {
+        return &self.provinces
+    }
+
+    #[inline]
+    pub fn index_of (&self, province: ProvinceId) -> Option<u32> {
+        return self.indices.get(&province).copied()
+    }
+
+    /// Index of the province at the given pixel
+    #[inline]
+    pub fn index_at (&self, x: u32, y: u32) -> Option<u32> {
+        if x >= self.width || y >= self.height {
+            return None
+        }
+        return Some(self.pixels[(y * self.width + x) as usize])
+    }
+
+    #[inline]
+    pub fn province_at (&self, x: u32, y: u32) -> Option<ProvinceId> {
+        return self.index_at(x, y).map(|idx| self.provinces[idx as usize])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ProvinceMap, ProvinceId};
+
+    #[test]
+    fn pixels_to_provinces () {
+        let data = [0x5A, 0x91, 0xEB, 0, 0, 0, 0x5A, 0x91, 0xEB, 1, 2, 3];
+        let map = ProvinceMap::from_rgb(2, 2, &data);
+        assert_eq!(map.provinces().len(), 3);
+        assert_eq!(map.province_at(0, 1), Some(ProvinceId::from_rgb([0x5A, 0x91, 0xEB])));
+        assert_eq!(map.index_of("x5a91eb".parse().unwrap()), map.index_at(0, 0));
+        assert_eq!(map.province_at(2, 0), None);
+    }
+}
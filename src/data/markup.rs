@@ -0,0 +1,347 @@
+use std::fmt::Display;
+use eframe::{egui::{Ui, TextStyle}, epaint::{Color32, Stroke, text::{LayoutJob, TextFormat}}};
+use super::Localization;
+
+/// How deep `$key$` references are expanded, so texts that refer to each other don't recurse forever
+const MAX_DEPTH: usize = 8;
+
+/// Piece of a localized text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Text (String),
+    /// Text of another key (e.g. `$concept_law$` or `$VALUE|+=$`)
+    Key { key: String, format: Option<String> },
+    /// Expression evaluated by the game (e.g. `[GetPlayer.GetName]`)
+    Scripted (String),
+    /// Link to a game concept, shown as its text (e.g. `[Concept('concept_law', '$concept_law$')]`)
+    Concept { key: String, text: Vec<Segment> },
+    /// `@icon!`
+    Icon (String),
+    /// `#style ... #!`, where `style` may combine several with `;` (e.g. `#bold;v`)
+    Format { style: String, children: Vec<Segment> },
+    /// `§Y ... §!`
+    Color { code: char, children: Vec<Segment> }
+}
+
+/// Mistakes found while parsing a text, which the game shows as is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MarkupProblem {
+    UnclosedFormat (String),
+    UnexpectedFormatEnd,
+    UnclosedColor (char),
+    UnexpectedColorEnd,
+    UnclosedBracket
+}
+
+impl Display for MarkupProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnclosedFormat(style) => write!(f, "`#{style}` is never closed with `#!`"),
+            Self::UnexpectedFormatEnd => f.write_str("`#!` without a matching `#style`"),
+            Self::UnclosedColor(code) => write!(f, "`§{code}` is never closed with `§!`"),
+            Self::UnexpectedColorEnd => f.write_str("`§!` without a matching color"),
+            Self::UnclosedBracket => f.write_str("`[` is never closed with `]`")
+        }
+    }
+}
+
+/// Parsed localized text
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Markup {
+    pub segments: Vec<Segment>,
+    pub problems: Vec<MarkupProblem>
+}
+
+enum Frame {
+    Format (String),
+    Color (char)
+}
+
+impl Markup {
+    /// Parses `text`, keeping going after any problem like the game does
+    pub fn parse (text: &str) -> Self {
+        let chars = text.chars().collect::<Vec<_>>();
+        let mut problems = Vec::new();
+        let mut stack = vec![(None, Vec::<Segment>::new())];
+        let mut i = 0;
+
+        while i < chars.len() {
+            let c = chars[i];
+            i += 1;
+
+            match c {
+                '\\' if i < chars.len() => {
+                    push_text(&mut stack, if chars[i] == 'n' { '\n' } else { chars[i] });
+                    i += 1;
+                },
+
+                '$' => match find(&chars, i, '$') {
+                    Some(end) if end > i && !chars[i..end].iter().any(|x| x.is_whitespace()) => {
+                        let content = chars[i..end].iter().collect::<String>();
+                        let (key, format) = match content.split_once('|') {
+                            Some((key, format)) => (key.to_string(), Some(format.to_string())),
+                            None => (content, None)
+                        };
+                        push(&mut stack, Segment::Key { key, format });
+                        i = end + 1;
+                    },
+                    _ => push_text(&mut stack, c)
+                },
+
+                '[' => {
+                    let mut depth = 1;
+                    let mut end = i;
+                    while end < chars.len() {
+                        match chars[end] {
+                            '[' => depth += 1,
+                            ']' => depth -= 1,
+                            _ => {}
+                        }
+                        if depth == 0 {
+                            break
+                        }
+                        end += 1;
+                    }
+
+                    if end >= chars.len() {
+                        problems.push(MarkupProblem::UnclosedBracket);
+                        push_text(&mut stack, c);
+                        continue
+                    }
+
+                    let code = chars[i..end].iter().collect::<String>();
+                    push(&mut stack, parse_scripted(code));
+                    i = end + 1;
+                },
+
+                '@' => {
+                    let end = i + chars[i..].iter().take_while(|x| x.is_alphanumeric() || **x == '_').count();
+                    if end > i && chars.get(end) == Some(&'!') {
+                        push(&mut stack, Segment::Icon(chars[i..end].iter().collect()));
+                        i = end + 1;
+                    } else {
+                        push_text(&mut stack, c);
+                    }
+                },
+
+                '#' if chars.get(i) == Some(&'!') => {
+                    i += 1;
+                    match stack.iter().rposition(|(frame, _)| matches!(frame, Some(Frame::Format(_)))) {
+                        Some(pos) => close(&mut stack, pos, pos + 1, &mut problems),
+                        None => problems.push(MarkupProblem::UnexpectedFormatEnd)
+                    }
+                },
+
+                '#' => {
+                    let end = i + chars[i..].iter().take_while(|x| !x.is_whitespace()).count();
+                    if end == i {
+                        push_text(&mut stack, c);
+                        continue
+                    }
+
+                    stack.push((Some(Frame::Format(chars[i..end].iter().collect())), Vec::new()));
+                    // the space after the style isn't part of the text
+                    i = end + usize::from(end < chars.len());
+                },
+
+                '§' if i < chars.len() => {
+                    let code = chars[i];
+                    i += 1;
+                    if code != '!' {
+                        stack.push((Some(Frame::Color(code)), Vec::new()));
+                        continue
+                    }
+
+                    match stack.iter().rposition(|(frame, _)| matches!(frame, Some(Frame::Color(_)))) {
+                        Some(pos) => close(&mut stack, pos, pos + 1, &mut problems),
+                        None => problems.push(MarkupProblem::UnexpectedColorEnd)
+                    }
+                },
+
+                _ => push_text(&mut stack, c)
+            }
+        }
+
+        close(&mut stack, 1, 1, &mut problems);
+
+        let segments = stack.pop().map(|(_, x)| x).unwrap_or_default();
+        return Self { segments, problems }
+    }
+
+    /// Keys referenced with `$key$`, including the ones inside of formatted blocks
+    pub fn keys (&self) -> Vec<&str> {
+        fn visit<'a> (segments: &'a [Segment], result: &mut Vec<&'a str>) {
+            for segment in segments {
+                match segment {
+                    Segment::Key { key, .. } => result.push(key),
+                    Segment::Concept { text: children, .. } | Segment::Format { children, .. } | Segment::Color { children, .. } => visit(children, result),
+                    _ => {}
+                }
+            }
+        }
+
+        let mut result = Vec::new();
+        visit(&self.segments, &mut result);
+        return result
+    }
+
+    /// Lays out the text the way the game would show it, replacing `$key$` with the texts of the current language
+    pub fn layout_job (&self, ui: &Ui, localization: &Localization) -> LayoutJob {
+        let format = TextFormat {
+            font_id: TextStyle::Body.resolve(ui.style()),
+            color: ui.visuals().text_color(),
+            ..Default::default()
+        };
+
+        let mut job = LayoutJob::default();
+        append(&mut job, &self.segments, &format, ui, localization, 0);
+        return job
+    }
+}
+
+fn append (job: &mut LayoutJob, segments: &[Segment], format: &TextFormat, ui: &Ui, localization: &Localization, depth: usize) {
+    for segment in segments {
+        match segment {
+            Segment::Text(text) => job.append(text, 0.0, format.clone()),
+
+            Segment::Key { key, .. } => match localization.name(key) {
+                Some(text) if depth < MAX_DEPTH => append(job, &Markup::parse(text).segments, format, ui, localization, depth + 1),
+                _ => job.append(&format!("${key}$"), 0.0, TextFormat { color: Color32::LIGHT_RED, ..format.clone() })
+            },
+
+            Segment::Scripted(code) => job.append(&format!("[{code}]"), 0.0, TextFormat { italics: true, color: ui.visuals().weak_text_color(), ..format.clone() }),
+
+            Segment::Concept { text, .. } => {
+                let format = TextFormat { underline: Stroke::new(1.0, format.color), ..format.clone() };
+                append(job, text, &format, ui, localization, depth)
+            },
+
+            Segment::Icon(icon) => job.append(icon, 0.0, TextFormat { background: ui.visuals().faint_bg_color, color: ui.visuals().weak_text_color(), ..format.clone() }),
+
+            Segment::Format { style, children } => {
+                let mut format = format.clone();
+                for style in style.split(';') {
+                    match style {
+                        "b" | "bold" | "header" | "title" => format.color = ui.visuals().strong_text_color(),
+                        "i" | "italic" | "lore" => format.italics = true,
+                        "v" | "variable" | "value" => format.color = Color32::from_rgb(255, 230, 150),
+                        "P" | "positive" => format.color = Color32::LIGHT_GREEN,
+                        "N" | "negative" => format.color = Color32::LIGHT_RED,
+                        "tooltippable" | "concept" => format.underline = Stroke::new(1.0, format.color),
+                        _ => {}
+                    }
+                }
+                append(job, children, &format, ui, localization, depth)
+            },
+
+            Segment::Color { code, children } => {
+                let color = match code {
+                    'Y' => Color32::YELLOW,
+                    'R' => Color32::LIGHT_RED,
+                    'G' => Color32::LIGHT_GREEN,
+                    'B' => Color32::LIGHT_BLUE,
+                    'W' => Color32::WHITE,
+                    _ => format.color
+                };
+                append(job, children, &TextFormat { color, ..format.clone() }, ui, localization, depth)
+            }
+        }
+    }
+}
+
+/// `Concept('key', 'text')` becomes a [`Segment::Concept`], anything else is left to the game
+fn parse_scripted (code: String) -> Segment {
+    let args = match code.strip_prefix("Concept(").and_then(|x| x.strip_suffix(')')) {
+        Some(x) => x,
+        None => return Segment::Scripted(code)
+    };
+
+    let (key, text) = match args.split_once(',') {
+        Some((key, text)) => (key.trim(), text.trim()),
+        None => return Segment::Scripted(code)
+    };
+
+    let unquote = |x: &str| x.strip_prefix('\'').and_then(|x| x.strip_suffix('\'')).map(str::to_string);
+    return match (unquote(key), unquote(text)) {
+        (Some(key), Some(text)) => Segment::Concept { key, text: Markup::parse(&text).segments },
+        _ => Segment::Scripted(code)
+    }
+}
+
+#[inline]
+fn find (chars: &[char], from: usize, c: char) -> Option<usize> {
+    return chars[from..].iter().position(|x| *x == c).map(|x| x + from)
+}
+
+#[inline]
+fn push (stack: &mut [(Option<Frame>, Vec<Segment>)], segment: Segment) {
+    if let Some((_, segments)) = stack.last_mut() {
+        segments.push(segment);
+    }
+}
+
+#[inline]
+fn push_text (stack: &mut [(Option<Frame>, Vec<Segment>)], c: char) {
+    let segments = match stack.last_mut() {
+        Some((_, x)) => x,
+        None => return
+    };
+
+    match segments.last_mut() {
+        Some(Segment::Text(text)) => text.push(c),
+        _ => segments.push(Segment::Text(c.to_string()))
+    }
+}
+
+/// Closes every frame from `pos` upwards, reporting the ones from `unclosed` upwards as never closed
+fn close (stack: &mut Vec<(Option<Frame>, Vec<Segment>)>, pos: usize, unclosed: usize, problems: &mut Vec<MarkupProblem>) {
+    while stack.len() > pos {
+        let (frame, children) = match stack.pop() {
+            Some(x) => x,
+            None => return
+        };
+
+        let reported = stack.len() >= unclosed;
+        let segment = match frame {
+            Some(Frame::Format(style)) => {
+                if reported {
+                    problems.push(MarkupProblem::UnclosedFormat(style.clone()));
+                }
+                Segment::Format { style, children }
+            },
+            Some(Frame::Color(code)) => {
+                if reported {
+                    problems.push(MarkupProblem::UnclosedColor(code));
+                }
+                Segment::Color { code, children }
+            },
+            None => return
+        };
+        push(stack, segment);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Markup, Segment, MarkupProblem};
+
+    #[test]
+    fn parse_markup () {
+        let markup = Markup::parse("#bold $concept_law$#! costs @money! §Y[Concept('concept_law', 'laws')]§!\\n[GetPlayer.GetName]");
+        assert!(markup.problems.is_empty());
+        assert_eq!(markup.segments, vec![
+            Segment::Format { style: "bold".to_string(), children: vec![Segment::Key { key: "concept_law".to_string(), format: None }] },
+            Segment::Text(" costs ".to_string()),
+            Segment::Icon("money".to_string()),
+            Segment::Text(" ".to_string()),
+            Segment::Color { code: 'Y', children: vec![Segment::Concept { key: "concept_law".to_string(), text: vec![Segment::Text("laws".to_string())] }] },
+            Segment::Text("\n".to_string()),
+            Segment::Scripted("GetPlayer.GetName".to_string())
+        ]);
+        assert_eq!(markup.keys(), ["concept_law"]);
+
+        assert_eq!(Markup::parse("#N lost $VALUE|+=$").problems, [MarkupProblem::UnclosedFormat("N".to_string())]);
+        assert_eq!(Markup::parse("done#!").problems, [MarkupProblem::UnexpectedFormatEnd]);
+        assert_eq!(Markup::parse("#v §R red #! [Oops").problems, [MarkupProblem::UnclosedColor('R'), MarkupProblem::UnclosedBracket]);
+    }
+}
@@ -10,21 +10,41 @@ macro_rules! flat_mod {
 }
 
 pub mod country;
+pub mod economy;
+pub mod flag;
+pub mod law;
+pub mod politics;
 pub mod state;
 pub mod culture;
 pub mod religion;
+pub mod pop_type;
+pub mod technology;
+pub mod markup;
+pub mod dds;
+pub mod ser;
+pub mod json;
+pub mod validate;
+pub mod map;
 
-flat_mod! { color, ident }
+flat_mod! { color, ident, mods, layers, script, province, localization }
 
 use std::{path::{Path, PathBuf}, collections::{BTreeMap}};
-use country::GameCountry;
+use country::{GameCountry, CountryRank, CountryType, CountryDefinition, CountryHistory};
 use culture::Culture;
-use futures::{Stream, TryStreamExt, TryFutureExt};
+use law::{GameLaws, Law, LawGroup};
+use politics::{GamePolitics, InterestGroup, Ideology, PoliticalMovement};
+use economy::{GameEconomy, Goods, Building, ProductionMethod, ProductionMethodGroup};
+use flag::{GameFlags, CoatOfArms, FlagDefinitionList, NamedColors};
+use futures::{Stream, TryStreamExt};
 use into_string::IntoPathBuf;
 use itertools::Itertools;
 use religion::Religion;
-use crate::{utils::{FlattenOkIter, refcell::RefCell}, Result};
-use self::state::GameState;
+use pop_type::PopType;
+use technology::Technology;
+use serde::{Serialize, de::DeserializeOwned};
+use tokio::task::spawn_blocking;
+use crate::{utils::{FlattenOkIter, refcell::RefCell, texture_cache::TextureCache}, Result, error::{Error, Span}};
+use self::state::{GameState, StateDefinition, RegionPops, StateRegion};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct GamePaths {
@@ -61,31 +81,252 @@ impl GamePaths {
 #[derive(Debug)]
 #[non_exhaustive]
 pub struct Game {
+    /// Paths of the base game
     pub path: GamePaths,
+    pub layers: GameLayers,
+    pub origins: Origins,
     pub countries: GameCountry,
     pub states: GameState,
+    pub economy: GameEconomy,
+    pub laws: GameLaws,
+    pub politics: GamePolitics,
+    pub flags: GameFlags,
+    pub technologies: RefCell<BTreeMap<String, Technology>>,
     pub religions: RefCell<BTreeMap<String, Religion>>,
-    pub cultures: RefCell<BTreeMap<String, Culture>>
+    pub cultures: RefCell<BTreeMap<String, Culture>>,
+    pub pop_types: RefCell<BTreeMap<String, PopType>>,
+    pub localization: RefCell<Localization>,
+    /// Textures of the game files that are currently shown
+    pub textures: RefCell<TextureCache>
 }
 
 impl Game {
     #[inline]
     pub async fn new<P: IntoPathBuf> (path: P) -> Result<Self> {
-        let path = GamePaths::new(path);
-        let (countries, states, religions, cultures) = futures::try_join! {
-            GameCountry::from_game(&path),
-            GameState::from_game(&path),
-            Religion::from_game(&path).and_then(TryStreamExt::try_collect::<BTreeMap<_, _>>),
-            Culture::from_game(&path).and_then(TryStreamExt::try_collect::<BTreeMap<_, _>>)
+        return Self::with_layers(GameLayers::new(GamePaths::new(path), std::iter::empty())).await
+    }
+
+    /// Loads the base game at `path` with `mods` on top of it, in load order
+    #[inline]
+    pub async fn with_mods<P: IntoPathBuf> (path: P, mods: impl IntoIterator<Item = Mod>) -> Result<Self> {
+        return Self::with_layers(GameLayers::new(GamePaths::new(path), mods)).await
+    }
+
+    /// Loads every layer, failing if any of the files couldn't be parsed
+    #[inline]
+    pub async fn with_layers (layers: GameLayers) -> Result<Self> {
+        let (this, errors) = Self::with_layers_lenient(layers).await?;
+        return match Error::collect(errors) {
+            Some(e) => Err(e),
+            None => Ok(this)
+        }
+    }
+
+    /// Loads every layer, skipping the files that couldn't be parsed and returning their errors alongside the game.
+    /// Only errors that prevent the game from loading at all (e.g. a missing base game directory) are returned as `Err`.
+    pub async fn with_layers_lenient (layers: GameLayers) -> Result<(Self, Vec<Error>)> {
+        let (ranks, tys, definitions, history, state_defs, pops, state_regions, goods, buildings, production_methods, production_method_groups, law_groups, laws, interest_groups, ideologies, movements, technologies, religions, cultures, pop_types, coats_of_arms, flag_definitions, named_colors, (localization, localization_errors)) = futures::try_join! {
+            CountryRank::from_game(&layers),
+            CountryType::from_game(&layers),
+            CountryDefinition::from_game(&layers),
+            CountryHistory::from_game(&layers),
+            StateDefinition::from_game(&layers),
+            RegionPops::from_game(&layers),
+            StateRegion::from_game(&layers),
+            Goods::from_game(&layers),
+            Building::from_game(&layers),
+            ProductionMethod::from_game(&layers),
+            ProductionMethodGroup::from_game(&layers),
+            LawGroup::from_game(&layers),
+            Law::from_game(&layers),
+            InterestGroup::from_game(&layers),
+            Ideology::from_game(&layers),
+            PoliticalMovement::from_game(&layers),
+            Technology::from_game(&layers),
+            Religion::from_game(&layers),
+            Culture::from_game(&layers),
+            PopType::from_game(&layers),
+            CoatOfArms::from_game(&layers),
+            FlagDefinitionList::from_game(&layers),
+            NamedColors::from_game(&layers),
+            Localization::from_game(&layers)
         }?;
 
-        return Ok(Self {
-            path,
-            countries,
-            states,
-            religions: RefCell::new(religions),
-            cultures: RefCell::new(cultures)
-        })
+        let mut errors = Vec::new();
+        errors.extend(ranks.errors);
+        errors.extend(tys.errors);
+        errors.extend(definitions.errors);
+        errors.extend(history.errors);
+        errors.extend(state_defs.errors);
+        errors.extend(pops.errors);
+        errors.extend(state_regions.errors);
+        errors.extend(goods.errors);
+        errors.extend(buildings.errors);
+        errors.extend(production_methods.errors);
+        errors.extend(production_method_groups.errors);
+        errors.extend(law_groups.errors);
+        errors.extend(laws.errors);
+        errors.extend(interest_groups.errors);
+        errors.extend(ideologies.errors);
+        errors.extend(movements.errors);
+        errors.extend(technologies.errors);
+        errors.extend(religions.errors);
+        errors.extend(cultures.errors);
+        errors.extend(pop_types.errors);
+        errors.extend(coats_of_arms.errors);
+        errors.extend(flag_definitions.errors);
+        errors.extend(named_colors.errors);
+        errors.extend(localization_errors);
+
+        let origins = Origins {
+            religions: religions.origins,
+            cultures: cultures.origins,
+            pop_types: pop_types.origins,
+            country_ranks: ranks.origins,
+            country_types: tys.origins,
+            country_definitions: definitions.origins,
+            country_history: history.origins,
+            state_definitions: state_defs.origins,
+            state_pops: pops.origins,
+            state_regions: state_regions.origins,
+            goods: goods.origins,
+            buildings: buildings.origins,
+            production_methods: production_methods.origins,
+            production_method_groups: production_method_groups.origins,
+            law_groups: law_groups.origins,
+            laws: laws.origins,
+            interest_groups: interest_groups.origins,
+            ideologies: ideologies.origins,
+            movements: movements.origins,
+            technologies: technologies.origins,
+            coats_of_arms: coats_of_arms.origins,
+            flag_definitions: flag_definitions.origins,
+            named_colors: named_colors.origins
+        };
+
+        let this = Self {
+            path: layers.base().clone(),
+            layers,
+            origins,
+            countries: GameCountry {
+                ranks: RefCell::new(ranks.entries),
+                tys: RefCell::new(tys.entries),
+                definitions: RefCell::new(definitions.entries),
+                history: RefCell::new(history.entries)
+            },
+            states: GameState {
+                defs: RefCell::new(state_defs.entries),
+                pops: RefCell::new(pops.entries),
+                regions: RefCell::new(state_regions.entries)
+            },
+            economy: GameEconomy {
+                goods: RefCell::new(goods.entries),
+                buildings: RefCell::new(buildings.entries),
+                production_methods: RefCell::new(production_methods.entries),
+                production_method_groups: RefCell::new(production_method_groups.entries)
+            },
+            laws: GameLaws {
+                groups: RefCell::new(law_groups.entries),
+                laws: RefCell::new(laws.entries)
+            },
+            politics: GamePolitics {
+                interest_groups: RefCell::new(interest_groups.entries),
+                ideologies: RefCell::new(ideologies.entries),
+                movements: RefCell::new(movements.entries)
+            },
+            flags: GameFlags {
+                coats_of_arms: RefCell::new(coats_of_arms.entries),
+                definitions: RefCell::new(flag_definitions.entries),
+                named_colors: RefCell::new(named_colors.entries)
+            },
+            technologies: RefCell::new(technologies.entries),
+            religions: RefCell::new(religions.entries),
+            cultures: RefCell::new(cultures.entries),
+            pop_types: RefCell::new(pop_types.entries),
+            localization: RefCell::new(localization),
+            textures: RefCell::new(TextureCache::default())
+        };
+
+        return Ok((this, errors))
+    }
+}
+
+/// Reads and parses a script file, attaching its path and the position of the error to any parsing errors
+#[inline]
+pub(crate) async fn parse_path<T: DeserializeOwned + Send + 'static> (path: impl AsRef<Path>) -> Result<T> {
+    let path = path.as_ref().to_path_buf();
+    let data = match read_to_string(&path).await {
+        Ok(x) => x,
+        Err(e) => return Err(Error::from(e).with_file(path))
+    };
+    return spawn_blocking(move || parse_slice(data.as_bytes()).map_err(|e| e.with_file(path))).await.unwrap()
+}
+
+/// Parses a script file, keeping track of the key path that was being deserialized when an error happened
+pub(crate) fn parse_slice<T: DeserializeOwned> (data: &[u8]) -> Result<T> {
+    let tape = match jomini::TextTape::from_slice(data) {
+        Ok(x) => x,
+        Err(e) => {
+            let offset = match e.kind() {
+                jomini::ErrorKind::InvalidSyntax { offset, .. } | jomini::ErrorKind::StackEmpty { offset } => Some(*offset),
+                _ => None
+            };
+
+            let mut error = Error::from(e);
+            if let Some(offset) = offset {
+                error = error.with_span(Span::from_offset(data, offset));
+            }
+            return Err(error)
+        }
+    };
+
+    let deserializer = jomini::text::de::TextDeserializer::from_utf8_tape(&tape);
+    return serde_path_to_error::deserialize(&deserializer).map_err(|e| {
+        let key_path = e.path().to_string();
+        let mut error = Error::from(e.into_inner());
+        if let Some(span) = Span::from_key_path(data, &key_path) {
+            error = error.with_span(span);
+        }
+        error.with_key_path(key_path)
+    })
+}
+
+/// Parses `data` as a map of entries, writes it back and checks that parsing the output gives the same entries
+#[cfg(test)]
+pub(crate) fn round_trip<K, V> (data: &[u8]) -> BTreeMap<K, V> where
+    K: Ord + std::fmt::Debug + Serialize + DeserializeOwned,
+    V: PartialEq + std::fmt::Debug + Serialize + DeserializeOwned
+{
+    let parsed = parse_slice::<BTreeMap<K, V>>(data).unwrap();
+    let text = ser::to_string(&parsed).unwrap();
+    assert_eq!(parse_slice::<BTreeMap<K, V>>(text.as_bytes()).unwrap(), parsed, "written as:\n{text}");
+    return parsed
+}
+
+/// Keys of the untyped fields of an entry, in order
+#[cfg(test)]
+pub(crate) fn keys (other: &[(String, ScriptValue)]) -> Vec<&str> {
+    return other.iter().map(|(key, _)| key.as_str()).collect()
+}
+
+/// Writes a base game to `root` with `files` (by path relative to `root`), creating every directory that is loaded from it
+#[cfg(test)]
+pub(crate) async fn write_game (root: &Path, files: &[(&str, &str)]) {
+    let _ = tokio::fs::remove_dir_all(root).await;
+    let dirs = [
+        CountryRank::PATH, CountryType::PATH, CountryDefinition::PATH, CountryHistory::PATH,
+        StateDefinition::PATH, RegionPops::PATH, StateRegion::PATH,
+        Goods::PATH, Building::PATH, ProductionMethod::PATH, ProductionMethodGroup::PATH,
+        LawGroup::PATH, Law::PATH, InterestGroup::PATH, Ideology::PATH, PoliticalMovement::PATH,
+        Technology::PATH, Religion::PATH, Culture::PATH, PopType::PATH,
+        CoatOfArms::PATH, FlagDefinitionList::PATH, NamedColors::PATH, Localization::PATH
+    ];
+
+    for dir in dirs {
+        tokio::fs::create_dir_all(root.join(dir)).await.unwrap();
+    }
+    for (path, data) in files {
+        tokio::fs::write(root.join(path), data).await.unwrap();
     }
 }
 
@@ -97,6 +338,21 @@ pub(crate) async fn read_to_string (path: impl AsRef<Path>) -> std::io::Result<S
     Ok(string)
 }
 
+/// Writes `value` as a Paradox script file, creating its parent directories if needed
+#[inline]
+pub(crate) async fn write_to_path<T: ?Sized + Serialize> (path: impl AsRef<Path>, value: &T) -> Result<()> {
+    let path = path.as_ref();
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+
+    // game files are expected to be UTF-8 with BOM
+    let mut data = String::from('\u{feff}');
+    data.push_str(&ser::to_string(value)?);
+    tokio::fs::write(path, data).await?;
+    return Ok(())
+}
+
 #[inline]
 pub(crate) fn flat_map_ok<T, E, I, F, U> (iter: I, f: F) -> impl Iterator<Item = ::core::result::Result<<U as IntoIterator>::Item, E>> where
     I: IntoIterator<Item = ::core::result::Result<T, E>>,
@@ -0,0 +1,499 @@
+use std::{path::{Path, PathBuf}, collections::{BTreeMap, BTreeSet}, io::ErrorKind, ffi::OsString};
+use futures::Future;
+use into_string::IntoPathBuf;
+use serde::{Serialize, Deserialize};
+use crate::{Result, error::Error};
+use super::{Game, GamePaths, GameLayers, Origin, religion::Religion, culture::Culture, pop_type::PopType, country::{CountryRank, CountryType, CountryDefinition, CountryHistory}, state::{StateDefinition, RegionPops, StateRegion}, economy::{Goods, Building, ProductionMethod, ProductionMethodGroup}, law::{Law, LawGroup}, politics::{InterestGroup, Ideology, PoliticalMovement}, technology::Technology, Localization, LocalizationFile};
+
+/// Contents of a mod's `.metadata/metadata.json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ModMetadata {
+    pub name: String,
+    pub id: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub supported_game_version: String,
+    #[serde(default)]
+    pub short_description: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub relationships: Vec<serde_json::Value>,
+    #[serde(default)]
+    pub game_custom_data: GameCustomData
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct GameCustomData {
+    #[serde(default)]
+    pub multiplayer_synchronized: bool,
+    /// Game directories (e.g. `common/religions`) that are hidden from the layers below this mod
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub replace_paths: Vec<String>
+}
+
+impl ModMetadata {
+    #[inline]
+    pub fn new (name: impl Into<String>, id: impl Into<String>) -> Self {
+        return Self {
+            name: name.into(),
+            id: id.into(),
+            version: String::from("1.0"),
+            supported_game_version: String::new(),
+            short_description: String::new(),
+            tags: Vec::new(),
+            relationships: Vec::new(),
+            game_custom_data: GameCustomData {
+                multiplayer_synchronized: true,
+                replace_paths: Vec::new()
+            }
+        }
+    }
+
+    #[inline]
+    pub fn replace_paths (&self) -> &[String] {
+        return &self.game_custom_data.replace_paths
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mod {
+    pub path: GamePaths,
+    pub metadata: ModMetadata
+}
+
+impl Mod {
+    #[inline]
+    pub fn new (path: impl IntoPathBuf, metadata: ModMetadata) -> Self {
+        return Self {
+            path: GamePaths::new(path),
+            metadata
+        }
+    }
+
+    #[inline]
+    pub async fn open (path: impl IntoPathBuf) -> Result<Self> {
+        let path = GamePaths::new(path);
+        let metadata_path = Self::metadata_path(path.game());
+        let data = super::read_to_string(&metadata_path).await.map_err(|e| Error::from(e).with_file(&metadata_path))?;
+        let metadata = serde_json::from_str::<ModMetadata>(&data).map_err(|e| Error::from(e).with_file(&metadata_path))?;
+        return Ok(Self { path, metadata })
+    }
+
+    #[inline]
+    pub fn metadata_path (root: &Path) -> PathBuf {
+        return root.join(".metadata").join("metadata.json")
+    }
+
+    #[inline]
+    pub fn is_mod (root: &Path) -> bool {
+        return Self::metadata_path(root).is_file()
+    }
+
+    #[inline]
+    pub async fn save_metadata (&self) -> Result<()> {
+        let path = Self::metadata_path(self.path.game());
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let data = serde_json::to_string_pretty(&self.metadata)?;
+        tokio::fs::write(path, data).await?;
+        return Ok(())
+    }
+
+    /// Writes the metadata and every game file that differs from the layers below this mod.
+    ///
+    /// Files are overriden as a whole, so changed files are written with all of their entries, while new entries go to a file owned by the mod.
+    pub async fn save (&self, game: &Game) -> Result<()> {
+        self.check_target(game.path.game()).await?;
+        self.save_metadata().await?;
+
+        // if this mod is one of the loaded layers, only the ones below it are compared against
+        let layer = game.layers.position(self.path.game());
+        let ctx = SaveContext {
+            base: match layer {
+                Some(layer) => game.layers.without(layer),
+                None => game.layers.clone()
+            },
+            layer,
+            target: self.path.game()
+        };
+
+        ctx.save(
+            Religion::PATH, self.new_entries_file("religions"),
+            &*game.religions.borrow(), &game.origins.religions, Religion::from_path,
+            |path, entries| async move { Religion::to_path(path, entries.iter().map(|(k, v)| (k, v))).await }
+        ).await?;
+
+        ctx.save(
+            Culture::PATH, self.new_entries_file("cultures"),
+            &*game.cultures.borrow(), &game.origins.cultures, Culture::from_path,
+            |path, entries| async move { Culture::to_path(path, entries.iter().map(|(k, v)| (k, v))).await }
+        ).await?;
+
+        ctx.save(
+            PopType::PATH, self.new_entries_file("pop_types"),
+            &*game.pop_types.borrow(), &game.origins.pop_types, PopType::from_path,
+            |path, entries| async move { PopType::to_path(path, entries.iter().map(|(k, v)| (k, v))).await }
+        ).await?;
+
+        ctx.save(
+            CountryRank::PATH, self.new_entries_file("country_ranks"),
+            &*game.countries.ranks.borrow(), &game.origins.country_ranks, CountryRank::from_path,
+            |path, entries| async move { CountryRank::to_path(path, entries.iter().map(|(k, v)| (k, v))).await }
+        ).await?;
+
+        ctx.save(
+            CountryType::PATH, self.new_entries_file("country_types"),
+            &*game.countries.tys.borrow(), &game.origins.country_types, CountryType::from_path,
+            |path, entries| async move { CountryType::to_path(path, entries.iter().map(|(k, v)| (k, v))).await }
+        ).await?;
+
+        ctx.save(
+            CountryDefinition::PATH, self.new_entries_file("country_definitions"),
+            &*game.countries.definitions.borrow(), &game.origins.country_definitions, CountryDefinition::from_path,
+            |path, entries| async move { CountryDefinition::to_path(path, entries.iter().map(|(k, v)| (k, v))).await }
+        ).await?;
+
+        ctx.save(
+            CountryHistory::PATH, self.new_entries_file("country_history"),
+            &*game.countries.history.borrow(), &game.origins.country_history, CountryHistory::from_path,
+            |path, entries| async move { CountryHistory::to_path(path, entries.iter().map(|(k, v)| (k, v))).await }
+        ).await?;
+
+        ctx.save(
+            StateDefinition::PATH, self.new_entries_file("states"),
+            &*game.states.defs.borrow(), &game.origins.state_definitions, StateDefinition::from_path,
+            |path, entries| async move { StateDefinition::to_path(path, entries.iter().map(|(k, v)| (k, v))).await }
+        ).await?;
+
+        ctx.save(
+            RegionPops::PATH, self.new_entries_file("pops"),
+            &*game.states.pops.borrow(), &game.origins.state_pops, RegionPops::from_path,
+            |path, entries| async move { RegionPops::to_path(path, entries.iter().map(|(k, v)| (k, v))).await }
+        ).await?;
+
+        ctx.save(
+            StateRegion::PATH, self.new_entries_file("state_regions"),
+            &*game.states.regions.borrow(), &game.origins.state_regions, StateRegion::from_path,
+            |path, entries| async move { StateRegion::to_path(path, entries.iter().map(|(k, v)| (k, v))).await }
+        ).await?;
+
+        ctx.save(
+            Goods::PATH, self.new_entries_file("goods"),
+            &*game.economy.goods.borrow(), &game.origins.goods, Goods::from_path,
+            |path, entries| async move { Goods::to_path(path, entries.iter().map(|(k, v)| (k, v))).await }
+        ).await?;
+
+        ctx.save(
+            Building::PATH, self.new_entries_file("buildings"),
+            &*game.economy.buildings.borrow(), &game.origins.buildings, Building::from_path,
+            |path, entries| async move { Building::to_path(path, entries.iter().map(|(k, v)| (k, v))).await }
+        ).await?;
+
+        ctx.save(
+            ProductionMethod::PATH, self.new_entries_file("production_methods"),
+            &*game.economy.production_methods.borrow(), &game.origins.production_methods, ProductionMethod::from_path,
+            |path, entries| async move { ProductionMethod::to_path(path, entries.iter().map(|(k, v)| (k, v))).await }
+        ).await?;
+
+        ctx.save(
+            ProductionMethodGroup::PATH, self.new_entries_file("production_method_groups"),
+            &*game.economy.production_method_groups.borrow(), &game.origins.production_method_groups, ProductionMethodGroup::from_path,
+            |path, entries| async move { ProductionMethodGroup::to_path(path, entries.iter().map(|(k, v)| (k, v))).await }
+        ).await?;
+
+        ctx.save(
+            LawGroup::PATH, self.new_entries_file("law_groups"),
+            &*game.laws.groups.borrow(), &game.origins.law_groups, LawGroup::from_path,
+            |path, entries| async move { LawGroup::to_path(path, entries.iter().map(|(k, v)| (k, v))).await }
+        ).await?;
+
+        ctx.save(
+            Law::PATH, self.new_entries_file("laws"),
+            &*game.laws.laws.borrow(), &game.origins.laws, Law::from_path,
+            |path, entries| async move { Law::to_path(path, entries.iter().map(|(k, v)| (k, v))).await }
+        ).await?;
+
+        ctx.save(
+            InterestGroup::PATH, self.new_entries_file("interest_groups"),
+            &*game.politics.interest_groups.borrow(), &game.origins.interest_groups, InterestGroup::from_path,
+            |path, entries| async move { InterestGroup::to_path(path, entries.iter().map(|(k, v)| (k, v))).await }
+        ).await?;
+
+        ctx.save(
+            Ideology::PATH, self.new_entries_file("ideologies"),
+            &*game.politics.ideologies.borrow(), &game.origins.ideologies, Ideology::from_path,
+            |path, entries| async move { Ideology::to_path(path, entries.iter().map(|(k, v)| (k, v))).await }
+        ).await?;
+
+        ctx.save(
+            PoliticalMovement::PATH, self.new_entries_file("movements"),
+            &*game.politics.movements.borrow(), &game.origins.movements, PoliticalMovement::from_path,
+            |path, entries| async move { PoliticalMovement::to_path(path, entries.iter().map(|(k, v)| (k, v))).await }
+        ).await?;
+
+        ctx.save(
+            Technology::PATH, self.new_entries_file("technologies"),
+            &*game.technologies.borrow(), &game.origins.technologies, Technology::from_path,
+            |path, entries| async move { Technology::to_path(path, entries.iter().map(|(k, v)| (k, v))).await }
+        ).await?;
+
+        self.save_localization(game).await?;
+        return Ok(())
+    }
+
+    /// Writes every text that differs from the other localization files to a `replace` file per language, which the game loads after the rest
+    async fn save_localization (&self, game: &Game) -> Result<()> {
+        let prefix = format!("zz_{}_l_", self.file_id());
+        let (reference, _) = Localization::load(&game.layers, |x| {
+            x.file.starts_with(self.path.game()) && x.file_name().and_then(|x| x.to_str()).map_or(false, |x| x.starts_with(&prefix))
+        }).await?;
+
+        let localization = game.localization.borrow();
+        for (language, texts) in localization.languages.iter() {
+            let entries = texts.iter()
+                .filter(|(key, text)| reference.get(language, key) != Some(*text))
+                .map(|(key, text)| (key.clone(), text.clone()))
+                .collect::<Vec<_>>();
+
+            let path = self.path.game().join(Localization::PATH).join(language).join("replace").join(self.localization_file(language));
+            if entries.is_empty() {
+                remove_stale(&path).await?;
+            } else {
+                LocalizationFile { language: language.clone(), entries }.to_path(path).await?;
+            }
+        }
+
+        return Ok(())
+    }
+
+    /// Name of the file where entries that don't exist in the base game are written
+    #[inline]
+    fn new_entries_file (&self, category: &str) -> String {
+        return format!("zz_{}_{category}.txt", self.file_id())
+    }
+
+    /// Name of the file where the edited texts of `language` are written. The game requires the `_l_<language>` suffix
+    #[inline]
+    fn localization_file (&self, language: &str) -> String {
+        return format!("zz_{}_l_{language}.yml", self.file_id())
+    }
+
+    /// Id of the mod, usable as part of a file name
+    #[inline]
+    fn file_id (&self) -> String {
+        return self.metadata.id.chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+            .collect::<String>()
+    }
+
+    /// Makes sure we never write inside the base game's folder
+    async fn check_target (&self, base: &Path) -> Result<()> {
+        tokio::fs::create_dir_all(self.path.game()).await?;
+        let target = tokio::fs::canonicalize(self.path.game()).await?;
+        let base = tokio::fs::canonicalize(base).await?;
+
+        if target.starts_with(&base) || base.starts_with(&target) {
+            return Err(std::io::Error::new(ErrorKind::PermissionDenied, "mods cannot be saved inside the base game folder").into())
+        }
+        return Ok(())
+    }
+}
+
+struct SaveContext<'a> {
+    /// Layers that the mod is compared against
+    base: GameLayers,
+    /// Index of the mod's layer, if it's loaded
+    layer: Option<usize>,
+    target: &'a Path
+}
+
+impl<'a> SaveContext<'a> {
+    /// Writes every file of `dir` that contains an entry that was changed or removed, and every new entry to `new_entries`.
+    /// Entries that already belonged to one of the mod's own files are kept on it, and files that no longer differ from the base layers are removed.
+    async fn save<K, V, I, R, RFut, W, WFut> (&self, dir: &str, new_entries: String, current: &BTreeMap<K, V>, origins: &BTreeMap<K, Origin>, read: R, write: W) -> Result<()> where
+        K: Ord + Clone,
+        V: Clone + PartialEq,
+        I: IntoIterator<Item = (K, V)>,
+        R: Fn(PathBuf) -> RFut,
+        RFut: Future<Output = Result<I>>,
+        W: Fn(PathBuf, Vec<(K, V)>) -> WFut,
+        WFut: Future<Output = Result<()>>
+    {
+        let target = self.target.join(dir);
+        let files = self.base.files(dir).await?;
+        let parsed = futures::future::try_join_all(files.iter().map(|x| read(x.file.clone()))).await?
+            .into_iter()
+            .map(|x| x.into_iter().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        // file on which every key is finally defined, since later keys override earlier ones
+        let mut effective = BTreeMap::new();
+        for (i, entries) in parsed.iter().enumerate() {
+            for (key, _) in entries.iter() {
+                effective.insert(key, i);
+            }
+        }
+
+        let mut written = BTreeSet::new();
+        for (i, (file, entries)) in files.iter().zip(parsed.iter()).enumerate() {
+            let mut changed = false;
+            let mut result = Vec::with_capacity(entries.len());
+            for (key, value) in entries.iter() {
+                match current.get(key) {
+                    Some(current) => {
+                        changed |= effective.get(key) == Some(&i) && current != value;
+                        result.push((key.clone(), current.clone()));
+                    },
+                    None => changed = true
+                }
+            }
+
+            let name = match file.file_name() {
+                Some(name) => name.to_os_string(),
+                None => continue
+            };
+
+            let path = target.join(&name);
+            if changed {
+                result.sort_by(|(lhs, _), (rhs, _)| lhs.cmp(rhs));
+                write(path, result).await?;
+            } else {
+                remove_stale(&path).await?;
+            }
+            written.insert(name);
+        }
+
+        // entries that don't exist on the base layers
+        let mut groups = BTreeMap::<OsString, Vec<(K, V)>>::new();
+        groups.insert(OsString::from(&new_entries), Vec::new());
+
+        for origin in origins.values().filter(|x| Some(x.layer) == self.layer) {
+            if let Some(name) = origin.file_name().filter(|x| !written.contains(*x)) {
+                groups.entry(name.to_os_string()).or_default();
+            }
+        }
+
+        for (key, value) in current.iter().filter(|(key, _)| !effective.contains_key(key)) {
+            let name = match origins.get(key) {
+                Some(origin) if Some(origin.layer) == self.layer => origin.file_name()
+                    .filter(|x| !written.contains(*x))
+                    .map(|x| x.to_os_string()),
+                _ => None
+            };
+
+            groups.entry(name.unwrap_or_else(|| OsString::from(&new_entries)))
+                .or_default()
+                .push((key.clone(), value.clone()));
+        }
+
+        for (name, entries) in groups {
+            let path = target.join(name);
+            if entries.is_empty() {
+                remove_stale(&path).await?;
+            } else {
+                write(path, entries).await?;
+            }
+        }
+
+        return Ok(())
+    }
+}
+
+#[inline]
+async fn remove_stale (path: &Path) -> std::io::Result<()> {
+    match tokio::fs::remove_file(path).await {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+        _ => Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::{Path, PathBuf}, collections::BTreeMap};
+    use crate::{utils::serde_vec_map::VecMap, data::{parse_path, write_to_path, GamePaths, GameLayers, Origin}};
+    use super::{SaveContext, Mod, ModMetadata};
+
+    const DIR: &str = "common/test";
+
+    /// Saves `current` as a mod on top of a game whose `DIR` has `a.txt` (`x = 1 y = 2`) and `b.txt` (`z = 3`).
+    /// Entries on `own` were loaded from that file of the mod, which is the layer after the game's
+    async fn save (root: &Path, current: &[(&str, u32)], own: &[(&str, &str)]) -> PathBuf {
+        let game = root.join("game");
+        tokio::fs::create_dir_all(game.join(DIR)).await.unwrap();
+        tokio::fs::write(game.join(DIR).join("a.txt"), "x = 1\ny = 2").await.unwrap();
+        tokio::fs::write(game.join(DIR).join("b.txt"), "z = 3").await.unwrap();
+
+        let target = root.join("mod");
+        let layer = match own.is_empty() {
+            true => None,
+            false => Some(1)
+        };
+
+        let ctx = SaveContext { base: GameLayers::new(GamePaths::new(game), Vec::new()), layer, target: &target };
+        let current = current.iter().map(|(k, v)| (k.to_string(), *v)).collect::<BTreeMap<_, _>>();
+        let origins = own.iter().map(|(k, file)| (k.to_string(), Origin { layer: 1, file: target.join(DIR).join(file) })).collect::<BTreeMap<_, _>>();
+
+        ctx.save(
+            DIR, "zz_test.txt".to_string(), &current, &origins,
+            |path| async move { parse_path::<BTreeMap<String, u32>>(path).await },
+            |path, entries| async move { write_to_path(path, &VecMap(&entries)).await }
+        ).await.unwrap();
+        return target.join(DIR)
+    }
+
+    #[tokio::test]
+    async fn metadata_round_trip () {
+        let root = std::env::temp_dir().join(format!("vicky3-mod-metadata-{}", std::process::id()));
+        let _ = tokio::fs::remove_dir_all(&root).await;
+        assert!(!Mod::is_mod(&root));
+
+        let mut metadata = ModMetadata::new("Test", "test");
+        metadata.game_custom_data.replace_paths.push("common/religions".to_string());
+        let project = Mod::new(root.clone(), metadata);
+        project.save_metadata().await.unwrap();
+
+        assert!(Mod::is_mod(&root));
+        assert_eq!(Mod::open(root.clone()).await.unwrap(), project);
+
+        // fields missing on the file take their defaults
+        tokio::fs::write(Mod::metadata_path(&root), r#"{ "name": "Minimal", "id": "minimal" }"#).await.unwrap();
+        let opened = Mod::open(root.clone()).await.unwrap();
+        assert_eq!(opened.metadata.name, "Minimal");
+        assert!(opened.metadata.replace_paths().is_empty());
+
+        tokio::fs::remove_dir_all(&root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn writes_changed_files () {
+        let root = std::env::temp_dir().join(format!("vicky3-mod-save-{}", std::process::id()));
+        let _ = tokio::fs::remove_dir_all(&root).await;
+
+        let dir = save(&root, &[("x", 1), ("y", 5), ("z", 3), ("w", 4)], &[]).await;
+        assert_eq!(parse_path::<BTreeMap<String, u32>>(dir.join("a.txt")).await.unwrap(), BTreeMap::from([("x".to_string(), 1), ("y".to_string(), 5)]));
+        assert_eq!(parse_path::<BTreeMap<String, u32>>(dir.join("zz_test.txt")).await.unwrap(), BTreeMap::from([("w".to_string(), 4)]));
+        assert!(!dir.join("b.txt").exists());
+
+        // files that no longer differ from the game are removed
+        save(&root, &[("x", 1), ("y", 2), ("z", 3)], &[]).await;
+        assert!(!dir.join("a.txt").exists());
+        assert!(!dir.join("zz_test.txt").exists());
+
+        // new entries stay on the mod's file they were loaded from
+        save(&root, &[("x", 1), ("y", 2), ("z", 3), ("v", 6), ("w", 4)], &[("w", "mine.txt")]).await;
+        assert_eq!(parse_path::<BTreeMap<String, u32>>(dir.join("mine.txt")).await.unwrap(), BTreeMap::from([("w".to_string(), 4)]));
+        assert_eq!(parse_path::<BTreeMap<String, u32>>(dir.join("zz_test.txt")).await.unwrap(), BTreeMap::from([("v".to_string(), 6)]));
+
+        // and the file is removed once it has none
+        save(&root, &[("x", 1), ("y", 2), ("z", 3), ("v", 6)], &[("w", "mine.txt")]).await;
+        assert!(!dir.join("mine.txt").exists());
+
+        tokio::fs::remove_dir_all(&root).await.unwrap();
+    }
+}
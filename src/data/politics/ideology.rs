@@ -0,0 +1,213 @@
+use std::{path::Path, collections::{HashMap, BTreeMap}, str::FromStr};
+use eframe::egui::{Ui, ComboBox, RichText, Grid, CollapsingHeader, Color32};
+use serde::{Serialize, Deserialize};
+use crate::{Result, utils::{list::ListEntry, attribute_bool, serde_vec_map::VecMap}, data::{parse_path, write_to_path, Game, GameLayers, Loaded, ScriptValue, law::Law}};
+
+#[derive(Debug, Clone, PartialEq, Default)]
+#[non_exhaustive]
+pub struct Ideology {
+    pub icon: Option<String>,
+    /// whether characters can have the ideology, instead of only interest groups
+    pub character_ideology: bool,
+    /// Stances on the laws of every law group the ideology cares about, in file order
+    pub law_stances: Vec<LawGroupStances>,
+    pub other: Vec<(String, ScriptValue)>
+}
+
+/// Stances of an ideology on the laws of a group (e.g. `lawgroup_governance_principles = { law_monarchy = approve }`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct LawGroupStances {
+    pub group: String,
+    pub laws: Vec<(String, LawStance)>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LawStance {
+    StronglyDisapprove,
+    Disapprove,
+    Neutral,
+    Approve,
+    StronglyApprove
+}
+
+impl LawStance {
+    pub const ALL: [Self; 5] = [Self::StronglyDisapprove, Self::Disapprove, Self::Neutral, Self::Approve, Self::StronglyApprove];
+
+    /// Name of the stance in the game files
+    #[inline]
+    pub fn as_str (self) -> &'static str {
+        return match self {
+            Self::StronglyDisapprove => "strongly_disapprove",
+            Self::Disapprove => "disapprove",
+            Self::Neutral => "neutral",
+            Self::Approve => "approve",
+            Self::StronglyApprove => "strongly_approve"
+        }
+    }
+
+    #[inline]
+    pub fn color (self) -> Color32 {
+        return match self {
+            Self::StronglyDisapprove => Color32::RED,
+            Self::Disapprove => Color32::LIGHT_RED,
+            Self::Neutral => Color32::GRAY,
+            Self::Approve => Color32::LIGHT_GREEN,
+            Self::StronglyApprove => Color32::GREEN
+        }
+    }
+}
+
+impl FromStr for LawStance {
+    type Err = ();
+
+    #[inline]
+    fn from_str (s: &str) -> std::result::Result<Self, Self::Err> {
+        return Self::ALL.into_iter().find(|x| x.as_str() == s).ok_or(())
+    }
+}
+
+impl Ideology {
+    pub const PATH: &'static str = "common/ideologies";
+
+    /// Stance of the ideology on `law`, if it has one
+    #[inline]
+    pub fn stance (&self, law: &str) -> Option<LawStance> {
+        return self.law_stances.iter()
+            .flat_map(|x| x.laws.iter())
+            .find(|(name, _)| name == law)
+            .map(|(_, stance)| *stance)
+    }
+
+    pub fn from_entries (entries: Vec<(String, ScriptValue)>) -> Self {
+        let mut this = Self::default();
+        for (key, value) in entries {
+            match (key.as_str(), &value) {
+                ("icon", ScriptValue::Scalar(x)) => this.icon = Some(x.clone()),
+                ("character_ideology", x) if x.as_bool().is_some() => this.character_ideology = x.as_bool().unwrap(),
+                (group, ScriptValue::Block(laws)) if group.starts_with("lawgroup_") => {
+                    let stances = laws.iter()
+                        .map(|(law, stance)| Some((law.clone(), stance.parse::<LawStance>()?)))
+                        .collect::<Option<Vec<_>>>();
+
+                    match stances {
+                        Some(laws) => this.law_stances.push(LawGroupStances { group: key, laws }),
+                        None => this.other.push((key, value))
+                    }
+                },
+                _ => this.other.push((key, value))
+            }
+        }
+        return this
+    }
+
+    pub fn to_entries (&self) -> Vec<(String, ScriptValue)> {
+        let mut result = Vec::with_capacity(self.law_stances.len() + self.other.len() + 2);
+        if let Some(ref icon) = self.icon {
+            result.push(("icon".to_string(), ScriptValue::Scalar(icon.clone())));
+        }
+        if self.character_ideology {
+            result.push(("character_ideology".to_string(), ScriptValue::bool(true)));
+        }
+
+        for stances in self.law_stances.iter() {
+            let laws = stances.laws.iter()
+                .map(|(law, stance)| (law.clone(), ScriptValue::Scalar(stance.as_str().to_string())))
+                .collect();
+            result.push((stances.group.clone(), ScriptValue::Block(laws)));
+        }
+
+        result.extend(self.other.iter().cloned());
+        return result
+    }
+
+    #[inline]
+    pub async fn from_path (path: impl AsRef<Path>) -> Result<HashMap<String, Self>> {
+        return parse_path(path).await
+    }
+
+    #[inline]
+    pub async fn to_path<'a> (path: impl AsRef<Path>, entries: impl IntoIterator<Item = (&'a String, &'a Self)>) -> Result<()> {
+        let entries = entries.into_iter().collect::<Vec<_>>();
+        return write_to_path(path, &VecMap(&entries)).await
+    }
+
+    #[inline]
+    pub async fn from_game (game: &GameLayers) -> Result<Loaded<String, Self>> {
+        return game.load(Self::PATH, Self::from_path).await
+    }
+}
+
+impl ListEntry for Ideology {
+    #[inline]
+    fn color (&self) -> Option<eframe::epaint::Color32> {
+        None
+    }
+
+    fn render_info (&mut self, ui: &mut Ui, game: &Game) {
+        let laws = game.laws.laws.borrow();
+        attribute_bool(ui, "Character ideology", &mut self.character_ideology);
+
+        for stances in self.law_stances.iter_mut() {
+            CollapsingHeader::new(RichText::new(&stances.group).strong()).show(ui, |ui| {
+                render_stances(ui, stances, &laws);
+            });
+        }
+    }
+}
+
+/// Shows the stance on every law of the group, linking each of them to its definition
+fn render_stances (ui: &mut Ui, stances: &mut LawGroupStances, laws: &BTreeMap<String, Law>) {
+    Grid::new(("law_stances", &stances.group)).striped(true).show(ui, |ui| {
+        for (law, stance) in stances.laws.iter_mut() {
+            match laws.get(law.as_str()) {
+                Some(def) if def.group == stances.group => ui.label(law.as_str()).on_hover_text(format!("Progressiveness: {}", def.progressiveness)),
+                Some(def) => ui.label(RichText::new(law.as_str()).color(Color32::YELLOW)).on_hover_text(format!("Law belongs to {}", def.group)),
+                None => ui.label(RichText::new(law.as_str()).color(Color32::LIGHT_RED)).on_hover_text("Unknown law")
+            };
+
+            ComboBox::from_id_source(("law_stance", &stances.group, law.as_str()))
+                .selected_text(RichText::new(stance.as_str()).color(stance.color()))
+                .show_ui(ui, |ui| {
+                    for value in LawStance::ALL {
+                        ui.selectable_value(stance, value, RichText::new(value.as_str()).color(value.color()));
+                    }
+                });
+            ui.end_row();
+        }
+    });
+}
+
+impl Serialize for Ideology {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> where S: serde::Serializer {
+        return VecMap(&self.to_entries()).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Ideology {
+    #[inline]
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error> where D: serde::Deserializer<'de> {
+        let entries = crate::utils::serde_vec_map::deserialize::<'de, String, ScriptValue, _>(deserializer)?;
+        return Ok(Self::from_entries(entries))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{round_trip, keys};
+    use super::{Ideology, LawStance};
+
+    #[test]
+    fn law_stances_round_trip () {
+        let ideologies = round_trip::<String, Ideology>(b"ideology_paternalistic = {
+            icon = \"gfx/paternalistic.dds\"
+            lawgroup_governance_principles = { law_monarchy = strongly_approve law_council_republic = strongly_disapprove }
+            possible = { always = yes }
+        }");
+
+        let ideology = &ideologies["ideology_paternalistic"];
+        assert_eq!(ideology.stance("law_monarchy"), Some(LawStance::StronglyApprove));
+        assert_eq!(ideology.law_stances.len(), 1);
+        assert_eq!(keys(&ideology.other), ["possible"]);
+    }
+}
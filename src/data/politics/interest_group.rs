@@ -0,0 +1,147 @@
+use std::{path::Path, collections::HashMap};
+use eframe::egui::Ui;
+use serde::{Serialize, Deserialize, ser::SerializeMap, de::{Visitor, Error as _}};
+use crate::{Result, utils::{list::ListEntry, attribute_list, attribute_num, serde_vec_map::VecMap}, data::{Color, ScriptValue, parse_path, write_to_path, Game, GameLayers, Loaded}};
+
+/// Interest group. Like pop types, the typed fields are read and written with their own (de)serializers so the color keeps its syntax
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct InterestGroup {
+    pub color: Color,
+    pub texture: Option<Box<Path>>,
+    pub layer: Option<String>,
+    /// order of the group on the politics panel
+    pub index: u32,
+    /// ideologies that every instance of the group has
+    pub ideologies: Box<[String]>,
+    /// ideologies that the group's leaders can get
+    pub character_ideologies: Box<[String]>,
+    pub other: Vec<(String, ScriptValue)>
+}
+
+impl ListEntry for InterestGroup {
+    #[inline]
+    fn color (&self) -> Option<eframe::epaint::Color32> {
+        Some(self.color.into())
+    }
+
+    #[inline]
+    fn render_info (&mut self, ui: &mut Ui, game: &Game) {
+        self.color.render(ui);
+        attribute_num(ui, "Index", &mut self.index, None);
+        attribute_list(ui, "Ideologies", self.ideologies.iter_mut());
+        attribute_list(ui, "Character ideologies", self.character_ideologies.iter_mut());
+    }
+}
+
+impl InterestGroup {
+    pub const PATH: &'static str = "common/interest_groups";
+
+    #[inline]
+    pub async fn from_path (path: impl AsRef<Path>) -> Result<HashMap<String, Self>> {
+        return parse_path(path).await
+    }
+
+    #[inline]
+    pub async fn to_path<'a> (path: impl AsRef<Path>, entries: impl IntoIterator<Item = (&'a String, &'a Self)>) -> Result<()> {
+        let entries = entries.into_iter().collect::<Vec<_>>();
+        return write_to_path(path, &VecMap(&entries)).await
+    }
+
+    #[inline]
+    pub async fn from_game (game: &GameLayers) -> Result<Loaded<String, Self>> {
+        return game.load(Self::PATH, Self::from_path).await
+    }
+}
+
+impl Serialize for InterestGroup {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> where S: serde::Serializer {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("color", &self.color)?;
+        if let Some(ref texture) = self.texture {
+            map.serialize_entry("texture", texture)?;
+        }
+        if let Some(ref layer) = self.layer {
+            map.serialize_entry("layer", layer)?;
+        }
+        map.serialize_entry("index", &self.index)?;
+        for (key, list) in [("ideologies", &self.ideologies), ("character_ideologies", &self.character_ideologies)] {
+            if !list.is_empty() {
+                map.serialize_entry(key, list)?;
+            }
+        }
+        for (key, value) in self.other.iter() {
+            map.serialize_entry(key, value)?;
+        }
+        return map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for InterestGroup {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error> where D: serde::Deserializer<'de> {
+        struct LocalVisitor;
+        impl<'de> Visitor<'de> for LocalVisitor {
+            type Value = InterestGroup;
+
+            #[inline]
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "an interest group")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> std::result::Result<Self::Value, A::Error> where A: serde::de::MapAccess<'de> {
+                let (mut color, mut texture, mut layer, mut index) = (None, None, None, 0);
+                let (mut ideologies, mut character_ideologies) = (Box::default(), Box::default());
+                let mut other = Vec::new();
+
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "color" => color = Some(map.next_value()?),
+                        "texture" => texture = Some(map.next_value()?),
+                        "layer" => layer = Some(map.next_value()?),
+                        "index" => index = map.next_value()?,
+                        "ideologies" => ideologies = map.next_value()?,
+                        "character_ideologies" => character_ideologies = map.next_value()?,
+                        _ => other.push((key, map.next_value()?))
+                    }
+                }
+
+                return Ok(InterestGroup {
+                    color: color.ok_or_else(|| A::Error::missing_field("color"))?,
+                    texture,
+                    layer,
+                    index,
+                    ideologies,
+                    character_ideologies,
+                    other
+                })
+            }
+        }
+
+        return deserializer.deserialize_map(LocalVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{round_trip, keys};
+    use super::InterestGroup;
+
+    #[test]
+    fn keeps_unknown_fields () {
+        let groups = round_trip::<String, InterestGroup>(b"
+            ig_landowners = {
+                color = hsv { 0.1 0.5 0.6 }
+                texture = \"gfx/interface/icons/ig_icons/landowners.dds\"
+                index = 2
+                ideologies = { ideology_paternalistic ideology_traditionalist }
+                pop_weight = { value = 0 }
+                on_enable = { add_ideology = ideology_agrarian }
+            }
+        ");
+
+        let group = &groups["ig_landowners"];
+        assert_eq!(group.index, 2);
+        assert_eq!(group.ideologies.len(), 2);
+        assert_eq!(keys(&group.other), ["pop_weight", "on_enable"]);
+    }
+}
@@ -0,0 +1,11 @@
+use std::{collections::{BTreeMap}};
+use crate::utils::refcell::RefCell;
+
+flat_mod! { interest_group, ideology, movement }
+
+#[derive(Debug)]
+pub struct GamePolitics {
+    pub interest_groups: RefCell<BTreeMap<String, InterestGroup>>,
+    pub ideologies: RefCell<BTreeMap<String, Ideology>>,
+    pub movements: RefCell<BTreeMap<String, PoliticalMovement>>
+}
@@ -0,0 +1,102 @@
+use std::{path::Path, collections::HashMap};
+use eframe::egui::{Ui, ComboBox, RichText};
+use serde::{Serialize, Deserialize};
+use crate::{Result, utils::{list::ListEntry, attribute_list, serde_vec_map::VecMap}, data::{parse_path, write_to_path, Game, GameLayers, Loaded, ScriptValue}};
+
+#[derive(Debug, Clone, PartialEq, Default)]
+#[non_exhaustive]
+pub struct PoliticalMovement {
+    pub category: Option<String>,
+    /// ideology that the movement pushes for
+    pub ideology: Option<String>,
+    pub character_ideologies: Box<[String]>,
+    pub other: Vec<(String, ScriptValue)>
+}
+
+impl ListEntry for PoliticalMovement {
+    #[inline]
+    fn color (&self) -> Option<eframe::epaint::Color32> {
+        None
+    }
+
+    fn render_info (&mut self, ui: &mut Ui, game: &Game) {
+        let ideologies = game.politics.ideologies.borrow();
+        ComboBox::from_label(RichText::new("Ideology").strong())
+            .selected_text(self.ideology.as_deref().unwrap_or("None"))
+            .show_ui(ui, |ui| {
+                if ui.selectable_label(self.ideology.is_none(), "None").clicked() {
+                    self.ideology = None;
+                }
+                for name in ideologies.keys() {
+                    if ui.selectable_label(self.ideology.as_ref() == Some(name), name.as_str()).clicked() {
+                        self.ideology = Some(name.clone());
+                    }
+                }
+            });
+
+        attribute_list(ui, "Character ideologies", self.character_ideologies.iter_mut());
+    }
+}
+
+impl PoliticalMovement {
+    pub const PATH: &'static str = "common/political_movements";
+
+    pub fn from_entries (entries: Vec<(String, ScriptValue)>) -> Self {
+        let mut this = Self::default();
+        for (key, value) in entries {
+            match (key.as_str(), value) {
+                ("category", ScriptValue::Scalar(x)) => this.category = Some(x),
+                ("ideology", ScriptValue::Scalar(x)) => this.ideology = Some(x),
+                ("character_ideologies", x) if x.strings().is_some() => this.character_ideologies = x.strings().unwrap(),
+                (_, value) => this.other.push((key, value))
+            }
+        }
+        return this
+    }
+
+    pub fn to_entries (&self) -> Vec<(String, ScriptValue)> {
+        let mut result = Vec::with_capacity(self.other.len() + 3);
+        if let Some(ref category) = self.category {
+            result.push(("category".to_string(), ScriptValue::scalar(category)));
+        }
+        if let Some(ref ideology) = self.ideology {
+            result.push(("ideology".to_string(), ScriptValue::scalar(ideology)));
+        }
+        if !self.character_ideologies.is_empty() {
+            result.push(("character_ideologies".to_string(), ScriptValue::list(self.character_ideologies.iter())));
+        }
+        result.extend(self.other.iter().cloned());
+        return result
+    }
+
+    #[inline]
+    pub async fn from_path (path: impl AsRef<Path>) -> Result<HashMap<String, Self>> {
+        return parse_path(path).await
+    }
+
+    #[inline]
+    pub async fn to_path<'a> (path: impl AsRef<Path>, entries: impl IntoIterator<Item = (&'a String, &'a Self)>) -> Result<()> {
+        let entries = entries.into_iter().collect::<Vec<_>>();
+        return write_to_path(path, &VecMap(&entries)).await
+    }
+
+    #[inline]
+    pub async fn from_game (game: &GameLayers) -> Result<Loaded<String, Self>> {
+        return game.load(Self::PATH, Self::from_path).await
+    }
+}
+
+impl Serialize for PoliticalMovement {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> where S: serde::Serializer {
+        return VecMap(&self.to_entries()).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PoliticalMovement {
+    #[inline]
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error> where D: serde::Deserializer<'de> {
+        let entries = crate::utils::serde_vec_map::deserialize::<'de, String, ScriptValue, _>(deserializer)?;
+        return Ok(Self::from_entries(entries))
+    }
+}
@@ -0,0 +1,203 @@
+use std::{path::Path, collections::HashMap};
+use eframe::egui::{Ui, ComboBox, RichText};
+use serde::{Serialize, Deserialize, ser::SerializeMap, de::{Visitor, Error as _}};
+use crate::{Result, utils::{list::ListEntry, attribute_bool, attribute_num, serde_vec_map::VecMap}};
+use super::{Color, ScriptValue, parse_path, write_to_path, Game, GameLayers, Loaded};
+
+/// Type of a pop. Since colors are written in their own syntax (e.g. `rgb { 255 0 0 }`), the typed fields are read and written with their own
+/// (de)serializers instead of going through [`ScriptValue`], while the rest are kept as is
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct PopType {
+    pub texture: Box<Path>,
+    pub color: Color,
+    pub strata: Strata,
+    /// share of the building's wages that the pop type gets
+    pub wage_weight: f32,
+    /// literacy that pops of this type tend towards
+    pub literacy_target: f32,
+    pub is_slave: bool,
+    /// whether pops of this type can be enslaved
+    pub can_be_slave: bool,
+    pub unemployment: bool,
+    pub other: Vec<(String, ScriptValue)>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Strata {
+    Poor,
+    Middle,
+    Rich
+}
+
+impl Strata {
+    pub const ALL: [Self; 3] = [Self::Poor, Self::Middle, Self::Rich];
+
+    /// Name of the strata in the game files
+    #[inline]
+    pub fn as_str (self) -> &'static str {
+        return match self {
+            Self::Poor => "poor",
+            Self::Middle => "middle",
+            Self::Rich => "rich"
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Strata {
+    #[inline]
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error> where D: serde::Deserializer<'de> {
+        let value = <&str as Deserialize>::deserialize(deserializer)?;
+        return Self::ALL.into_iter()
+            .find(|x| x.as_str() == value)
+            .ok_or_else(|| <D::Error as serde::de::Error>::unknown_variant(value, &["poor", "middle", "rich"]))
+    }
+}
+
+impl Serialize for PopType {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> where S: serde::Serializer {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("texture", &self.texture)?;
+        map.serialize_entry("color", &self.color)?;
+        map.serialize_entry("strata", &self.strata)?;
+        if self.wage_weight != 0.0 {
+            map.serialize_entry("wage_weight", &self.wage_weight)?;
+        }
+        if self.literacy_target != 0.0 {
+            map.serialize_entry("literacy_target", &self.literacy_target)?;
+        }
+        for (key, value) in [("is_slave", self.is_slave), ("can_be_slave", self.can_be_slave), ("unemployment", self.unemployment)] {
+            if value {
+                map.serialize_entry(key, &value)?;
+            }
+        }
+        for (key, value) in self.other.iter() {
+            map.serialize_entry(key, value)?;
+        }
+        return map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for PopType {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error> where D: serde::Deserializer<'de> {
+        struct LocalVisitor;
+        impl<'de> Visitor<'de> for LocalVisitor {
+            type Value = PopType;
+
+            #[inline]
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "a pop type")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> std::result::Result<Self::Value, A::Error> where A: serde::de::MapAccess<'de> {
+                let (mut texture, mut color, mut strata) = (None, None, None);
+                let (mut wage_weight, mut literacy_target) = (0.0, 0.0);
+                let (mut is_slave, mut can_be_slave, mut unemployment) = (false, false, false);
+                let mut other = Vec::new();
+
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "texture" => texture = Some(map.next_value()?),
+                        "color" => color = Some(map.next_value()?),
+                        "strata" => strata = Some(map.next_value()?),
+                        "wage_weight" => wage_weight = map.next_value()?,
+                        "literacy_target" => literacy_target = map.next_value()?,
+                        "is_slave" => is_slave = map.next_value()?,
+                        "can_be_slave" => can_be_slave = map.next_value()?,
+                        "unemployment" => unemployment = map.next_value()?,
+                        _ => other.push((key, map.next_value()?))
+                    }
+                }
+
+                return Ok(PopType {
+                    texture: texture.ok_or_else(|| A::Error::missing_field("texture"))?,
+                    color: color.ok_or_else(|| A::Error::missing_field("color"))?,
+                    strata: strata.ok_or_else(|| A::Error::missing_field("strata"))?,
+                    wage_weight,
+                    literacy_target,
+                    is_slave,
+                    can_be_slave,
+                    unemployment,
+                    other
+                })
+            }
+        }
+
+        return deserializer.deserialize_map(LocalVisitor)
+    }
+}
+
+impl ListEntry for PopType {
+    #[inline]
+    fn color (&self) -> Option<eframe::epaint::Color32> {
+        Some(self.color.into())
+    }
+
+    fn render_info (&mut self, ui: &mut Ui, game: &Game) {
+        self.color.render(ui);
+
+        ComboBox::from_label(RichText::new("Strata").strong())
+            .selected_text(self.strata.as_str())
+            .show_ui(ui, |ui| {
+                for strata in Strata::ALL {
+                    ui.selectable_value(&mut self.strata, strata, strata.as_str());
+                }
+            });
+
+        attribute_num(ui, "Wage weight", &mut self.wage_weight, Some(0.0..=f32::MAX));
+        attribute_num(ui, "Literacy target", &mut self.literacy_target, Some(0.0..=1.0));
+        attribute_bool(ui, "Slave", &mut self.is_slave);
+        attribute_bool(ui, "Can be enslaved", &mut self.can_be_slave);
+        attribute_bool(ui, "Can be unemployed", &mut self.unemployment);
+    }
+}
+
+impl PopType {
+    pub const PATH: &'static str = "common/pop_types";
+
+    #[inline]
+    pub async fn from_path (path: impl AsRef<Path>) -> Result<HashMap<String, Self>> {
+        return parse_path(path).await
+    }
+
+    #[inline]
+    pub async fn to_path<'a> (path: impl AsRef<Path>, entries: impl IntoIterator<Item = (&'a String, &'a Self)>) -> Result<()> {
+        let entries = entries.into_iter().collect::<Vec<_>>();
+        return write_to_path(path, &VecMap(&entries)).await
+    }
+
+    #[inline]
+    pub async fn from_game (game: &GameLayers) -> Result<Loaded<String, Self>> {
+        return game.load(Self::PATH, Self::from_path).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{round_trip, keys, ser, Color};
+    use super::{PopType, Strata};
+
+    #[test]
+    fn keeps_unknown_fields () {
+        let pop_types = round_trip::<String, PopType>(b"
+            laborers = {
+                texture = \"gfx/interface/icons/pops_icons/laborers.dds\"
+                color = rgb { 154 120 92 }
+                strata = poor
+                wage_weight = 1
+                unemployment = yes
+                consumption_mult = 0.5
+                qualifications = { add = 10 }
+            }
+        ");
+
+        let laborers = &pop_types["laborers"];
+        assert_eq!(laborers.strata, Strata::Poor);
+        assert!(matches!(laborers.color, Color::RgbInt(_)));
+        assert_eq!(laborers.wage_weight, 1.0);
+        assert!(laborers.unemployment && !laborers.is_slave);
+        assert_eq!(keys(&laborers.other), ["consumption_mult", "qualifications"]);
+        assert!(ser::to_string(&pop_types).unwrap().contains("rgb{ 154 120 92 }"));
+    }
+}
@@ -0,0 +1,166 @@
+use std::{fmt::Display, str::FromStr, ops::{Deref, DerefMut}};
+use serde::{Serialize, Deserialize, ser::SerializeSeq};
+use super::RgbIntColor;
+
+/// Province, identified by its color on `provinces.png` (e.g. `x5A91EB`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ProvinceId (u32);
+
+/// Province name that isn't an `x` followed by six hex digits
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidProvinceId (pub String);
+
+impl ProvinceId {
+    #[inline]
+    pub const fn from_rgb ([r, g, b]: [u8; 3]) -> Self {
+        return Self(((r as u32) << 16) | ((g as u32) << 8) | b as u32)
+    }
+
+    #[inline]
+    pub const fn rgb (self) -> [u8; 3] {
+        let [_, r, g, b] = self.0.to_be_bytes();
+        return [r, g, b]
+    }
+}
+
+impl FromStr for ProvinceId {
+    type Err = InvalidProvinceId;
+
+    /// Parses `x5A91EB`, ignoring the casing of the prefix and hex digits
+    fn from_str (s: &str) -> Result<Self, Self::Err> {
+        let digits = match s.strip_prefix(['x', 'X']) {
+            Some(x) if x.len() == 6 && x.bytes().all(|x| x.is_ascii_hexdigit()) => x,
+            _ => return Err(InvalidProvinceId(s.to_string()))
+        };
+        return u32::from_str_radix(digits, 16).map(Self).map_err(|_| InvalidProvinceId(s.to_string()))
+    }
+}
+
+impl Display for ProvinceId {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "x{:06X}", self.0)
+    }
+}
+
+impl Display for InvalidProvinceId {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid province id '{}'", self.0)
+    }
+}
+
+impl std::error::Error for InvalidProvinceId {}
+
+impl From<RgbIntColor> for ProvinceId {
+    #[inline]
+    fn from(color: RgbIntColor) -> Self {
+        return Self::from_rgb(*color.as_array())
+    }
+}
+
+impl From<ProvinceId> for RgbIntColor {
+    #[inline]
+    fn from(id: ProvinceId) -> Self {
+        let [red, green, blue] = id.rgb();
+        return Self { red, green, blue }
+    }
+}
+
+impl Serialize for ProvinceId {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: serde::Serializer {
+        return serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ProvinceId {
+    #[inline]
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: serde::Deserializer<'de> {
+        let s = <std::borrow::Cow<'de, str> as Deserialize<'de>>::deserialize(deserializer)?;
+        return s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// List of provinces that keeps the names that aren't valid ids, so that they can be reported by validation
+/// instead of failing to load the whole file.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Provinces {
+    pub ids: Vec<ProvinceId>,
+    /// Names that couldn't be parsed, as written on the files
+    pub invalid: Vec<String>
+}
+
+impl Deref for Provinces {
+    type Target = Vec<ProvinceId>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        return &self.ids
+    }
+}
+
+impl DerefMut for Provinces {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        return &mut self.ids
+    }
+}
+
+impl FromIterator<ProvinceId> for Provinces {
+    #[inline]
+    fn from_iter<T: IntoIterator<Item = ProvinceId>>(iter: T) -> Self {
+        return Self { ids: iter.into_iter().collect(), invalid: Vec::new() }
+    }
+}
+
+impl Serialize for Provinces {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: serde::Serializer {
+        let mut ser = serializer.serialize_seq(Some(self.ids.len() + self.invalid.len()))?;
+        for id in self.ids.iter() {
+            ser.serialize_element(id)?;
+        }
+        for name in self.invalid.iter() {
+            ser.serialize_element(name)?;
+        }
+        return ser.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Provinces {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: serde::Deserializer<'de> {
+        let names = <Vec<String> as Deserialize<'de>>::deserialize(deserializer)?;
+        let mut result = Self { ids: Vec::with_capacity(names.len()), invalid: Vec::new() };
+        for name in names {
+            match name.parse() {
+                Ok(id) => result.ids.push(id),
+                Err(InvalidProvinceId(name)) => result.invalid.push(name)
+            }
+        }
+        return Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ProvinceId, Provinces};
+    use crate::data::RgbIntColor;
+
+    #[test]
+    fn parse_and_round_trip () {
+        let id = "x5a91Eb".parse::<ProvinceId>().unwrap();
+        assert_eq!(id.to_string(), "x5A91EB");
+        assert_eq!(RgbIntColor::from(id), RgbIntColor { red: 0x5A, green: 0x91, blue: 0xEB });
+        assert_eq!(ProvinceId::from(RgbIntColor::from(id)), id);
+        assert!("5A91EB".parse::<ProvinceId>().is_err());
+        assert!("x5A91E".parse::<ProvinceId>().is_err());
+        assert!("x5A91EG".parse::<ProvinceId>().is_err());
+
+        let data = "provinces = { x000001 X0000ff oops }";
+        #[derive(serde::Deserialize)]
+        struct Inner { provinces: Provinces }
+        let inner = jomini::text::de::from_utf8_slice::<Inner>(data.as_bytes()).unwrap();
+        assert_eq!(inner.provinces.ids, vec![ProvinceId::from_rgb([0, 0, 1]), ProvinceId::from_rgb([0, 0, 255])]);
+        assert_eq!(inner.provinces.invalid, vec!["oops".to_string()]);
+    }
+}
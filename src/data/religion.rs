@@ -1,44 +1,38 @@
 use std::{path::{Path}, collections::HashMap};
 use eframe::egui::{Ui};
-use futures::{Stream, TryStreamExt};
-use jomini::JominiDeserialize;
-use tokio::task::spawn_blocking;
+use serde::{Serialize, Deserialize, ser::SerializeMap, de::{Visitor, Error as _}};
 use crate::{Result, utils::{list::ListEntry, attribute_list}};
-use crate::utils::{ReadDirStream, FlattenOkIter};
-use super::{Color, read_to_string, Game, GamePaths};
+use crate::utils::serde_vec_map::VecMap;
+use super::{Color, ScriptValue, parse_path, write_to_path, Game, GameLayers, Loaded};
 
-#[derive(Debug, Clone, PartialEq, JominiDeserialize)]
+#[derive(Debug, Clone, PartialEq)]
 #[non_exhaustive]
 pub struct Religion {
     pub texture: Box<Path>,
     // religion traits, different from other kinds of traits
     pub traits: Box<[String]>,
     pub color: Color,
-    #[jomini(default)]
-    pub taboos: Box<[String]>
+    pub taboos: Box<[String]>,
+    pub other: Vec<(String, ScriptValue)>
 }
 
 impl Religion {
+    pub const PATH: &'static str = "common/religions";
+
     #[inline]
     pub async fn from_path (path: impl AsRef<Path>) -> Result<HashMap<String, Self>> {
-        let data = read_to_string(path).await?;
-        return spawn_blocking(move || jomini::text::de::from_utf8_slice(data.as_bytes())).await.unwrap()
+        return parse_path(path).await
     }
 
     #[inline]
-    pub async fn from_game (game: &GamePaths) -> Result<impl Stream<Item = Result<(String, Self)>>> {
-        let path = game.common().join("religions");
-        let iter = ReadDirStream::new(tokio::fs::read_dir(path).await?)
-            .map_err(<jomini::Error as From<std::io::Error>>::from)
-            .try_filter_map(|x: tokio::fs::DirEntry| async move {
-                if x.metadata().await.map_err(jomini::Error::from)?.is_file() {
-                    return Ok(Some(Self::from_path(x.path()).await?))
-                } else {
-                    return Ok(None)
-                }
-            });
+    pub async fn to_path<'a> (path: impl AsRef<Path>, entries: impl IntoIterator<Item = (&'a String, &'a Self)>) -> Result<()> {
+        let entries = entries.into_iter().collect::<Vec<_>>();
+        return write_to_path(path, &VecMap(&entries)).await
+    }
 
-        return Ok(FlattenOkIter::new(iter))
+    #[inline]
+    pub async fn from_game (game: &GameLayers) -> Result<Loaded<String, Self>> {
+        return game.load(Self::PATH, Self::from_path).await
     }
 }
 
@@ -50,8 +44,68 @@ impl ListEntry for Religion {
 
     #[inline]
     fn render_info (&mut self, ui: &mut Ui, game: &Game) {
+        game.textures.borrow_mut().show(ui, &game.layers, &self.texture, [64.0, 64.0]);
         attribute_list(ui, "Traits", self.traits.iter_mut());
         attribute_list(ui, "Taboos", self.taboos.iter_mut());
-        // todo texture
     }
-}
\ No newline at end of file
+
+    #[inline]
+    fn texture (&self) -> Option<&Path> {
+        Some(&self.texture)
+    }
+}
+
+impl Serialize for Religion {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> where S: serde::Serializer {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("texture", &self.texture)?;
+        map.serialize_entry("traits", &self.traits)?;
+        map.serialize_entry("color", &self.color)?;
+        if !self.taboos.is_empty() {
+            map.serialize_entry("taboos", &self.taboos)?;
+        }
+        for (key, value) in self.other.iter() {
+            map.serialize_entry(key, value)?;
+        }
+        return map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Religion {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error> where D: serde::Deserializer<'de> {
+        struct LocalVisitor;
+        impl<'de> Visitor<'de> for LocalVisitor {
+            type Value = Religion;
+
+            #[inline]
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "a religion")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> std::result::Result<Self::Value, A::Error> where A: serde::de::MapAccess<'de> {
+                let (mut texture, mut traits, mut color, mut taboos) = (None, None, None, Box::default());
+                let mut other = Vec::new();
+
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "texture" => texture = Some(map.next_value()?),
+                        "traits" => traits = Some(map.next_value()?),
+                        "color" => color = Some(map.next_value()?),
+                        "taboos" => taboos = map.next_value()?,
+                        _ => other.push((key, map.next_value()?))
+                    }
+                }
+
+                return Ok(Religion {
+                    texture: texture.ok_or_else(|| A::Error::missing_field("texture"))?,
+                    traits: traits.ok_or_else(|| A::Error::missing_field("traits"))?,
+                    color: color.ok_or_else(|| A::Error::missing_field("color"))?,
+                    taboos,
+                    other
+                })
+            }
+        }
+
+        return deserializer.deserialize_map(LocalVisitor)
+    }
+}
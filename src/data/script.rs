@@ -0,0 +1,253 @@
+use std::{fmt::Display, str::FromStr};
+use jomini::text::Property;
+use serde::{Serialize, Deserialize, de::Visitor, ser::{SerializeMap, SerializeSeq}};
+
+/// Untyped script value, which keeps the order and repetitions of its keys
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptValue {
+    Scalar (String),
+    List (Vec<ScriptValue>),
+    Block (Vec<(String, ScriptValue)>),
+    /// Value written with a comparison operator instead of `=` (e.g. `gdp > 1000`)
+    Compare (Operator, Box<ScriptValue>)
+}
+
+/// Operator between a key and its value, other than `=`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    LessThan,
+    LessThanEqual,
+    GreaterThan,
+    GreaterThanEqual,
+    NotEqual,
+    /// `==`
+    Exact,
+    /// `?=`, which is only applied if the scope exists
+    Exists
+}
+
+impl Operator {
+    #[inline]
+    pub fn symbol (self) -> &'static str {
+        return match self {
+            Self::LessThan => "<",
+            Self::LessThanEqual => "<=",
+            Self::GreaterThan => ">",
+            Self::GreaterThanEqual => ">=",
+            Self::NotEqual => "!=",
+            Self::Exact => "==",
+            Self::Exists => "?="
+        }
+    }
+
+    /// `None` for `=` and unknown symbols
+    #[inline]
+    pub fn from_symbol (symbol: &str) -> Option<Self> {
+        return [Self::LessThan, Self::LessThanEqual, Self::GreaterThan, Self::GreaterThanEqual, Self::NotEqual, Self::Exact, Self::Exists]
+            .into_iter()
+            .find(|x| x.symbol() == symbol)
+    }
+}
+
+impl ScriptValue {
+    #[inline]
+    pub fn as_str (&self) -> Option<&str> {
+        return match self {
+            Self::Scalar(x) => Some(x),
+            _ => None
+        }
+    }
+
+    #[inline]
+    pub fn as_bool (&self) -> Option<bool> {
+        return match self.as_str()? {
+            "yes" => Some(true),
+            "no" => Some(false),
+            _ => None
+        }
+    }
+
+    /// Parses a scalar value (e.g. a number)
+    #[inline]
+    pub fn parse<T: FromStr> (&self) -> Option<T> {
+        return self.as_str()?.parse().ok()
+    }
+
+    /// Returns the values of a list. Empty lists are read as empty blocks, and are accepted as both
+    #[inline]
+    pub fn as_list (&self) -> Option<&[ScriptValue]> {
+        return match self {
+            Self::List(x) => Some(x),
+            Self::Block(x) if x.is_empty() => Some(&[]),
+            _ => None
+        }
+    }
+
+    /// Returns the values of a list of scalars (e.g. `{ tech_a tech_b }`)
+    #[inline]
+    pub fn strings (&self) -> Option<Box<[String]>> {
+        return self.as_list()?.iter().map(|x| x.as_str().map(str::to_string)).collect()
+    }
+
+    #[inline]
+    pub fn as_block (&self) -> Option<&[(String, ScriptValue)]> {
+        return match self {
+            Self::Block(x) => Some(x),
+            _ => None
+        }
+    }
+
+    /// Returns the first value of `key`, if this is a block
+    #[inline]
+    pub fn get (&self, key: &str) -> Option<&ScriptValue> {
+        return self.as_block()?.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    #[inline]
+    pub fn bool (value: bool) -> Self {
+        return Self::Scalar(if value { "yes" } else { "no" }.to_string())
+    }
+
+    #[inline]
+    pub fn scalar (value: impl ToString) -> Self {
+        return Self::Scalar(value.to_string())
+    }
+
+    /// List of scalars (e.g. `{ tech_a tech_b }`)
+    #[inline]
+    pub fn list<T: ToString> (values: impl IntoIterator<Item = T>) -> Self {
+        return Self::List(values.into_iter().map(Self::scalar).collect())
+    }
+}
+
+impl Display for ScriptValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Scalar(x) => f.write_str(x),
+            Self::List(list) => {
+                f.write_str("{")?;
+                for value in list.iter() {
+                    write!(f, " {value}")?;
+                }
+                f.write_str(" }")
+            },
+            Self::Block(entries) => {
+                f.write_str("{")?;
+                for (key, value) in entries.iter() {
+                    match value {
+                        Self::Compare(op, value) => write!(f, " {key} {} {value}", op.symbol())?,
+                        value => write!(f, " {key} = {value}")?
+                    }
+                }
+                f.write_str(" }")
+            },
+            Self::Compare(op, value) => write!(f, "{} {value}", op.symbol())
+        }
+    }
+}
+
+impl Serialize for ScriptValue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: serde::Serializer {
+        match self {
+            Self::Scalar(x) => serializer.serialize_str(x),
+            Self::List(list) => {
+                let mut ser = serializer.serialize_seq(Some(list.len()))?;
+                for value in list.iter() {
+                    ser.serialize_element(value)?;
+                }
+                ser.end()
+            },
+            Self::Block(entries) => {
+                let mut ser = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries.iter() {
+                    ser.serialize_entry(key, value)?;
+                }
+                ser.end()
+            },
+            // the script serializer writes the operator in place of the key's `=`
+            Self::Compare(op, value) => serializer.serialize_newtype_variant(crate::data::ser::OPERATOR, 0, op.symbol(), value)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ScriptValue {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: serde::Deserializer<'de> {
+        struct LocalVisitor;
+        impl<'de> Visitor<'de> for LocalVisitor {
+            type Value = ScriptValue;
+
+            #[inline]
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "a script value")
+            }
+
+            #[inline]
+            fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E> where E: serde::de::Error {
+                Ok(ScriptValue::bool(v))
+            }
+
+            #[inline]
+            fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> where E: serde::de::Error {
+                Ok(ScriptValue::Scalar(v.to_string()))
+            }
+
+            #[inline]
+            fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> where E: serde::de::Error {
+                Ok(ScriptValue::Scalar(v.to_string()))
+            }
+
+            #[inline]
+            fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E> where E: serde::de::Error {
+                Ok(ScriptValue::Scalar(v.to_string()))
+            }
+
+            #[inline]
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> where E: serde::de::Error {
+                Ok(ScriptValue::Scalar(v.to_string()))
+            }
+
+            #[inline]
+            fn visit_string<E>(self, v: String) -> Result<Self::Value, E> where E: serde::de::Error {
+                Ok(ScriptValue::Scalar(v))
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error> where A: serde::de::SeqAccess<'de> {
+                let mut result = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+                while let Some(value) = seq.next_element()? {
+                    result.push(value);
+                }
+                return Ok(ScriptValue::List(result))
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error> where A: serde::de::MapAccess<'de> {
+                let mut result = Vec::with_capacity(map.size_hint().unwrap_or_default());
+                while let Some(key) = map.next_key::<String>()? {
+                    // reading the value as a property keeps its operator, which is otherwise read as `=`
+                    let property = map.next_value::<Property<ScriptValue>>()?;
+                    let value = match Operator::from_symbol(property.op().symbol()) {
+                        Some(op) => ScriptValue::Compare(op, Box::new(property.into_value())),
+                        None => property.into_value()
+                    };
+                    result.push((key, value));
+                }
+                return Ok(ScriptValue::Block(result))
+            }
+        }
+
+        return deserializer.deserialize_any(LocalVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ScriptValue;
+
+    #[test]
+    fn keeps_repeated_keys () {
+        let data = "a = { x = 1 x = 2 list = { 3 4 } }";
+        let value = jomini::text::de::from_utf8_slice::<ScriptValue>(data.as_bytes()).unwrap();
+        let a = value.get("a").unwrap();
+        assert_eq!(a.as_block().unwrap().iter().filter(|(k, _)| k == "x").count(), 2);
+        assert_eq!(a.get("list"), Some(&ScriptValue::List(vec![ScriptValue::Scalar("3".into()), ScriptValue::Scalar("4".into())])));
+    }
+}
@@ -0,0 +1,619 @@
+use std::fmt::{Display, Write};
+use serde::{Serialize, ser::{self, Impossible}};
+
+/// Serializes `value` as the contents of a Paradox script file.
+///
+/// The root value must be a map or a struct, and its entries are written as top-level `key = value` pairs.
+#[inline]
+pub fn to_string<T: ?Sized + Serialize> (value: &T) -> Result<String> {
+    let mut ser = Serializer::new();
+    value.serialize(Root(&mut ser))?;
+    ser.output.push('\n');
+    return Ok(ser.output)
+}
+
+/// Name of the newtype variants whose variant is the operator written in place of their key's `=` (e.g. `gdp > 1000`)
+pub(crate) const OPERATOR: &str = "$operator";
+
+pub type Result<T, E = Error> = ::core::result::Result<T, E>;
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The root of a file must be a map or a struct
+    ExpectedRoot,
+    /// Keys must be scalar values
+    ExpectedKey,
+    Unsupported (&'static str),
+    Custom (String)
+}
+
+impl Display for Error {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ExpectedRoot => f.write_str("the root of a script file must be a map or a struct"),
+            Self::ExpectedKey => f.write_str("script keys must be scalar values"),
+            Self::Unsupported(ty) => write!(f, "{ty} cannot be represented in script files"),
+            Self::Custom(msg) => f.write_str(msg)
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    #[inline]
+    fn custom<T>(msg: T) -> Self where T: Display {
+        Self::Custom(msg.to_string())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Serializer {
+    output: String,
+    depth: usize,
+    /// Length of the output before the last key was written, so that it can be removed if its value is empty (e.g. `None`)
+    key_mark: usize
+}
+
+impl Serializer {
+    #[inline]
+    pub fn new () -> Self {
+        return Self::default()
+    }
+
+    #[inline]
+    fn indent (&mut self) {
+        self.output.push('\n');
+        for _ in 0..self.depth {
+            self.output.push('\t');
+        }
+    }
+
+    #[inline]
+    fn write_key<K: ?Sized + Serialize> (&mut self, key: &K, first: bool) -> Result<()> {
+        self.key_mark = self.output.len();
+        if !first || self.depth > 0 {
+            self.indent();
+        }
+        key.serialize(KeySerializer(self))?;
+        self.output.push_str(" = ");
+        return Ok(())
+    }
+
+    /// Writes the value of the last key, returning whether anything was written
+    #[inline]
+    fn write_value<V: ?Sized + Serialize> (&mut self, value: &V) -> Result<bool> {
+        let key_mark = self.key_mark;
+        let start = self.output.len();
+        value.serialize(&mut *self)?;
+        if self.output.len() == start {
+            self.output.truncate(key_mark);
+            return Ok(false)
+        }
+        return Ok(true)
+    }
+
+    fn write_str (&mut self, v: &str) {
+        let is_bare = !v.is_empty() && v.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | ':' | '@' | '-' | '\''));
+        if is_bare {
+            self.output.push_str(v);
+            return
+        }
+
+        self.output.push('"');
+        for c in v.chars() {
+            if matches!(c, '"' | '\\') {
+                self.output.push('\\');
+            }
+            self.output.push(c);
+        }
+        self.output.push('"');
+    }
+
+    #[inline]
+    fn write_display (&mut self, v: impl Display) {
+        let _ = write!(self.output, "{v}");
+    }
+
+    #[inline]
+    fn write_float<F: Display> (&mut self, v: F, is_finite: bool) -> Result<()> {
+        if !is_finite {
+            return Err(Error::Custom(format!("non-finite number '{v}'")))
+        }
+        self.write_display(v);
+        return Ok(())
+    }
+
+    #[inline]
+    fn open_block (&mut self) {
+        self.output.push('{');
+        self.depth += 1;
+    }
+
+    #[inline]
+    fn close_block (&mut self, empty: bool) {
+        self.depth -= 1;
+        if empty {
+            self.output.push_str(" }");
+        } else {
+            self.indent();
+            self.output.push('}');
+        }
+    }
+}
+
+impl<'a> ser::Serializer for &'a mut Serializer {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Seq<'a>;
+    type SerializeTuple = Seq<'a>;
+    type SerializeTupleStruct = Seq<'a>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Block<'a>;
+    type SerializeStruct = Block<'a>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    #[inline]
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.output.push_str(if v { "yes" } else { "no" });
+        return Ok(())
+    }
+
+    #[inline]
+    fn serialize_i8(self, v: i8) -> Result<()> { self.write_display(v); Ok(()) }
+    #[inline]
+    fn serialize_i16(self, v: i16) -> Result<()> { self.write_display(v); Ok(()) }
+    #[inline]
+    fn serialize_i32(self, v: i32) -> Result<()> { self.write_display(v); Ok(()) }
+    #[inline]
+    fn serialize_i64(self, v: i64) -> Result<()> { self.write_display(v); Ok(()) }
+    #[inline]
+    fn serialize_u8(self, v: u8) -> Result<()> { self.write_display(v); Ok(()) }
+    #[inline]
+    fn serialize_u16(self, v: u16) -> Result<()> { self.write_display(v); Ok(()) }
+    #[inline]
+    fn serialize_u32(self, v: u32) -> Result<()> { self.write_display(v); Ok(()) }
+    #[inline]
+    fn serialize_u64(self, v: u64) -> Result<()> { self.write_display(v); Ok(()) }
+
+    #[inline]
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        self.write_float(v, v.is_finite())
+    }
+
+    #[inline]
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        self.write_float(v, v.is_finite())
+    }
+
+    #[inline]
+    fn serialize_char(self, v: char) -> Result<()> {
+        self.write_str(v.encode_utf8(&mut [0; 4]));
+        return Ok(())
+    }
+
+    #[inline]
+    fn serialize_str(self, v: &str) -> Result<()> {
+        self.write_str(v);
+        return Ok(())
+    }
+
+    #[inline]
+    fn serialize_bytes(self, _v: &[u8]) -> Result<()> {
+        Err(Error::Unsupported("byte arrays"))
+    }
+
+    /// Writes nothing, which makes the enclosing key be skipped
+    #[inline]
+    fn serialize_none(self) -> Result<()> {
+        Ok(())
+    }
+
+    #[inline]
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    /// Writes nothing, which makes the enclosing key be skipped
+    #[inline]
+    fn serialize_unit(self) -> Result<()> {
+        Ok(())
+    }
+
+    #[inline]
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        Ok(())
+    }
+
+    #[inline]
+    fn serialize_unit_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str) -> Result<()> {
+        self.write_str(variant);
+        return Ok(())
+    }
+
+    #[inline]
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _name: &'static str, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    /// Newtype variants are written as prefixed values (e.g. `rgb{ 255 128 64 }`)
+    #[inline]
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, name: &'static str, _variant_index: u32, variant: &'static str, value: &T) -> Result<()> {
+        if name == OPERATOR {
+            let len = match self.output.strip_suffix("= ") {
+                Some(x) => x.len(),
+                None => return Err(Error::Unsupported("operators outside of key-value pairs"))
+            };
+            self.output.truncate(len);
+            self.output.push_str(variant);
+            self.output.push(' ');
+        } else if !variant.is_empty() {
+            self.output.push_str(variant);
+        }
+        return value.serialize(self)
+    }
+
+    #[inline]
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        self.output.push('{');
+        return Ok(Seq { ser: self })
+    }
+
+    #[inline]
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        self.serialize_seq(Some(len))
+    }
+
+    #[inline]
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeTupleStruct> {
+        self.serialize_seq(Some(len))
+    }
+
+    #[inline]
+    fn serialize_tuple_variant(self, _name: &'static str, _variant_index: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeTupleVariant> {
+        Err(Error::Unsupported("tuple variants"))
+    }
+
+    #[inline]
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        self.open_block();
+        return Ok(Block { ser: self, empty: true })
+    }
+
+    #[inline]
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
+        self.serialize_map(Some(len))
+    }
+
+    #[inline]
+    fn serialize_struct_variant(self, _name: &'static str, _variant_index: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeStructVariant> {
+        Err(Error::Unsupported("struct variants"))
+    }
+}
+
+/// A `{ a b c }` list
+pub struct Seq<'a> {
+    ser: &'a mut Serializer
+}
+
+impl<'a> ser::SerializeSeq for Seq<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.ser.output.push(' ');
+        return value.serialize(&mut *self.ser)
+    }
+
+    #[inline]
+    fn end(self) -> Result<()> {
+        self.ser.output.push_str(" }");
+        return Ok(())
+    }
+}
+
+impl<'a> ser::SerializeTuple for Seq<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    #[inline]
+    fn end(self) -> Result<()> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl<'a> ser::SerializeTupleStruct for Seq<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    #[inline]
+    fn end(self) -> Result<()> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+/// A `{ key = value }` block. Repeated keys are allowed, and are how the game represents things like `create_state` or `create_pop`.
+pub struct Block<'a> {
+    ser: &'a mut Serializer,
+    empty: bool
+}
+
+impl<'a> ser::SerializeMap for Block<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<()> {
+        self.ser.write_key(key, false)
+    }
+
+    #[inline]
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.empty &= !self.ser.write_value(value)?;
+        return Ok(())
+    }
+
+    #[inline]
+    fn end(self) -> Result<()> {
+        self.ser.close_block(self.empty);
+        return Ok(())
+    }
+}
+
+impl<'a> ser::SerializeStruct for Block<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        ser::SerializeMap::serialize_entry(self, key, value)
+    }
+
+    #[inline]
+    fn end(self) -> Result<()> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+/// Top-level entries of a file, which aren't surrounded by braces
+struct Root<'a> (&'a mut Serializer);
+
+/// Entries of the root block
+pub struct RootBlock<'a> {
+    ser: &'a mut Serializer,
+    first: bool
+}
+
+impl<'a> ser::SerializeMap for RootBlock<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<()> {
+        self.ser.write_key(key, self.first)
+    }
+
+    #[inline]
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.first &= !self.ser.write_value(value)?;
+        return Ok(())
+    }
+
+    #[inline]
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeStruct for RootBlock<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        ser::SerializeMap::serialize_entry(self, key, value)
+    }
+
+    #[inline]
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+macro_rules! reject {
+    ($err:expr => $($fn:ident ($($arg:ty),*) $(-> $ret:ty)?;)+) => {
+        $(
+            #[inline]
+            fn $fn (self, $(_: $arg),*) -> Result<reject!(@ret $($ret)?)> {
+                Err($err)
+            }
+        )+
+    };
+
+    (@ret) => { () };
+    (@ret $ret:ty) => { $ret };
+}
+
+impl<'a> ser::Serializer for Root<'a> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Impossible<(), Error>;
+    type SerializeTuple = Impossible<(), Error>;
+    type SerializeTupleStruct = Impossible<(), Error>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = RootBlock<'a>;
+    type SerializeStruct = RootBlock<'a>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    reject! { Error::ExpectedRoot =>
+        serialize_bool (bool);
+        serialize_i8 (i8);
+        serialize_i16 (i16);
+        serialize_i32 (i32);
+        serialize_i64 (i64);
+        serialize_u8 (u8);
+        serialize_u16 (u16);
+        serialize_u32 (u32);
+        serialize_u64 (u64);
+        serialize_f32 (f32);
+        serialize_f64 (f64);
+        serialize_char (char);
+        serialize_str (&str);
+        serialize_bytes (&[u8]);
+        serialize_none ();
+        serialize_unit ();
+        serialize_unit_struct (&'static str);
+        serialize_unit_variant (&'static str, u32, &'static str);
+        serialize_seq (Option<usize>) -> Self::SerializeSeq;
+        serialize_tuple (usize) -> Self::SerializeTuple;
+        serialize_tuple_struct (&'static str, usize) -> Self::SerializeTupleStruct;
+        serialize_tuple_variant (&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant;
+        serialize_struct_variant (&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant;
+    }
+
+    #[inline]
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    #[inline]
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _name: &'static str, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    #[inline]
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, _name: &'static str, _variant_index: u32, _variant: &'static str, _value: &T) -> Result<()> {
+        Err(Error::ExpectedRoot)
+    }
+
+    #[inline]
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Ok(RootBlock { ser: self.0, first: true })
+    }
+
+    #[inline]
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
+        self.serialize_map(Some(len))
+    }
+}
+
+/// Writes map keys, which must be scalars
+struct KeySerializer<'a> (&'a mut Serializer);
+
+impl<'a> ser::Serializer for KeySerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Impossible<(), Error>;
+    type SerializeTuple = Impossible<(), Error>;
+    type SerializeTupleStruct = Impossible<(), Error>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = Impossible<(), Error>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    #[inline]
+    fn serialize_bool(self, v: bool) -> Result<()> { ser::Serializer::serialize_bool(self.0, v) }
+    #[inline]
+    fn serialize_i8(self, v: i8) -> Result<()> { ser::Serializer::serialize_i8(self.0, v) }
+    #[inline]
+    fn serialize_i16(self, v: i16) -> Result<()> { ser::Serializer::serialize_i16(self.0, v) }
+    #[inline]
+    fn serialize_i32(self, v: i32) -> Result<()> { ser::Serializer::serialize_i32(self.0, v) }
+    #[inline]
+    fn serialize_i64(self, v: i64) -> Result<()> { ser::Serializer::serialize_i64(self.0, v) }
+    #[inline]
+    fn serialize_u8(self, v: u8) -> Result<()> { ser::Serializer::serialize_u8(self.0, v) }
+    #[inline]
+    fn serialize_u16(self, v: u16) -> Result<()> { ser::Serializer::serialize_u16(self.0, v) }
+    #[inline]
+    fn serialize_u32(self, v: u32) -> Result<()> { ser::Serializer::serialize_u32(self.0, v) }
+    #[inline]
+    fn serialize_u64(self, v: u64) -> Result<()> { ser::Serializer::serialize_u64(self.0, v) }
+    #[inline]
+    fn serialize_f32(self, v: f32) -> Result<()> { ser::Serializer::serialize_f32(self.0, v) }
+    #[inline]
+    fn serialize_f64(self, v: f64) -> Result<()> { ser::Serializer::serialize_f64(self.0, v) }
+    #[inline]
+    fn serialize_char(self, v: char) -> Result<()> { ser::Serializer::serialize_char(self.0, v) }
+    #[inline]
+    fn serialize_str(self, v: &str) -> Result<()> { ser::Serializer::serialize_str(self.0, v) }
+
+    #[inline]
+    fn serialize_unit_variant(self, name: &'static str, variant_index: u32, variant: &'static str) -> Result<()> {
+        ser::Serializer::serialize_unit_variant(self.0, name, variant_index, variant)
+    }
+
+    #[inline]
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    #[inline]
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _name: &'static str, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    reject! { Error::ExpectedKey =>
+        serialize_bytes (&[u8]);
+        serialize_none ();
+        serialize_unit ();
+        serialize_unit_struct (&'static str);
+        serialize_seq (Option<usize>) -> Self::SerializeSeq;
+        serialize_tuple (usize) -> Self::SerializeTuple;
+        serialize_tuple_struct (&'static str, usize) -> Self::SerializeTupleStruct;
+        serialize_tuple_variant (&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant;
+        serialize_map (Option<usize>) -> Self::SerializeMap;
+        serialize_struct (&'static str, usize) -> Self::SerializeStruct;
+        serialize_struct_variant (&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant;
+    }
+
+    #[inline]
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, _name: &'static str, _variant_index: u32, _variant: &'static str, _value: &T) -> Result<()> {
+        Err(Error::ExpectedKey)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use crate::data::{Color, Ident, state::StateDefinition};
+
+    #[test]
+    fn color_prefix () {
+        let color = b"1 = rgb{ 255 128 64 } 2 = hsv{ 1 0.5 0.25 } 3 = hsv360{ 255 128 64 } 4 = { 0.5 0.25 1 }";
+        let colors = jomini::text::de::from_utf8_slice::<BTreeMap<u32, Color>>(color).unwrap();
+        let text = super::to_string(&colors).unwrap();
+        assert_eq!(text, "1 = rgb{ 255 128 64 }\n2 = hsv{ 1 0.5 0.25 }\n3 = hsv360{ 255 128 64 }\n4 = { 0.5 0.25 1 }\n");
+    }
+
+    #[test]
+    fn repeated_keys () {
+        let state = b"s:STATE_TEST = {
+            create_state = { country = c:GBR owned_provinces = { x1A2B3C } state_type = incorporated }
+            create_state = { country = c:FRA owned_provinces = { x000001 x000002 } }
+            add_homeland = cu:british
+        }";
+
+        let states = jomini::text::de::from_utf8_slice::<BTreeMap<Ident, StateDefinition>>(state).unwrap();
+        let text = super::to_string(&states).unwrap();
+        let parsed = jomini::text::de::from_utf8_slice::<BTreeMap<Ident, StateDefinition>>(text.as_bytes()).unwrap();
+
+        assert!(text.contains("s:STATE_TEST = {"));
+        assert!(text.contains("country = c:GBR"));
+        assert_eq!(text.matches("create_state").count(), 2);
+        assert_eq!(states, parsed);
+    }
+}
@@ -1,16 +1,14 @@
 use std::{path::Path};
-use futures::{Stream, TryStreamExt};
 use jomini::JominiDeserialize;
-use serde::{Serialize, Deserialize};
-use tokio::task::spawn_blocking;
-use crate::{Result, utils::{ReadDirStream, FlattenOkIter}, data::{read_to_string, GamePaths, Ident}};
+use serde::{Serialize, Deserialize, ser::SerializeMap};
+use crate::{Result, data::{parse_path, write_to_path, GameLayers, Loaded, Ident, IdentKind, ProvinceId, Provinces}};
 
 pub type NamedStateDefinition<'a> = (&'a String, &'a StateDefinition);
 
-#[derive(Debug, Clone, PartialEq, Serialize, JominiDeserialize)]
+#[derive(Debug, Clone, PartialEq, JominiDeserialize)]
 pub struct RegionDefinition {
     pub country: Ident,
-    pub owned_provinces: Vec<String>, // todo serde as hex nums
+    pub owned_provinces: Provinces,
     #[jomini(default, duplicated)]
     pub state_type: Vec<Ident> // todo probably state traits
 }
@@ -23,7 +21,69 @@ pub struct StateDefinition {
     pub homelands: Vec<Ident>
 }
 
+impl Serialize for RegionDefinition {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> where S: serde::Serializer {
+        let mut ser = serializer.serialize_map(None)?;
+        ser.serialize_entry("country", &self.country)?;
+        ser.serialize_entry("owned_provinces", &self.owned_provinces)?;
+        for state_type in self.state_type.iter() {
+            ser.serialize_entry("state_type", state_type)?;
+        }
+        return ser.end()
+    }
+}
+
+impl Serialize for StateDefinition {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> where S: serde::Serializer {
+        let mut ser = serializer.serialize_map(Some(self.regions.len() + self.homelands.len()))?;
+        for region in self.regions.iter() {
+            ser.serialize_entry("create_state", region)?;
+        }
+        for homeland in self.homelands.iter() {
+            ser.serialize_entry("add_homeland", homeland)?;
+        }
+        return ser.end()
+    }
+}
+
 impl StateDefinition {
+    pub const PATH: &'static str = "common/history/states";
+
+    /// Removes `province` from every region, dropping the regions that are left without provinces.
+    /// Returns whether the province was owned by any of them.
+    pub fn remove_province (&mut self, province: ProvinceId) -> bool {
+        let mut removed = false;
+        for region in self.regions.iter_mut() {
+            let len = region.owned_provinces.len();
+            region.owned_provinces.retain(|x| *x != province);
+            removed |= region.owned_provinces.len() != len;
+        }
+
+        self.regions.retain(|x| !x.owned_provinces.is_empty() || !x.owned_provinces.invalid.is_empty());
+        return removed
+    }
+
+    /// Adds `province` to the region of `country`, creating it if needed
+    pub fn add_province (&mut self, province: ProvinceId, country: &Ident) {
+        let region = match self.regions.iter().position(|x| x.country.eq_name(country)) {
+            Some(idx) => &mut self.regions[idx],
+            None => {
+                self.regions.push(RegionDefinition {
+                    country: Ident { value: country.value.clone(), kind: IdentKind::Country },
+                    owned_provinces: Provinces::default(),
+                    state_type: Vec::new()
+                });
+                self.regions.last_mut().unwrap()
+            }
+        };
+
+        if !region.owned_provinces.contains(&province) {
+            region.owned_provinces.push(province);
+        }
+    }
+
     #[inline]
     pub async fn from_path (path: impl AsRef<Path>) -> Result<impl Iterator<Item = (Ident, Self)>> {
         #[derive(Deserialize)]
@@ -38,25 +98,23 @@ impl StateDefinition {
             states: Vec<States>
         }
 
-        let data = read_to_string(path).await?;
-        return spawn_blocking(move ||
-            jomini::text::de::from_utf8_slice::<Inner>(data.as_bytes()).map(|x| x.states.into_iter().flat_map(|x| x.0))
-        ).await.unwrap()
+        return parse_path::<Inner>(path).await.map(|x| x.states.into_iter().flat_map(|x| x.0))
+    }
+
+    #[inline]
+    pub async fn to_path<'a> (path: impl AsRef<Path>, entries: impl IntoIterator<Item = (&'a Ident, &'a Self)>) -> Result<()> {
+        #[derive(Serialize)]
+        struct Inner<'a> {
+            #[serde(rename = "STATES", serialize_with = "crate::utils::serde_vec_map::serialize")]
+            states: Vec<(&'a Ident, &'a StateDefinition)>
+        }
+
+        let inner = Inner { states: entries.into_iter().collect() };
+        return write_to_path(path, &inner).await
     }
 
     #[inline]
-    pub async fn from_game (game: &GamePaths) -> Result<impl Stream<Item = Result<(Ident, Self)>>> {
-        let path = game.history().join("pops");
-        let iter = ReadDirStream::new(tokio::fs::read_dir(path).await?)
-            .map_err(<jomini::Error as From<std::io::Error>>::from)
-            .try_filter_map(|x: tokio::fs::DirEntry| async move {
-                if x.metadata().await.map_err(jomini::Error::from)?.is_file() {
-                    return Ok(Some(Self::from_path(x.path()).await?))
-                } else {
-                    return Ok(None)
-                }
-            });
-
-        return Ok(FlattenOkIter::new(iter))
+    pub async fn from_game (game: &GameLayers) -> Result<Loaded<Ident, Self>> {
+        return game.load(Self::PATH, Self::from_path).await
     }
 }
\ No newline at end of file
@@ -1,55 +1,101 @@
 use std::{collections::BTreeMap};
-use crate::{utils::refcell::RefCell, Result};
-use super::{GamePaths, Ident};
-use futures::stream::TryStreamExt;
-use futures::TryFutureExt;
-flat_mod! { def, pops }
-
-#[derive(Debug, PartialEq)]
-pub struct StateRegion<'a> {
-    def: &'a RegionDefinition,
-    pops: &'a [CreatePop]
-}
-
-#[derive(Debug, PartialEq)]
-pub struct State<'a> {
-    def: &'a StateDefinition,
-    pops: &'a RegionPops
-}
-
-
-impl<'a> State<'a> {
-    #[inline]
-    pub fn get<'b> (&'b self, region: &str) -> Option<StateRegion<'b>> where 'a: 'b {
-        let def = self.def.regions.iter().find(|x| &x.country == region)?;
-        let pops = self.pops.get(region)?;
-        return Some(StateRegion { def, pops })
-    }
-}
+use crate::utils::refcell::RefCell;
+use super::{Ident, IdentKind, ProvinceId};
+flat_mod! { def, pops, region }
 
 #[derive(Debug)]
 pub struct GameState {
     pub defs: RefCell<BTreeMap<Ident, StateDefinition>>,
-    pub pops: RefCell<BTreeMap<Ident, RegionPops>>
+    pub pops: RefCell<BTreeMap<Ident, RegionPops>>,
+    /// Map data of every state, by name (e.g. `STATE_SVEALAND`)
+    pub regions: RefCell<BTreeMap<String, StateRegion>>
 }
 
 impl GameState {
-    pub fn get<'a> (&'a self, state: &str) -> Option<State<'a>> {
-        todo!()
+    /// Name of the state region that `province` geographically belongs to
+    pub fn state_of (&self, province: ProvinceId) -> Option<String> {
+        let regions = self.regions.borrow();
+        return regions.iter()
+            .find(|(_, region)| region.provinces.contains(&province))
+            .map(|(name, _)| name.clone())
+    }
+
+    /// Moves `province` to the region of `country` in the state it belongs to, taking it from any other owner.
+    /// The state's history is created if it didn't exist. Returns the name of the state.
+    pub fn assign_province (&self, province: ProvinceId, country: &Ident) -> Option<String> {
+        let state = self.state_of(province)?;
+        let mut defs = self.defs.borrow_mut();
+
+        for def in defs.values_mut() {
+            def.remove_province(province);
+        }
+
+        let key = Ident { value: state.clone(), kind: IdentKind::State };
+        let def = defs.entry(key).or_insert_with(|| StateDefinition { regions: Vec::new(), homelands: Vec::new() });
+        def.add_province(province, country);
+        return Some(state)
     }
 }
 
-impl GameState {
-    #[inline]
-    pub async fn from_game (game: &GamePaths) -> Result<Self> {
-        let (defs, pops) = futures::try_join! {
-            StateDefinition::from_game(game).and_then(TryStreamExt::try_collect::<BTreeMap<_, _>>),
-            RegionPops::from_game(game).and_then(TryStreamExt::try_collect::<BTreeMap<_, _>>)
-        }?;
-
-        return Ok(Self {
-            defs: RefCell::new(defs),
-            pops: RefCell::new(pops)
-        })
-    }
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use crate::{utils::refcell::RefCell, data::{Ident, ProvinceId, Provinces}};
+    use super::{GameState, StateDefinition, RegionDefinition, StateRegion};
+
+    fn province (id: u8) -> ProvinceId {
+        return ProvinceId::from_rgb([0, 0, id])
+    }
+
+    fn region (country: &str, provinces: &[u8]) -> RegionDefinition {
+        return RegionDefinition {
+            country: Ident::from_str(country),
+            owned_provinces: Provinces { ids: provinces.iter().copied().map(province).collect(), invalid: Vec::new() },
+            state_type: Vec::new()
+        }
+    }
+
+    fn game_state (defs: Vec<(&str, StateDefinition)>) -> GameState {
+        let map = jomini::text::de::from_utf8_slice::<StateRegion>(b"id = 1 provinces = { x000001 x000002 x000003 }").unwrap();
+        return GameState {
+            defs: RefCell::new(defs.into_iter().map(|(k, v)| (Ident::from_str(k), v)).collect()),
+            pops: RefCell::new(BTreeMap::new()),
+            regions: RefCell::new(BTreeMap::from([("STATE_A".to_string(), map)]))
+        }
+    }
+
+    #[test]
+    fn moves_province_between_countries () {
+        let state = game_state(vec![("s:STATE_A", StateDefinition {
+            regions: vec![region("c:GBR", &[1, 2]), region("c:FRA", &[3])],
+            homelands: Vec::new()
+        })]);
+
+        assert_eq!(state.assign_province(province(2), &Ident::from_str("c:FRA")).as_deref(), Some("STATE_A"));
+        let defs = state.defs.borrow();
+        let def = &defs[&Ident::from_str("s:STATE_A")];
+        assert_eq!(def.regions, vec![region("c:GBR", &[1]), region("c:FRA", &[3, 2])]);
+    }
+
+    #[test]
+    fn assigns_province_without_owner () {
+        let state = game_state(Vec::new());
+        assert_eq!(state.assign_province(province(1), &Ident::from_str("GBR")).as_deref(), Some("STATE_A"));
+        assert_eq!(state.defs.borrow()[&Ident::from_str("s:STATE_A")].regions, vec![region("c:GBR", &[1])]);
+
+        // provinces outside of every state region can't be assigned
+        assert_eq!(state.assign_province(province(9), &Ident::from_str("GBR")), None);
+    }
+
+    #[test]
+    fn drops_emptied_region () {
+        let mut def = StateDefinition { regions: vec![region("c:GBR", &[1]), region("c:FRA", &[2])], homelands: Vec::new() };
+        assert!(def.remove_province(province(1)));
+        assert!(!def.remove_province(province(1)));
+        assert_eq!(def.regions, vec![region("c:FRA", &[2])]);
+
+        def.add_province(province(1), &Ident::from_str("c:FRA"));
+        def.add_province(province(1), &Ident::from_str("c:FRA"));
+        assert_eq!(def.regions, vec![region("c:FRA", &[2, 1])]);
+    }
+}
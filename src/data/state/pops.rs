@@ -1,9 +1,7 @@
-use std::{path::Path, ptr::addr_of};
-use futures::{Stream, TryStreamExt};
+use std::{path::Path, ptr::addr_of, collections::BTreeMap};
 use jomini::JominiDeserialize;
 use serde::{Deserialize, Serialize, de::{Visitor, Unexpected}, ser::SerializeMap};
-use tokio::task::spawn_blocking;
-use crate::{Result, data::{Ident, GamePaths, read_to_string}, utils::{ReadDirStream, FlattenOkIter}};
+use crate::{Result, data::{Ident, IdentKind, GameLayers, Loaded, parse_path, write_to_path, culture::Culture}};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct RegionPops {
@@ -14,13 +12,34 @@ pub struct RegionPops {
 impl RegionPops {
     #[inline]
     pub fn get<'a> (&'a self, region: &str) -> Option<&'a [CreatePop]> {
-        for (region, pops) in self.regions.iter() {
-            if region == region {
+        for (key, pops) in self.regions.iter() {
+            if key.value == region {
                 return Some(pops)
             }
         }
         return None
     }
+
+    #[inline]
+    pub fn get_mut<'a> (&'a mut self, region: &str) -> Option<&'a mut Vec<CreatePop>> {
+        return self.regions.iter_mut()
+            .find(|(key, _)| key.value == region)
+            .map(|(_, pops)| pops)
+    }
+
+    /// Returns the pops of `region`, adding an empty entry for it if there was none
+    #[inline]
+    pub fn get_or_insert (&mut self, region: &str) -> &mut Vec<CreatePop> {
+        let idx = match self.regions.iter().position(|(key, _)| key.value == region) {
+            Some(idx) => idx,
+            None => {
+                let key = Ident { value: region.to_string(), kind: IdentKind::RegionState };
+                self.regions.push((key, Vec::new()));
+                self.regions.len() - 1
+            }
+        };
+        return &mut self.regions[idx].1
+    }
 }
 
 impl Serialize for RegionPops {
@@ -46,10 +65,94 @@ pub struct CreatePop {
     pub culture: Ident,
     #[serde(default)]
     pub religion: Option<Ident>,
+    #[serde(default)]
+    pub pop_type: Option<Ident>,
     pub size: u64
 }
 
+impl CreatePop {
+    /// Religion of the pop, which defaults to the one of its culture
+    #[inline]
+    pub fn religion<'a> (&'a self, cultures: &'a BTreeMap<String, Culture>) -> Option<&'a str> {
+        return match self.religion {
+            Some(ref religion) => Some(&religion.value),
+            None => cultures.get(&self.culture.value).map(|x| x.religion.as_str())
+        }
+    }
+
+    /// Splits the pop in two halves, returning the new one
+    #[inline]
+    pub fn split (&mut self) -> Self {
+        let mut other = self.clone();
+        other.size = self.size / 2;
+        self.size -= other.size;
+        return other
+    }
+
+    /// Scales the sizes of `pops` so that they add up to `total`, keeping their proportions.
+    /// If all of them are empty, `total` is split evenly.
+    pub fn scale (pops: &mut [CreatePop], total: u64) {
+        if pops.is_empty() {
+            return
+        }
+
+        let len = pops.len() as u64;
+        let current = pops.iter().map(|x| x.size as u128).sum::<u128>();
+        let mut assigned = 0;
+        for pop in pops.iter_mut() {
+            pop.size = match current {
+                0 => total / len,
+                _ => ((pop.size as u128 * total as u128) / current) as u64
+            };
+            assigned += pop.size;
+        }
+
+        // rounding leftovers go to the biggest pop
+        if let Some(biggest) = pops.iter_mut().max_by_key(|x| x.size) {
+            biggest.size += total - assigned;
+        }
+    }
+}
+
+/// Population of a set of pops, broken down by different criteria
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PopTotals {
+    pub total: u64,
+    pub by_state: BTreeMap<String, u64>,
+    pub by_country: BTreeMap<String, u64>,
+    pub by_culture: BTreeMap<String, u64>,
+    /// Pops without a religion count towards the one of their culture
+    pub by_religion: BTreeMap<String, u64>
+}
+
+impl PopTotals {
+    pub fn new<'a> (pops: impl IntoIterator<Item = (&'a Ident, &'a RegionPops)>, cultures: &BTreeMap<String, Culture>) -> Self {
+        let mut this = Self::default();
+        for (state, regions) in pops {
+            for (country, pops) in regions.regions.iter() {
+                for pop in pops.iter() {
+                    this.add(&state.value, &country.value, pop, cultures);
+                }
+            }
+        }
+        return this
+    }
+
+    #[inline]
+    pub fn add (&mut self, state: &str, country: &str, pop: &CreatePop, cultures: &BTreeMap<String, Culture>) {
+        self.total += pop.size;
+        *self.by_state.entry(state.to_string()).or_default() += pop.size;
+        *self.by_country.entry(country.to_string()).or_default() += pop.size;
+        *self.by_culture.entry(pop.culture.value.clone()).or_default() += pop.size;
+        if let Some(religion) = pop.religion(cultures) {
+            *self.by_religion.entry(religion.to_string()).or_default() += pop.size;
+        }
+    }
+}
+
 impl RegionPops {
+    pub const PATH: &'static str = "common/history/pops";
+
     #[inline]
     pub async fn from_path (path: impl AsRef<Path>) -> Result<impl Iterator<Item = (Ident, Self)>> {
         #[derive(Deserialize)]
@@ -65,26 +168,24 @@ impl RegionPops {
             pops: Vec<StateRegionPops>
         }
 
-        let data = read_to_string(path).await?;
-        return spawn_blocking(move ||
-            jomini::text::de::from_utf8_slice::<Inner>(data.as_bytes()).map(|x| x.pops.into_iter().flat_map(|x| x.states))
-        ).await.unwrap()
+        return parse_path::<Inner>(path).await.map(|x| x.pops.into_iter().flat_map(|x| x.states))
     }
 
     #[inline]
-    pub async fn from_game (game: &GamePaths) -> Result<impl Stream<Item = Result<(Ident, Self)>>> {
-        let path = game.history().join("states");
-        let iter = ReadDirStream::new(tokio::fs::read_dir(path).await?)
-            .map_err(<jomini::Error as From<std::io::Error>>::from)
-            .try_filter_map(|x: tokio::fs::DirEntry| async move {
-                if x.metadata().await.map_err(jomini::Error::from)?.is_file() {
-                    return Ok(Some(Self::from_path(x.path()).await?))
-                } else {
-                    return Ok(None)
-                }
-            });
+    pub async fn to_path<'a> (path: impl AsRef<Path>, entries: impl IntoIterator<Item = (&'a Ident, &'a Self)>) -> Result<()> {
+        #[derive(Serialize)]
+        struct Inner<'a> {
+            #[serde(rename = "POPS", serialize_with = "crate::utils::serde_vec_map::serialize")]
+            pops: Vec<(&'a Ident, &'a RegionPops)>
+        }
 
-        return Ok(FlattenOkIter::new(iter))
+        let inner = Inner { pops: entries.into_iter().collect() };
+        return write_to_path(path, &inner).await
+    }
+
+    #[inline]
+    pub async fn from_game (game: &GameLayers) -> Result<Loaded<Ident, Self>> {
+        return game.load(Self::PATH, Self::from_path).await
     }
 }
 
@@ -143,4 +244,19 @@ impl Into<Vec<CreatePop>> for PopsList {
     fn into(self) -> Vec<CreatePop> {
         self.0
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use crate::data::Ident;
+    use super::CreatePop;
+
+    #[test]
+    fn scale_keeps_proportions () {
+        let mut pops = [100, 200, 300].map(|size| CreatePop { culture: Ident::from_str("french"), religion: None, pop_type: None, size });
+        CreatePop::scale(&mut pops, 1000);
+        assert_eq!(pops.iter().map(|x| x.size).collect::<Vec<_>>(), [166, 333, 501]);
+
+        CreatePop::scale(&mut pops[..2], 0);
+        assert_eq!(pops[0].size + pops[1].size, 0);
+    }
+}
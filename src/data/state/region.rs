@@ -0,0 +1,112 @@
+use std::{path::Path, collections::HashMap};
+use jomini::JominiDeserialize;
+use serde::{Serialize, Deserialize, ser::SerializeMap};
+use crate::{Result, utils::serde_vec_map::VecMap, data::{parse_path, write_to_path, GameLayers, Loaded, ProvinceId, Provinces}};
+
+/// Geographic side of a state, as defined in `map_data/state_regions`
+#[derive(Debug, Clone, PartialEq, JominiDeserialize)]
+#[non_exhaustive]
+pub struct StateRegion {
+    pub id: u32,
+    #[jomini(default)]
+    pub subsistence_building: Option<String>,
+    pub provinces: Provinces,
+    #[jomini(default)]
+    pub impassable: Provinces,
+    #[jomini(default)]
+    pub prime_land: Provinces,
+    #[jomini(default)]
+    pub traits: Vec<String>,
+    #[jomini(default)]
+    pub city: Option<ProvinceId>,
+    #[jomini(default)]
+    pub port: Option<ProvinceId>,
+    #[jomini(default)]
+    pub farm: Option<ProvinceId>,
+    #[jomini(default)]
+    pub mine: Option<ProvinceId>,
+    #[jomini(default)]
+    pub wood: Option<ProvinceId>,
+    #[jomini(default)]
+    pub arable_land: u32,
+    #[jomini(default)]
+    pub arable_resources: Vec<String>,
+    /// Maximum level of the buildings that can be built (e.g. `bg_iron_mining = 24`)
+    #[jomini(default, deserialize_with = "crate::utils::serde_vec_map::deserialize")]
+    pub capped_resources: Vec<(String, u32)>,
+    #[jomini(alias = "resource", duplicated)]
+    pub resources: Vec<ResourceBlock>,
+    #[jomini(default)]
+    pub naval_exit_id: Option<u32>
+}
+
+/// Discoverable resource of a state (e.g. rubber or gold fields)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ResourceBlock {
+    #[serde(rename = "type")]
+    pub ty: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub depleted_type: Option<String>,
+    #[serde(default)]
+    pub undiscovered_amount: u32,
+    #[serde(default)]
+    pub discovered_amount: u32
+}
+
+impl StateRegion {
+    pub const PATH: &'static str = "map_data/state_regions";
+
+    /// Hubs of the state, as `(kind, province)`
+    #[inline]
+    pub fn hubs (&self) -> impl Iterator<Item = (&'static str, ProvinceId)> {
+        return [("city", self.city), ("port", self.port), ("farm", self.farm), ("mine", self.mine), ("wood", self.wood)]
+            .into_iter()
+            .filter_map(|(kind, province)| Some((kind, province?)))
+    }
+
+    #[inline]
+    pub async fn from_path (path: impl AsRef<Path>) -> Result<HashMap<String, Self>> {
+        return parse_path(path).await
+    }
+
+    #[inline]
+    pub async fn to_path<'a> (path: impl AsRef<Path>, entries: impl IntoIterator<Item = (&'a String, &'a Self)>) -> Result<()> {
+        let entries = entries.into_iter().collect::<Vec<_>>();
+        return write_to_path(path, &VecMap(&entries)).await
+    }
+
+    #[inline]
+    pub async fn from_game (game: &GameLayers) -> Result<Loaded<String, Self>> {
+        return game.load(Self::PATH, Self::from_path).await
+    }
+}
+
+impl Serialize for StateRegion {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> where S: serde::Serializer {
+        let mut ser = serializer.serialize_map(None)?;
+        ser.serialize_entry("id", &self.id)?;
+        ser.serialize_entry("subsistence_building", &self.subsistence_building)?;
+        ser.serialize_entry("provinces", &self.provinces)?;
+        if !self.impassable.is_empty() {
+            ser.serialize_entry("impassable", &self.impassable)?;
+        }
+        if !self.prime_land.is_empty() {
+            ser.serialize_entry("prime_land", &self.prime_land)?;
+        }
+        if !self.traits.is_empty() {
+            ser.serialize_entry("traits", &self.traits)?;
+        }
+        for (kind, province) in self.hubs() {
+            ser.serialize_entry(kind, &province)?;
+        }
+        ser.serialize_entry("arable_land", &self.arable_land)?;
+        ser.serialize_entry("arable_resources", &self.arable_resources)?;
+        ser.serialize_entry("capped_resources", &VecMap(&self.capped_resources))?;
+        for resource in self.resources.iter() {
+            ser.serialize_entry("resource", resource)?;
+        }
+        ser.serialize_entry("naval_exit_id", &self.naval_exit_id)?;
+        return ser.end()
+    }
+}
@@ -0,0 +1,188 @@
+use std::{path::Path, collections::{HashMap, BTreeMap, BTreeSet}};
+use serde::{Serialize, Deserialize};
+use crate::{Result, utils::serde_vec_map::VecMap};
+use super::{parse_path, write_to_path, GameLayers, Loaded, ScriptValue};
+
+#[derive(Debug, Clone, PartialEq, Default)]
+#[non_exhaustive]
+pub struct Technology {
+    /// e.g. `era_1`
+    pub era: String,
+    pub texture: Option<Box<Path>>,
+    /// `production`, `military` or `society`
+    pub category: String,
+    /// every one of these must be researched before this one
+    pub unlocking_technologies: Box<[String]>,
+    pub modifier: Vec<(String, ScriptValue)>,
+    pub other: Vec<(String, ScriptValue)>
+}
+
+impl Technology {
+    pub const PATH: &'static str = "common/technology/technologies";
+
+    pub fn from_entries (entries: Vec<(String, ScriptValue)>) -> Self {
+        let mut this = Self::default();
+        for (key, value) in entries {
+            match (key.as_str(), value) {
+                ("era", ScriptValue::Scalar(x)) => this.era = x,
+                ("texture", ScriptValue::Scalar(x)) => this.texture = Some(Path::new(&x).into()),
+                ("category", ScriptValue::Scalar(x)) => this.category = x,
+                ("unlocking_technologies", x) if x.strings().is_some() => this.unlocking_technologies = x.strings().unwrap(),
+                ("modifier", ScriptValue::Block(x)) => this.modifier = x,
+                (_, value) => this.other.push((key, value))
+            }
+        }
+        return this
+    }
+
+    pub fn to_entries (&self) -> Vec<(String, ScriptValue)> {
+        let mut result = vec![("era".to_string(), ScriptValue::scalar(&self.era))];
+        if let Some(ref texture) = self.texture {
+            result.push(("texture".to_string(), ScriptValue::scalar(texture.display())));
+        }
+        result.push(("category".to_string(), ScriptValue::scalar(&self.category)));
+        if !self.unlocking_technologies.is_empty() {
+            result.push(("unlocking_technologies".to_string(), ScriptValue::list(self.unlocking_technologies.iter())));
+        }
+        if !self.modifier.is_empty() {
+            result.push(("modifier".to_string(), ScriptValue::Block(self.modifier.clone())));
+        }
+        result.extend(self.other.iter().cloned());
+        return result
+    }
+
+    #[inline]
+    pub async fn from_path (path: impl AsRef<Path>) -> Result<HashMap<String, Self>> {
+        return parse_path(path).await
+    }
+
+    #[inline]
+    pub async fn to_path<'a> (path: impl AsRef<Path>, entries: impl IntoIterator<Item = (&'a String, &'a Self)>) -> Result<()> {
+        let entries = entries.into_iter().collect::<Vec<_>>();
+        return write_to_path(path, &VecMap(&entries)).await
+    }
+
+    #[inline]
+    pub async fn from_game (game: &GameLayers) -> Result<Loaded<String, Self>> {
+        return game.load(Self::PATH, Self::from_path).await
+    }
+
+    /// Cycles of the tree, each as the path of technologies that leads back to its first one.
+    /// Every cycle is reported once, starting at its first technology by name.
+    pub fn cycles (techs: &BTreeMap<String, Self>) -> Vec<Vec<&str>> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum Mark { Visiting, Done }
+
+        fn visit<'a> (name: &'a str, techs: &'a BTreeMap<String, Technology>, marks: &mut HashMap<&'a str, Mark>, path: &mut Vec<&'a str>, result: &mut Vec<Vec<&'a str>>) {
+            match marks.get(name) {
+                Some(Mark::Done) => return,
+                Some(Mark::Visiting) => {
+                    let start = path.iter().position(|x| *x == name).unwrap();
+                    let mut cycle = path[start..].to_vec();
+                    let first = cycle.iter().enumerate().min_by_key(|(_, x)| **x).map(|(i, _)| i).unwrap();
+                    cycle.rotate_left(first);
+                    result.push(cycle);
+                    return
+                },
+                None => {}
+            }
+
+            let tech = match techs.get_key_value(name) {
+                Some((key, tech)) => (key.as_str(), tech),
+                None => return
+            };
+
+            marks.insert(tech.0, Mark::Visiting);
+            path.push(tech.0);
+            for parent in tech.1.unlocking_technologies.iter() {
+                visit(parent, techs, marks, path, result);
+            }
+            path.pop();
+            marks.insert(tech.0, Mark::Done);
+        }
+
+        let mut marks = HashMap::new();
+        let mut result = Vec::new();
+        for name in techs.keys() {
+            visit(name, techs, &mut marks, &mut Vec::new(), &mut result);
+        }
+
+        result.sort_unstable();
+        result.dedup();
+        return result
+    }
+
+    /// Technologies that can never be researched, because one of their requirements is unknown, part of a cycle or unreachable itself
+    pub fn unreachable (techs: &BTreeMap<String, Self>) -> BTreeSet<&str> {
+        let mut reachable = BTreeSet::<&str>::new();
+        loop {
+            let len = reachable.len();
+            for (name, tech) in techs.iter() {
+                if !reachable.contains(name.as_str()) && tech.unlocking_technologies.iter().all(|x| reachable.contains(x.as_str())) {
+                    reachable.insert(name.as_str());
+                }
+            }
+
+            if reachable.len() == len {
+                break
+            }
+        }
+
+        return techs.keys().map(String::as_str).filter(|x| !reachable.contains(x)).collect()
+    }
+}
+
+impl Serialize for Technology {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> where S: serde::Serializer {
+        return VecMap(&self.to_entries()).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Technology {
+    #[inline]
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error> where D: serde::Deserializer<'de> {
+        let entries = crate::utils::serde_vec_map::deserialize::<'de, String, ScriptValue, _>(deserializer)?;
+        return Ok(Self::from_entries(entries))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use crate::data::{parse_slice, round_trip, keys};
+    use super::Technology;
+
+    #[test]
+    fn cycles_and_unreachable () {
+        let techs = parse_slice::<BTreeMap<String, Technology>>(b"
+            a = { era = era_1 category = society }
+            b = { era = era_1 category = society unlocking_technologies = { a d } }
+            c = { era = era_2 category = society unlocking_technologies = { b } }
+            d = { era = era_2 category = society unlocking_technologies = { c } }
+            e = { era = era_2 category = military unlocking_technologies = { a } }
+            f = { era = era_3 category = military unlocking_technologies = { missing } }
+        ").unwrap();
+
+        assert_eq!(Technology::cycles(&techs), vec![vec!["b", "d", "c"]]);
+        assert_eq!(Technology::unreachable(&techs).into_iter().collect::<Vec<_>>(), ["b", "c", "d", "f"]);
+    }
+
+    #[test]
+    fn keeps_unknown_fields () {
+        let techs = round_trip::<String, Technology>(b"
+            railways = {
+                era = era_2
+                category = production
+                unlocking_technologies = { mechanical_tools }
+                modifier = { state_infrastructure_add = 5 }
+                can_research = { always = yes }
+                ai_weight = { value = 2 }
+            }
+        ");
+
+        let tech = &techs["railways"];
+        assert_eq!(tech.modifier.len(), 1);
+        assert_eq!(keys(&tech.other), ["can_research", "ai_weight"]);
+    }
+}
@@ -0,0 +1,401 @@
+use std::{borrow::Borrow, collections::{BTreeSet, BTreeMap}, fmt::Display};
+use super::{Game, Origin, Ident, ProvinceId, ScriptValue, technology::Technology};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Warning,
+    Error
+}
+
+/// A problem found while validating the game data
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[non_exhaustive]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Kind of entity (e.g. `culture`)
+    pub category: &'static str,
+    pub key: String,
+    pub field: &'static str,
+    pub message: String,
+    pub source: Option<Origin>
+}
+
+impl Display for Diagnostic {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error"
+        };
+
+        write!(f, "{severity}: {} '{}' ({}): {}", self.category, self.key, self.field, self.message)?;
+        if let Some(ref source) = self.source {
+            write!(f, " [{}]", source.file.display())?;
+        }
+        return Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+struct Diagnostics (Vec<Diagnostic>);
+
+impl Diagnostics {
+    #[inline]
+    fn push<K: Ord + Borrow<Q>, Q: ?Sized + Ord + ToString> (&mut self, severity: Severity, category: &'static str, key: &Q, field: &'static str, message: impl Into<String>, origins: &BTreeMap<K, Origin>) {
+        self.0.push(Diagnostic {
+            severity,
+            category,
+            key: key.to_string(),
+            field,
+            message: message.into(),
+            source: origins.get(key).cloned()
+        })
+    }
+
+    /// Reports an error if `value` isn't one of `known`
+    #[inline]
+    fn reference<K: Ord + Borrow<Q>, Q: ?Sized + Ord + ToString> (&mut self, known: &BTreeSet<&str>, kind: &str, value: &str, category: &'static str, key: &Q, field: &'static str, origins: &BTreeMap<K, Origin>) {
+        if !known.contains(value) {
+            self.push(Severity::Error, category, key, field, format!("unknown {kind} '{value}'"), origins)
+        }
+    }
+}
+
+impl Game {
+    /// Checks that every reference between the loaded entities points to an existing one
+    pub fn validate (&self) -> Vec<Diagnostic> {
+        let religions = self.religions.borrow();
+        let cultures = self.cultures.borrow();
+        let pop_types = self.pop_types.borrow();
+        let ranks = self.countries.ranks.borrow();
+        let tys = self.countries.tys.borrow();
+        let definitions = self.countries.definitions.borrow();
+        let history = self.countries.history.borrow();
+        let state_defs = self.states.defs.borrow();
+        let pops = self.states.pops.borrow();
+        let state_regions = self.states.regions.borrow();
+        let goods = self.economy.goods.borrow();
+        let buildings = self.economy.buildings.borrow();
+        let methods = self.economy.production_methods.borrow();
+        let method_groups = self.economy.production_method_groups.borrow();
+        let law_groups = self.laws.groups.borrow();
+        let laws = self.laws.laws.borrow();
+        let technologies = self.technologies.borrow();
+        let interest_groups = self.politics.interest_groups.borrow();
+        let ideologies = self.politics.ideologies.borrow();
+        let movements = self.politics.movements.borrow();
+        let coats = self.flags.coats_of_arms.borrow();
+        let flag_definitions = self.flags.definitions.borrow();
+        let named_colors = self.flags.named_colors.borrow();
+
+        let religion_names = religions.keys().map(String::as_str).collect::<BTreeSet<_>>();
+        let culture_names = cultures.keys().map(String::as_str).collect::<BTreeSet<_>>();
+        let pop_type_names = pop_types.keys().map(String::as_str).collect::<BTreeSet<_>>();
+        let rank_names = ranks.keys().map(String::as_str).collect::<BTreeSet<_>>();
+        let ty_names = tys.keys().map(String::as_str).collect::<BTreeSet<_>>();
+        let country_names = names(definitions.keys());
+        let state_names = names(state_defs.keys());
+        let goods_names = goods.keys().map(String::as_str).collect::<BTreeSet<_>>();
+        let method_names = methods.keys().map(String::as_str).collect::<BTreeSet<_>>();
+        let method_group_names = method_groups.keys().map(String::as_str).collect::<BTreeSet<_>>();
+        let law_group_names = law_groups.keys().map(String::as_str).collect::<BTreeSet<_>>();
+        let law_names = laws.keys().map(String::as_str).collect::<BTreeSet<_>>();
+        let tech_names = technologies.keys().map(String::as_str).collect::<BTreeSet<_>>();
+        let ideology_names = ideologies.keys().map(String::as_str).collect::<BTreeSet<_>>();
+        let coat_names = coats.keys().map(String::as_str).collect::<BTreeSet<_>>();
+        let color_names = named_colors.keys().map(String::as_str).collect::<BTreeSet<_>>();
+
+        let origins = &self.origins;
+        let mut result = Diagnostics::default();
+
+        for (key, culture) in cultures.iter() {
+            result.reference(&religion_names, "religion", &culture.religion, "culture", key, "religion", &origins.cultures);
+        }
+
+        for (key, ty) in tys.iter() {
+            result.reference(&rank_names, "country rank", &ty.default_rank, "country type", key, "default_rank", &origins.country_types);
+        }
+
+        for (key, def) in definitions.iter() {
+            result.reference(&ty_names, "country type", &def.country_type, "country definition", key, "country_type", &origins.country_definitions);
+            for culture in def.cultures.iter() {
+                result.reference(&culture_names, "culture", culture, "country definition", key, "cultures", &origins.country_definitions);
+            }
+            if let Some(ref capital) = def.capital {
+                result.reference(&state_names, "state", &capital.value, "country definition", key, "capital", &origins.country_definitions);
+            }
+        }
+
+        for (key, setup) in history.iter() {
+            result.reference(&country_names, "country", &key.value, "country history", key, "", &origins.country_history);
+            for law in setup.laws() {
+                result.reference(&law_names, "law", law, "country history", key, "activate_law", &origins.country_history);
+            }
+            for tech in setup.technologies() {
+                result.reference(&tech_names, "technology", tech, "country history", key, "add_technology_researched", &origins.country_history);
+            }
+        }
+
+        for (key, state) in state_defs.iter() {
+            let map = state_regions.get(&key.value);
+            if map.is_none() {
+                result.push(Severity::Error, "state", key, "", "state has no map data", &origins.state_definitions);
+            }
+
+            let provinces = map.map(|x| x.provinces.iter().collect::<BTreeSet<_>>());
+            for region in state.regions.iter() {
+                result.reference(&country_names, "country", &region.country.value, "state", key, "create_state", &origins.state_definitions);
+                for province in region.owned_provinces.invalid.iter() {
+                    result.push(Severity::Error, "state", key, "create_state.owned_provinces", format!("malformed province id '{province}'"), &origins.state_definitions);
+                }
+                if let Some(ref provinces) = provinces {
+                    for province in region.owned_provinces.iter().filter(|x| !provinces.contains(x)) {
+                        result.push(Severity::Error, "state", key, "create_state.owned_provinces", format!("province '{province}' isn't part of the state region"), &origins.state_definitions);
+                    }
+                }
+            }
+        }
+
+        // states that own every province
+        let mut owners = BTreeMap::<ProvinceId, Vec<&Ident>>::new();
+        for (key, state) in state_defs.iter() {
+            for region in state.regions.iter() {
+                for province in region.owned_provinces.iter() {
+                    owners.entry(*province).or_default().push(key);
+                }
+            }
+        }
+
+        for (province, states) in owners.iter().filter(|(_, x)| x.len() > 1) {
+            result.push(Severity::Error, "state", states[0], "create_state.owned_provinces", format!("province '{province}' is owned {} times", states.len()), &origins.state_definitions);
+        }
+
+        for (key, region) in state_regions.iter() {
+            for (field, list) in [("provinces", &region.provinces), ("impassable", &region.impassable), ("prime_land", &region.prime_land)] {
+                for province in list.invalid.iter() {
+                    result.push(Severity::Error, "state region", key, field, format!("malformed province id '{province}'"), &origins.state_regions);
+                }
+            }
+
+            for province in region.provinces.iter() {
+                if !owners.contains_key(province) && !region.impassable.contains(province) {
+                    result.push(Severity::Warning, "state region", key, "provinces", format!("province '{province}' isn't owned by any country"), &origins.state_regions);
+                }
+            }
+
+            for (field, list) in [("impassable", &region.impassable), ("prime_land", &region.prime_land)] {
+                for province in list.iter().filter(|x| !region.provinces.contains(x)) {
+                    result.push(Severity::Warning, "state region", key, field, format!("unknown province '{province}'"), &origins.state_regions);
+                }
+            }
+
+            for (kind, province) in region.hubs().filter(|(_, x)| !region.provinces.contains(x)) {
+                result.push(Severity::Warning, "state region", key, kind, format!("hub province '{province}' isn't part of the state region"), &origins.state_regions);
+            }
+        }
+
+        for (key, state) in pops.iter() {
+            if !state_names.contains(key.value.as_str()) {
+                result.push(Severity::Warning, "state pops", key, "", "pops are defined for a state without history", &origins.state_pops);
+            }
+
+            for (region, pops) in state.regions.iter() {
+                result.reference(&country_names, "country", &region.value, "state pops", key, "region_state", &origins.state_pops);
+                for pop in pops.iter() {
+                    result.reference(&culture_names, "culture", &pop.culture.value, "state pops", key, "create_pop.culture", &origins.state_pops);
+                    if let Some(ref religion) = pop.religion {
+                        result.reference(&religion_names, "religion", &religion.value, "state pops", key, "create_pop.religion", &origins.state_pops);
+                    }
+                    if let Some(ref pop_type) = pop.pop_type {
+                        result.reference(&pop_type_names, "pop type", &pop_type.value, "state pops", key, "create_pop.pop_type", &origins.state_pops);
+                    }
+                }
+            }
+        }
+
+        for (key, building) in buildings.iter() {
+            for tech in building.unlocking_technologies.iter() {
+                result.reference(&tech_names, "technology", tech, "building", key, "unlocking_technologies", &origins.buildings);
+            }
+            for group in building.production_method_groups.iter() {
+                result.reference(&method_group_names, "production method group", group, "building", key, "production_method_groups", &origins.buildings);
+            }
+        }
+
+        for (key, group) in method_groups.iter() {
+            for method in group.production_methods.iter() {
+                result.reference(&method_names, "production method", method, "production method group", key, "production_methods", &origins.production_method_groups);
+            }
+        }
+
+        for (key, method) in methods.iter() {
+            for tech in method.unlocking_technologies.iter() {
+                result.reference(&tech_names, "technology", tech, "production method", key, "unlocking_technologies", &origins.production_methods);
+            }
+            let modifiers = &method.building_modifiers;
+            for (name, _) in modifiers.inputs().chain(modifiers.outputs()) {
+                result.reference(&goods_names, "goods", name, "production method", key, "building_modifiers", &origins.production_methods);
+            }
+            for (pop_type, _) in modifiers.employment() {
+                result.reference(&pop_type_names, "pop type", pop_type, "production method", key, "building_modifiers", &origins.production_methods);
+            }
+        }
+
+        for (key, law) in laws.iter() {
+            result.reference(&law_group_names, "law group", &law.group, "law", key, "group", &origins.laws);
+            for tech in law.unlocking_technologies.iter() {
+                result.reference(&tech_names, "technology", tech, "law", key, "unlocking_technologies", &origins.laws);
+            }
+            for (field, list) in [("unlocking_laws", &law.unlocking_laws), ("disallowing_laws", &law.disallowing_laws)] {
+                for other in list.iter() {
+                    result.reference(&law_names, "law", other, "law", key, field, &origins.laws);
+                }
+            }
+        }
+
+        for (key, group) in interest_groups.iter() {
+            for (field, list) in [("ideologies", &group.ideologies), ("character_ideologies", &group.character_ideologies)] {
+                for ideology in list.iter() {
+                    result.reference(&ideology_names, "ideology", ideology, "interest group", key, field, &origins.interest_groups);
+                }
+            }
+        }
+
+        for (key, ideology) in ideologies.iter() {
+            for stances in ideology.law_stances.iter() {
+                result.reference(&law_group_names, "law group", &stances.group, "ideology", key, "law_stances", &origins.ideologies);
+                for (law, _) in stances.laws.iter() {
+                    match laws.get(law) {
+                        Some(def) if def.group != stances.group => {
+                            result.push(Severity::Warning, "ideology", key, "law_stances", format!("law '{law}' belongs to '{}', not '{}'", def.group, stances.group), &origins.ideologies);
+                        },
+                        Some(_) => {},
+                        None => result.push(Severity::Error, "ideology", key, "law_stances", format!("unknown law '{law}'"), &origins.ideologies)
+                    }
+                }
+            }
+        }
+
+        for (key, movement) in movements.iter() {
+            if let Some(ref ideology) = movement.ideology {
+                result.reference(&ideology_names, "ideology", ideology, "political movement", key, "ideology", &origins.movements);
+            }
+        }
+
+        for (key, tech) in technologies.iter() {
+            for parent in tech.unlocking_technologies.iter() {
+                result.reference(&tech_names, "technology", parent, "technology", key, "unlocking_technologies", &origins.technologies);
+            }
+        }
+
+        // research silently stops at cycles, and at anything that depends on them
+        let cycles = Technology::cycles(&technologies);
+        for cycle in cycles.iter() {
+            let path = cycle.iter().chain(cycle.first()).copied().collect::<Vec<_>>().join(" -> ");
+            result.push(Severity::Error, "technology", cycle[0], "unlocking_technologies", format!("technologies require each other: {path}"), &origins.technologies);
+        }
+
+        // technologies with an unknown requirement were already reported above
+        for name in Technology::unreachable(&technologies) {
+            let unknown_parent = technologies[name].unlocking_technologies.iter().any(|x| !tech_names.contains(x.as_str()));
+            if !unknown_parent && !cycles.iter().any(|x| x.contains(&name)) {
+                result.push(Severity::Error, "technology", name, "unlocking_technologies", "technology can never be researched", &origins.technologies);
+            }
+        }
+
+        for (key, list) in flag_definitions.iter() {
+            if !country_names.contains(key.as_str()) {
+                result.push(Severity::Warning, "flag definition", key, "", "flags are defined for an unknown country", &origins.flag_definitions);
+            }
+            for definition in list.definitions.iter() {
+                result.reference(&coat_names, "coat of arms", &definition.coa, "flag definition", key, "flag_definition.coa", &origins.flag_definitions);
+                if let Some(ref canton) = definition.subject_canton {
+                    result.reference(&coat_names, "coat of arms", canton, "flag definition", key, "flag_definition.subject_canton", &origins.flag_definitions);
+                }
+            }
+        }
+
+        for (key, coat) in coats.iter() {
+            for sub in coat.subs.iter() {
+                result.reference(&coat_names, "coat of arms", &sub.parent, "coat of arms", key, "sub.parent", &origins.coats_of_arms);
+            }
+
+            // literal colors are lists (e.g. `rgb { 255 0 0 }`), and script variables are resolved by the game
+            let colors = coat.colors.iter()
+                .chain(coat.colored_emblems.iter().flat_map(|x| x.colors.iter()))
+                .chain(coat.subs.iter().flat_map(|x| x.colors.iter()))
+                .flatten()
+                .filter_map(ScriptValue::as_str)
+                .filter(|x| !x.starts_with('@'));
+
+            for color in colors {
+                result.reference(&color_names, "named color", color, "coat of arms", key, "color", &origins.coats_of_arms);
+            }
+        }
+
+        let mut result = result.0;
+        result.sort_by(|lhs, rhs| rhs.severity.cmp(&lhs.severity).then_with(|| lhs.cmp(rhs)));
+        return result
+    }
+}
+
+#[inline]
+fn names<'a> (idents: impl IntoIterator<Item = &'a Ident>) -> BTreeSet<&'a str> {
+    return idents.into_iter().map(|x| x.value.as_str()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{Game, write_game};
+    use super::Severity;
+
+    #[tokio::test]
+    async fn reports_broken_references () {
+        let root = std::env::temp_dir().join(format!("vicky3-mod-validate-{}", std::process::id()));
+        write_game(&root, &[
+            ("common/religions/00_religions.txt", "protestant = { texture = a.dds traits = { christian } color = { 0.1 0.1 0.8 } }"),
+            ("common/cultures/00_cultures.txt", "
+                british = { color = { 0.7 0.1 0.1 } religion = protestant graphics = european ethnicities = { 1 = caucasian } }
+                french = { color = { 0.1 0.1 0.7 } religion = catholic graphics = european ethnicities = { 1 = caucasian } }
+            "),
+            ("common/country_ranks/00_country_ranks.txt", "great_power = { rank_value = 5 icon_index = 4 }"),
+            ("common/country_types/00_country_types.txt", "
+                recognized = { default_rank = great_power }
+                unrecognized = { is_unrecognized = yes default_rank = tribal_federation }
+            "),
+            ("common/country_definitions/00_countries.txt", "
+                GBR = { color = { 200 0 0 } country_type = recognized tier = kingdom cultures = { british } }
+                FRA = { color = { 0 0 200 } country_type = colonial tier = kingdom cultures = { british gaulish } }
+            "),
+            ("common/history/pops/00_pops.txt", "
+                POPS = {
+                    s:STATE_A = {
+                        region_state:GBR = {
+                            create_pop = { culture = british size = 500 }
+                            create_pop = { culture = welsh religion = sunni size = 1000 }
+                        }
+                    }
+                }
+            ")
+        ]).await;
+
+        let game = Game::new(root.clone()).await.unwrap();
+        let diagnostics = game.validate().into_iter()
+            .map(|x| (x.severity, x.category, x.key, x.field, game.layers.relative(x.source.as_ref().unwrap()).to_string_lossy().into_owned()))
+            .collect::<Vec<_>>();
+
+        let expected = [
+            (Severity::Error, "country definition", "FRA", "country_type", "country_definitions/00_countries.txt"),
+            (Severity::Error, "country definition", "FRA", "cultures", "country_definitions/00_countries.txt"),
+            (Severity::Error, "country type", "unrecognized", "default_rank", "country_types/00_country_types.txt"),
+            (Severity::Error, "culture", "french", "religion", "cultures/00_cultures.txt"),
+            (Severity::Error, "state pops", "s:STATE_A", "create_pop.culture", "history/pops/00_pops.txt"),
+            (Severity::Error, "state pops", "s:STATE_A", "create_pop.religion", "history/pops/00_pops.txt"),
+            // the pops are fine, but the state has no history
+            (Severity::Warning, "state pops", "s:STATE_A", "", "history/pops/00_pops.txt")
+        ].map(|(severity, category, key, field, file)| (severity, category, key.to_string(), field, file.to_string()));
+
+        assert_eq!(diagnostics, expected);
+        tokio::fs::remove_dir_all(&root).await.unwrap();
+    }
+}
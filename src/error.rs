@@ -0,0 +1,260 @@
+use std::{path::{PathBuf, Path}, fmt::Display};
+use crate::data::ser;
+
+/// Position inside of a file, starting at `1:1`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize
+}
+
+impl Span {
+    /// Computes the line and column of the byte `offset` of `data`
+    pub fn from_offset (data: &[u8], offset: usize) -> Self {
+        let data = &data[..usize::min(offset, data.len())];
+        let line = 1 + data.iter().filter(|x| **x == b'\n').count();
+        let column = 1 + match data.iter().rposition(|x| *x == b'\n') {
+            Some(x) => data.len() - x - 1,
+            None => data.len()
+        };
+        return Self { line, column }
+    }
+
+    /// Finds the definition of a `.` separated key path (e.g. `french.ethnicities`) inside of a script file.
+    /// Sequence indices (e.g. `[2]`) and unknown segments are ignored.
+    pub fn from_key_path (data: &[u8], key_path: &str) -> Option<Self> {
+        let mut offset = None;
+        let mut start = 0;
+
+        for segment in key_path.split('.') {
+            let segment = match segment.find('[') {
+                Some(idx) => &segment[..idx],
+                None => segment
+            };
+
+            if segment.is_empty() || segment == "?" {
+                continue
+            }
+
+            let found = find_key(&data[start..], segment.as_bytes())? + start;
+            offset = Some(found);
+            start = found + segment.len();
+        }
+
+        return offset.map(|x| Self::from_offset(data, x))
+    }
+}
+
+impl Display for Span {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    Io (std::io::Error),
+    Parse (jomini::Error),
+    Serialize (ser::Error),
+    Json (serde_json::Error),
+    Image (image::ImageError),
+    /// Malformed localization file
+    Localization (String),
+    /// Malformed or unsupported texture
+    Texture (String),
+    /// Every error found while loading
+    Multiple (Vec<Error>)
+}
+
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    file: Option<PathBuf>,
+    span: Option<Span>,
+    key_path: Option<String>
+}
+
+impl Error {
+    #[inline]
+    pub fn new (kind: ErrorKind) -> Self {
+        return Self {
+            kind,
+            file: None,
+            span: None,
+            key_path: None
+        }
+    }
+
+    /// Returns `None` if there are no errors, the error if there is only one, or an [`ErrorKind::Multiple`] otherwise
+    #[inline]
+    pub fn collect (mut errors: Vec<Error>) -> Option<Self> {
+        return match errors.len() {
+            0 => None,
+            1 => errors.pop(),
+            _ => Some(Self::new(ErrorKind::Multiple(errors)))
+        }
+    }
+
+    #[inline]
+    pub fn kind (&self) -> &ErrorKind {
+        return &self.kind
+    }
+
+    #[inline]
+    pub fn file (&self) -> Option<&Path> {
+        return self.file.as_deref()
+    }
+
+    #[inline]
+    pub fn span (&self) -> Option<Span> {
+        return self.span
+    }
+
+    #[inline]
+    pub fn key_path (&self) -> Option<&str> {
+        return self.key_path.as_deref()
+    }
+
+    #[inline]
+    pub fn with_file (mut self, file: impl Into<PathBuf>) -> Self {
+        self.file = Some(file.into());
+        return self
+    }
+
+    #[inline]
+    pub fn with_span (mut self, span: Span) -> Self {
+        self.span = Some(span);
+        return self
+    }
+
+    #[inline]
+    pub fn with_key_path (mut self, key_path: impl Into<String>) -> Self {
+        self.key_path = Some(key_path.into());
+        return self
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(ref file) = self.file {
+            write!(f, "{}", file.display())?;
+            if let Some(ref span) = self.span {
+                write!(f, ":{span}")?;
+            }
+            f.write_str(" ")?;
+        }
+
+        if let Some(ref key_path) = self.key_path {
+            write!(f, "{key_path}: ")?;
+        }
+
+        match self.kind {
+            ErrorKind::Io(ref e) => Display::fmt(e, f),
+            ErrorKind::Parse(ref e) => Display::fmt(e, f),
+            ErrorKind::Serialize(ref e) => Display::fmt(e, f),
+            ErrorKind::Json(ref e) => Display::fmt(e, f),
+            ErrorKind::Image(ref e) => Display::fmt(e, f),
+            ErrorKind::Localization(ref e) | ErrorKind::Texture(ref e) => f.write_str(e),
+            ErrorKind::Multiple(ref errors) => {
+                write!(f, "{} errors found", errors.len())?;
+                for error in errors.iter() {
+                    write!(f, "\n{error}")?;
+                }
+                return Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    #[inline]
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self.kind {
+            ErrorKind::Io(ref e) => Some(e),
+            ErrorKind::Parse(ref e) => Some(e),
+            ErrorKind::Serialize(ref e) => Some(e),
+            ErrorKind::Json(ref e) => Some(e),
+            ErrorKind::Image(ref e) => Some(e),
+            ErrorKind::Localization(_) | ErrorKind::Texture(_) | ErrorKind::Multiple(_) => None
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    #[inline]
+    fn from(value: std::io::Error) -> Self {
+        Self::new(ErrorKind::Io(value))
+    }
+}
+
+impl From<jomini::Error> for Error {
+    #[inline]
+    fn from(value: jomini::Error) -> Self {
+        Self::new(ErrorKind::Parse(value))
+    }
+}
+
+impl From<ser::Error> for Error {
+    #[inline]
+    fn from(value: ser::Error) -> Self {
+        Self::new(ErrorKind::Serialize(value))
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    #[inline]
+    fn from(value: serde_json::Error) -> Self {
+        let span = match value.line() {
+            0 => None,
+            line => Some(Span { line, column: value.column() })
+        };
+
+        let mut this = Self::new(ErrorKind::Json(value));
+        this.span = span;
+        return this
+    }
+}
+
+impl From<image::ImageError> for Error {
+    #[inline]
+    fn from(value: image::ImageError) -> Self {
+        Self::new(ErrorKind::Image(value))
+    }
+}
+
+/// Finds `key` as a whole token followed by an operator
+fn find_key (data: &[u8], key: &[u8]) -> Option<usize> {
+    let is_token = |c: u8| c.is_ascii_alphanumeric() || matches!(c, b'_' | b'.' | b':' | b'@' | b'-' | b'\'');
+    let mut start = 0;
+
+    while let Some(idx) = data[start..].windows(key.len()).position(|x| x == key) {
+        let idx = start + idx;
+        let end = idx + key.len();
+        let before = idx == 0 || !is_token(data[idx - 1]);
+        let after = data[end..].iter()
+            .find(|x| !x.is_ascii_whitespace())
+            .map_or(false, |x| matches!(x, b'=' | b'<' | b'>' | b'?' | b'!'));
+
+        if before && after && (end == data.len() || !is_token(data[end])) {
+            return Some(idx)
+        }
+        start = idx + 1;
+    }
+
+    return None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Span;
+
+    #[test]
+    fn key_path_span () {
+        let data = b"english = {\n\tcolor = { 1 1 1 }\n}\nfrench = {\n\tcolor = { 1 1 1 }\n\tethnicities = {\n\t\t1 = neutral\n\t}\n}";
+        assert_eq!(Span::from_key_path(data, "french.ethnicities"), Some(Span { line: 6, column: 2 }));
+        assert_eq!(Span::from_key_path(data, "french.color"), Some(Span { line: 5, column: 2 }));
+        assert_eq!(Span::from_key_path(data, "german"), None);
+    }
+}
@@ -0,0 +1,64 @@
+use std::path::PathBuf;
+use eframe::egui::{Ui, ComboBox, RichText, Color32, ColorImage};
+use crate::data::{Game, flag::{FlagComposer, FlagDefinitionList, FLAG_SIZE}};
+
+/// Flag of a country, drawn from the coat of arms of any of its flag definitions
+pub struct FlagPreview<'a> {
+    game: &'a Game,
+    /// Country shown, and index of the flag definition shown for it
+    selected: Option<(String, usize)>
+}
+
+impl<'a> FlagPreview<'a> {
+    #[inline]
+    pub fn new (game: &'a Game) -> Self {
+        return Self { game, selected: None }
+    }
+
+    pub fn update (&mut self, ui: &mut Ui, tag: &str) {
+        let definitions = self.game.flags.definitions.borrow();
+        let list = definitions.get(tag);
+
+        let mut index = match self.selected {
+            Some((ref country, index)) if country == tag => index,
+            _ => list.and_then(FlagDefinitionList::default_flag).unwrap_or_default()
+        };
+
+        if let Some(list) = list {
+            ComboBox::from_label(RichText::new("Flag").strong())
+                .selected_text(list.definitions.get(index).map_or("", |x| x.coa.as_str()))
+                .show_ui(ui, |ui| {
+                    for (i, definition) in list.definitions.iter().enumerate() {
+                        let text = match definition.trigger {
+                            Some(_) => format!("{} (conditional)", definition.coa),
+                            None => definition.coa.clone()
+                        };
+                        ui.selectable_value(&mut index, i, text).on_hover_text(format!("Priority: {}", definition.priority));
+                    }
+                });
+        }
+        self.selected = Some((tag.to_string(), index));
+
+        // countries without flag definitions use the coat of arms named after their tag
+        let coa = list.and_then(|x| x.definitions.get(index)).map_or(tag, |x| x.coa.as_str()).to_string();
+        if !self.game.flags.coats_of_arms.borrow().contains_key(&coa) {
+            ui.label(RichText::new(format!("Unknown coat of arms '{coa}'")).color(Color32::LIGHT_RED));
+            return
+        }
+
+        let [width, height] = FLAG_SIZE;
+        // the generations are part of the key so that editing the coats of arms or their colors draws the flag again
+        let (coats, colors) = (&self.game.flags.coats_of_arms, &self.game.flags.named_colors);
+        let key = PathBuf::from(format!("flags/{coa}#{}.{}", coats.generation(), colors.generation()));
+        self.game.textures.borrow_mut().show_with(ui, &key, [width as f32, height as f32], || {
+            let mut composer = FlagComposer::new(&coa, &coats.borrow(), &colors.borrow(), &self.game.layers);
+            async move {
+                composer.load_textures().await;
+                // drawing is CPU bound, so it's kept off the async workers
+                let image = tokio::task::spawn_blocking(move || composer.compose(&coa, width, height)).await
+                    .map_err(|e| format!("drawing failed: {e}"))?;
+                Ok(ColorImage::from_rgba_unmultiplied([width as usize, height as usize], image.as_raw()))
+            }
+        });
+    }
+}
@@ -1,13 +1,15 @@
 use std::{path::{Path}, mem::MaybeUninit};
 use eframe::{egui::{CentralPanel, SidePanel, Window, RichText, Color32}};
 use rfd::FileDialog;
-use crate::{*, data::Game};
+use crate::{*, data::{Game, Mod}};
 
 #[derive(Debug)]
 #[non_exhaustive]
 pub struct Home {
     init_game_path: bool,
     pub game_path: String,
+    /// Mods loaded on top of the game, in load order
+    pub mod_paths: Vec<String>,
     show_error: bool,
     error_message: MaybeUninit<String>
 }
@@ -18,6 +20,7 @@ impl Default for Home {
         Self {
             init_game_path: true,
             game_path: String::new(),
+            mod_paths: Vec::new(),
             show_error: false,
             error_message: MaybeUninit::uninit()
         }
@@ -38,6 +41,7 @@ impl App for Home {
 
             let game_path = ui.button("Select game data path");
             ui.text_edit_singleline(&mut self.game_path);
+            self.mod_list(ui);
             let open_game_data = ui.button("Open game data");
 
             if game_path.clicked() {
@@ -53,10 +57,16 @@ impl App for Home {
                 },
 
                 (true, path) => {
-                    let game = match runtime().block_on(Game::new(path as &String)) {
+                    let load = async {
+                        let mods = futures::future::try_join_all(self.mod_paths.iter().map(Mod::open)).await?;
+                        Game::with_mods(path as &String, mods).await
+                    };
+
+                    let game = match runtime().block_on(load) {
                         Ok(game) => {
                             if let Some(storage) = frame.storage_mut() {
                                 storage.set_string("game_path", path.clone());
+                                storage.set_string("mod_paths", self.mod_paths.join("\n"));
                                 storage.flush();
                             }
                             game
@@ -88,11 +98,51 @@ impl Home {
             self.game_path = frame.storage()
                 .and_then(|stg| stg.get_string("game_path"))
                 .unwrap_or_default();
+            self.mod_paths = frame.storage()
+                .and_then(|stg| stg.get_string("mod_paths"))
+                .map(|x| x.lines().filter(|x| !x.is_empty()).map(ToString::to_string).collect())
+                .unwrap_or_default();
             self.init_game_path = false;
             ctx.request_repaint()
         }
     }
 
+    fn mod_list (&mut self, ui: &mut eframe::egui::Ui) {
+        ui.label(RichText::new("Mods (in load order)").strong());
+
+        let mut remove = None;
+        let mut move_up = None;
+        for (i, path) in self.mod_paths.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                if ui.small_button("^").clicked() && i > 0 {
+                    move_up = Some(i);
+                }
+                if ui.small_button("x").clicked() {
+                    remove = Some(i);
+                }
+                ui.text_edit_singleline(path);
+            });
+        }
+
+        if let Some(i) = move_up {
+            self.mod_paths.swap(i - 1, i);
+        }
+        if let Some(i) = remove {
+            self.mod_paths.remove(i);
+        }
+
+        if ui.button("Add mod").clicked() {
+            if let Some(Ok(path)) = FileDialog::new().pick_folder().map(|x| x.into_os_string().into_string()) {
+                if Mod::is_mod(path.as_ref()) {
+                    self.mod_paths.push(path);
+                } else {
+                    self.error_message.write(format!("'{path}' has no .metadata/metadata.json"));
+                    self.show_error = true;
+                }
+            }
+        }
+    }
+
     #[inline]
     fn select_game_path (&mut self) {
         let mut builder = FileDialog::new();
@@ -0,0 +1,222 @@
+use std::collections::BTreeMap;
+use eframe::egui::{Ui, ComboBox, RichText, Grid, CollapsingHeader, Color32, TextEdit, Button};
+use crate::{data::{Game, Localization, LocalizedText, markup::Markup}, utils::attribute_text};
+
+/// Texts shown at most on the search results, to keep the window responsive
+const MAX_RESULTS: usize = 200;
+
+/// Lists the entities without a name and the texts nothing uses on every language, letting their texts be edited in place
+pub struct LocalizationEditor<'a> {
+    game: &'a Game,
+    /// Only keys or texts containing it are listed
+    filter: String,
+    /// Texts typed for missing keys, until they're added
+    drafts: BTreeMap<String, String>,
+    report: Report
+}
+
+/// Keys that the loaded entities use and how well every language covers them, kept until any of them changes
+#[derive(Debug, Default)]
+struct Report {
+    /// Generations of the data the report was made from
+    source: Option<[u64; 18]>,
+    named: Vec<(&'static str, String)>,
+    /// Language, number of named keys it has a text for, and its orphaned keys
+    coverage: Vec<(String, usize, Vec<String>)>
+}
+
+impl Report {
+    fn update (&mut self, game: &Game, localization: &Localization) {
+        let source = [
+            game.religions.generation(), game.cultures.generation(), game.countries.ranks.generation(), game.countries.tys.generation(),
+            game.countries.definitions.generation(), game.pop_types.generation(), game.states.regions.generation(), game.economy.goods.generation(),
+            game.economy.buildings.generation(), game.economy.production_methods.generation(), game.economy.production_method_groups.generation(),
+            game.laws.groups.generation(), game.laws.laws.generation(), game.politics.interest_groups.generation(), game.politics.ideologies.generation(),
+            game.politics.movements.generation(), game.technologies.generation(), game.localization.generation()
+        ];
+        if self.source == Some(source) {
+            return
+        }
+
+        let used = Localization::used_keys(game);
+        self.named = Localization::named_keys(game);
+        self.coverage = localization.languages.keys()
+            .map(|language| {
+                let present = self.named.iter().filter(|(_, key)| localization.get(language, key).is_some()).count();
+                let orphaned = localization.orphaned(language, &used).into_iter().map(str::to_string).collect();
+                (language.clone(), present, orphaned)
+            })
+            .collect();
+        self.source = Some(source);
+    }
+}
+
+impl<'a> LocalizationEditor<'a> {
+    #[inline]
+    pub fn new (game: &'a Game) -> Self {
+        return Self { game, filter: String::new(), drafts: BTreeMap::new(), report: Report::default() }
+    }
+
+    pub fn update (&mut self, ui: &mut Ui) {
+        // borrowed mutably only to apply an edit, so that the report is kept until the texts change
+        let localization = self.game.localization.borrow();
+        self.report.update(self.game, &localization);
+        let named = &self.report.named;
+
+        let mut language = localization.language.clone();
+        ComboBox::from_label(RichText::new("Language").strong())
+            .selected_text(language.clone())
+            .show_ui(ui, |ui| {
+                for (option, ..) in self.report.coverage.iter() {
+                    ui.selectable_value(&mut language, option.clone(), option.as_str());
+                }
+            });
+
+        CollapsingHeader::new(RichText::new("Coverage").strong()).default_open(true).show(ui, |ui| {
+            Grid::new("localization_coverage").striped(true).show(ui, |ui| {
+                ui.label(RichText::new("Language").strong());
+                ui.label(RichText::new("Named").strong());
+                ui.label(RichText::new("Missing").strong());
+                ui.label(RichText::new("Orphaned").strong());
+                ui.end_row();
+
+                for (name, present, orphaned) in self.report.coverage.iter() {
+                    let missing = named.len() - present;
+
+                    let mut text = RichText::new(name.as_str());
+                    if &language == name {
+                        text = text.strong();
+                    }
+
+                    ui.label(text);
+                    ui.label(format!("{present}/{}", named.len()));
+                    ui.label(RichText::new(missing.to_string()).color(if missing > 0 { Color32::YELLOW } else { Color32::GREEN }));
+                    ui.label(orphaned.len().to_string());
+                    ui.end_row();
+                }
+            });
+        });
+
+        attribute_text(ui, "Filter", &mut self.filter);
+        let filter = self.filter.to_lowercase();
+        let matches = |key: &str, text: &str| filter.is_empty() || key.to_lowercase().contains(&filter) || text.to_lowercase().contains(&filter);
+        let mut edit = None;
+
+        let missing = named.iter()
+            .filter(|(_, key)| localization.get(&language, key).is_none() && matches(key, ""))
+            .collect::<Vec<_>>();
+
+        let drafts = &mut self.drafts;
+        CollapsingHeader::new(RichText::new(format!("Missing names ({})", missing.len())).strong()).show(ui, |ui| {
+            Grid::new("localization_missing").striped(true).show(ui, |ui| {
+                for (category, key) in missing {
+                    ui.label(*category);
+                    ui.label(key.as_str());
+
+                    let mut draft = drafts.get(key).cloned().unwrap_or_default();
+                    if ui.add(TextEdit::singleline(&mut draft).hint_text("Name")).changed() {
+                        match draft.is_empty() {
+                            true => drafts.remove(key),
+                            false => drafts.insert(key.clone(), draft.clone())
+                        };
+                    }
+                    if ui.add_enabled(!draft.is_empty(), Button::new("Add")).clicked() {
+                        drafts.remove(key);
+                        edit = Some((key.clone(), draft));
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+
+        let orphaned = self.report.coverage.iter()
+            .find(|(name, ..)| name == &language)
+            .map_or(&[] as &[String], |(_, _, x)| x.as_slice());
+
+        CollapsingHeader::new(RichText::new(format!("Orphaned keys ({})", orphaned.len())).strong()).show(ui, |ui| {
+            ui.label(RichText::new("Texts added by mods that no loaded entity refers to. Texts used by scripts (e.g. events) are listed too").weak());
+            if let Some(x) = render_texts(ui, "localization_orphaned", &localization, &language, orphaned.iter().map(String::as_str), &matches) {
+                edit = Some(x);
+            }
+        });
+
+        CollapsingHeader::new(RichText::new("Texts").strong()).show(ui, |ui| {
+            if filter.is_empty() {
+                ui.label("Type on the filter to search every text");
+                return
+            }
+
+            let keys = localization.languages.get(&language)
+                .into_iter()
+                .flat_map(|x| x.iter())
+                .filter(|(key, text)| matches(key, &text.text))
+                .take(MAX_RESULTS)
+                .map(|(key, _)| key.as_str());
+
+            if let Some(x) = render_texts(ui, "localization_texts", &localization, &language, keys, &matches) {
+                edit = Some(x);
+            }
+        });
+
+        let changed_language = language != localization.language;
+        drop(localization);
+        if !changed_language && edit.is_none() {
+            return
+        }
+
+        let mut localization = self.game.localization.borrow_mut();
+        if let Some((key, text)) = edit {
+            let texts = localization.languages.entry(language.clone()).or_default();
+            match texts.get_mut(&key) {
+                Some(x) => x.text = text,
+                None => { texts.insert(key, LocalizedText { version: None, text }); }
+            }
+        }
+        localization.language = language;
+    }
+}
+
+/// Editable text of every one of `keys` that matches the filter, along with the file it was loaded from and a preview of its markup.
+/// Returns the key and new text of the one edited, if any
+fn render_texts<'a> (ui: &mut Ui, id: &str, localization: &Localization, language: &str, keys: impl Iterator<Item = &'a str>, matches: &impl Fn(&str, &str) -> bool) -> Option<(String, String)> {
+    let mut edit = None;
+    Grid::new(id).striped(true).show(ui, |ui| {
+        for key in keys {
+            let file = localization.origins.get(language)
+                .and_then(|x| x.get(key))
+                .and_then(|x| x.file_name())
+                .map(|x| x.to_string_lossy().into_owned());
+
+            let (text, preview, problems) = match localization.get(language, key) {
+                Some(text) if matches(key, &text.text) => {
+                    let markup = Markup::parse(&text.text);
+                    let mut problems = markup.problems.iter().map(ToString::to_string).collect::<Vec<_>>();
+                    for unknown in markup.keys().into_iter().filter(|x| localization.name(x).is_none()) {
+                        problems.push(format!("unknown key `${unknown}$`"));
+                    }
+                    (text, markup.layout_job(ui, localization), problems)
+                },
+                _ => continue
+            };
+
+            let label = ui.label(key);
+            if let Some(file) = file {
+                label.on_hover_text(file);
+            }
+
+            let mut value = text.text.clone();
+            if ui.add(TextEdit::singleline(&mut value).desired_width(400.0)).changed() {
+                edit = Some((key.to_string(), value));
+            }
+            ui.label(preview);
+
+            if problems.is_empty() {
+                ui.label("");
+            } else {
+                ui.label(RichText::new("⚠").color(Color32::YELLOW)).on_hover_text(problems.join("\n"));
+            }
+            ui.end_row();
+        }
+    });
+    return edit
+}
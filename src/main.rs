@@ -1,12 +1,20 @@
 #![feature(fn_traits, unboxed_closures, vec_into_raw_parts, new_uninit, local_key_cell_methods)]
 
+pub mod cli;
 pub mod data;
+pub mod error;
+pub mod flag_preview;
 pub mod home;
+pub mod localization_editor;
+pub mod map;
 pub mod mod_folder;
+pub mod save_mod;
+pub mod problems;
 pub mod states;
+pub mod tech_tree;
 pub(crate) mod utils;
 
-pub type Result<T> = ::core::result::Result<T, jomini::Error>;
+pub type Result<T> = ::core::result::Result<T, error::Error>;
 
 // /Users/Aandreba/Library/Application Support/Steam/steamapps/common/Victoria 3/game
 
@@ -16,6 +24,8 @@ use eframe::*;
 use home::Home;
 use tokio::runtime::Runtime;
 use mod_folder::*;
+use save_mod::SaveMod;
+use problems::Problems;
 
 thread_local! {
     pub static GAME: Cell<Option<Game>> = Cell::new(None);
@@ -38,6 +48,13 @@ fn main() -> anyhow::Result<()> {
         .build()?;
     unsafe { init_runtime(builder) }
 
+    // Run headless if a command was given
+    let cli = <cli::Cli as clap::Parser>::parse();
+    if let Some(command) = cli.command {
+        let code = runtime().block_on(command.run());
+        std::process::exit(code)
+    }
+
     //unsafe { Game::initialize("D:/SteamLibrary/steamapps/common/Victoria 3/game").await };
     let options = eframe::NativeOptions {
         ..Default::default()
@@ -58,7 +75,7 @@ fn main() -> anyhow::Result<()> {
             options,
             Box::new(move |_cc| {
                 new_mod_folder! {
-                    { game, false, false, false, false, false, false },
+                    { game, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, false, SaveMod::default(), Problems::default() },
                     { ModFolderLists::new },
                     box result
                 }
@@ -0,0 +1,406 @@
+use std::{collections::hash_map::DefaultHasher, hash::{Hash, Hasher}};
+use eframe::{egui::{Ui, ScrollArea, Sense, TextureHandle, TextureOptions, ColorImage, Slider, RichText, ComboBox, CollapsingHeader}, epaint::{Color32, Rect, pos2, vec2}};
+use tokio::task::JoinHandle;
+use crate::{Result, runtime, data::{Game, Ident, IdentKind, map::ProvinceMap}};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MapMode {
+    #[default]
+    Country,
+    State,
+    Culture,
+    Religion
+}
+
+impl MapMode {
+    pub const ALL: [Self; 4] = [Self::Country, Self::State, Self::Culture, Self::Religion];
+
+    #[inline]
+    pub fn name (self) -> &'static str {
+        return match self {
+            Self::Country => "Country",
+            Self::State => "State",
+            Self::Culture => "Majority culture",
+            Self::Religion => "Majority religion"
+        }
+    }
+}
+
+/// Owner of a province
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProvinceOwner {
+    pub state: Ident,
+    pub country: Ident
+}
+
+/// Map of the provinces, colored by one of the [`MapMode`]s
+pub struct MapView<'a> {
+    game: &'a Game,
+    loading: Option<JoinHandle<Result<ProvinceMap>>>,
+    map: Option<std::result::Result<ProvinceMap, String>>,
+    pub mode: MapMode,
+    zoom: f32,
+    texture: Option<TextureHandle>,
+    /// Owners of the provinces, along with the generations of the states and regions they were found from
+    ownership: Option<([u64; 2], Ownership)>,
+    /// Mode and generations of the data the current texture was drawn from
+    drawn: Option<(MapMode, [u64; 6])>,
+    /// Whether clicking a province assigns it to `brush_country`
+    brush: bool,
+    brush_country: Option<Ident>,
+    /// Last province painted by the current drag of the brush. The texture isn't redrawn until the drag ends
+    painting: Option<u32>
+}
+
+const UNOWNED: Color32 = Color32::from_gray(40);
+const UNKNOWN: Color32 = Color32::from_gray(110);
+
+impl<'a> MapView<'a> {
+    #[inline]
+    pub fn new (game: &'a Game) -> Self {
+        return Self {
+            game,
+            loading: None,
+            map: None,
+            mode: MapMode::default(),
+            zoom: 1.0,
+            texture: None,
+            ownership: None,
+            drawn: None,
+            brush: false,
+            brush_country: None,
+            painting: None
+        }
+    }
+
+    #[inline]
+    pub fn map (&self) -> Option<&ProvinceMap> {
+        return self.map.as_ref().and_then(|x| x.as_ref().ok())
+    }
+
+    /// Draws the map, returning the state that was clicked, if any.
+    /// While the brush is enabled, clicked provinces are assigned to the selected country instead.
+    pub fn update (&mut self, ui: &mut Ui) -> Option<Ident> {
+        if !self.poll_map(ui) {
+            return None
+        }
+
+        ui.horizontal(|ui| {
+            for mode in MapMode::ALL {
+                ui.selectable_value(&mut self.mode, mode, mode.name());
+            }
+            ui.separator();
+            ui.add(Slider::new(&mut self.zoom, 1.0..=16.0).logarithmic(true).text("Zoom"));
+        });
+        self.brush_options(ui);
+
+        let map = match self.map {
+            Some(Ok(ref map)) => map,
+            _ => return None
+        };
+
+        // ownership and colors are only found again after the data they come from is edited (e.g. by the brush)
+        let states = &self.game.states;
+        let ownership_source = [states.defs.generation(), states.regions.generation()];
+        if self.ownership.as_ref().map_or(true, |(source, _)| *source != ownership_source) {
+            self.ownership = Some((ownership_source, Ownership::new(self.game, map)));
+        }
+        let ownership = &self.ownership.as_ref()?.1;
+        let owners = &ownership.owners;
+
+        let drawn = (self.mode, [
+            ownership_source[0],
+            ownership_source[1],
+            states.pops.generation(),
+            self.game.countries.definitions.generation(),
+            self.game.cultures.generation(),
+            self.game.religions.generation()
+        ]);
+        if self.texture.is_none() || (self.drawn != Some(drawn) && self.painting.is_none()) {
+            let colors = self.colors(owners);
+            self.texture = Some(ui.ctx().load_texture("province_map", render(map, &colors), TextureOptions::NEAREST));
+            self.drawn = Some(drawn);
+        }
+        let texture = self.texture.as_ref()?;
+
+        if self.brush {
+            ownership_warnings(ui, map, ownership);
+        }
+
+        let mut clicked = None;
+        let mut painted = None;
+        ScrollArea::both().show(ui, |ui| {
+            let width = ui.available_width() * self.zoom;
+            let size = vec2(width, width * map.height() as f32 / map.width() as f32);
+            let (rect, response) = ui.allocate_exact_size(size, Sense::click_and_drag());
+            ui.painter().image(texture.id(), rect, Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0)), Color32::WHITE);
+
+            let hovered = response.hover_pos().and_then(|pos| {
+                let pos = (pos - rect.min) / rect.size();
+                map.index_at((pos.x * map.width() as f32) as u32, (pos.y * map.height() as f32) as u32)
+            });
+
+            if !response.dragged() && self.painting.take().is_some() {
+                // draws the provinces that were painted during the drag
+                ui.ctx().request_repaint();
+            }
+
+            if let Some(idx) = hovered {
+                let owner = owners[idx as usize].as_ref();
+                match self.brush_country {
+                    Some(ref country) if self.brush && (response.clicked() || response.dragged()) => {
+                        if self.painting != Some(idx) && owner.map_or(true, |x| !x.country.eq_name(country)) {
+                            painted = Some(idx);
+                        }
+                        if response.dragged() {
+                            self.painting = Some(idx);
+                        }
+                    },
+                    _ if response.clicked() => clicked = owner.map(|x| x.state.clone()),
+                    _ => {}
+                }
+
+                let province = map.provinces()[idx as usize];
+                let text = match owner {
+                    Some(owner) => format!("{province}\n{}\n{}", owner.state.value, owner.country),
+                    None => province.to_string()
+                };
+                response.on_hover_text_at_pointer(text);
+            }
+        });
+
+        if let (Some(idx), Some(country)) = (painted, self.brush_country.as_ref()) {
+            if let Some(state) = states.assign_province(map.provinces()[idx as usize], country) {
+                // only the painted province changed owner, so the rest of the ownership is kept
+                if let Some((source, ownership)) = self.ownership.as_mut() {
+                    ownership.assign(idx, ProvinceOwner { state: Ident { value: state, kind: IdentKind::State }, country: country.clone() });
+                    *source = [states.defs.generation(), states.regions.generation()];
+                }
+            }
+        }
+
+        return clicked
+    }
+
+    fn brush_options (&mut self, ui: &mut Ui) {
+        let definitions = self.game.countries.definitions.borrow();
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.brush, "Brush");
+            ComboBox::from_id_source("brush_country")
+                .selected_text(self.brush_country.as_ref().map_or("Select a country", |x| x.value.as_str()))
+                .show_ui(ui, |ui| {
+                    for tag in definitions.keys() {
+                        let selected = self.brush_country.as_ref().map_or(false, |x| x.eq_name(tag));
+                        if ui.selectable_label(selected, tag.value.as_str()).clicked() {
+                            self.brush_country = Some(Ident { value: tag.value.clone(), kind: IdentKind::Country });
+                        }
+                    }
+                });
+
+            if self.brush {
+                ui.label(RichText::new("Click or drag over provinces to assign them").weak());
+            }
+        });
+    }
+
+    /// Starts loading the map if needed, returning whether it's done
+    fn poll_map (&mut self, ui: &mut Ui) -> bool {
+        if self.map.is_some() {
+            if let Some(Err(ref e)) = self.map {
+                ui.label(RichText::new(e).color(Color32::LIGHT_RED));
+                if ui.button("Retry").clicked() {
+                    self.map = None;
+                }
+            }
+            return true
+        }
+
+        match self.loading {
+            Some(ref handle) if handle.is_finished() => {
+                let handle = self.loading.take().unwrap();
+                self.map = Some(match runtime().block_on(handle) {
+                    Ok(Ok(map)) => Ok(map),
+                    Ok(Err(e)) => Err(e.to_string()),
+                    Err(e) => Err(e.to_string())
+                });
+                return true
+            },
+
+            Some(_) => {},
+
+            None => {
+                let layers = self.game.layers.clone();
+                self.loading = Some(runtime().spawn(async move { ProvinceMap::from_game(&layers).await }));
+            }
+        }
+
+        ui.horizontal(|ui| {
+            ui.spinner();
+            ui.label("Loading province map...");
+        });
+        ui.ctx().request_repaint();
+        return false
+    }
+
+    /// Color of every province for the current mode
+    fn colors (&self, owners: &[Option<ProvinceOwner>]) -> Vec<Color32> {
+        let definitions = self.game.countries.definitions.borrow();
+        let cultures = self.game.cultures.borrow();
+        let religions = self.game.religions.borrow();
+        let pops = self.game.states.pops.borrow();
+
+        return owners.iter().map(|owner| {
+            let owner = match owner {
+                Some(owner) => owner,
+                None => return UNOWNED
+            };
+
+            let pops = pops.get(&owner.state)
+                .and_then(|x| x.get(&owner.country.value))
+                .unwrap_or_default();
+
+            let color: Option<Color32> = match self.mode {
+                MapMode::Country => definitions.get(&Ident::from_str(&owner.country.value)).map(|x| x.color.into()),
+                MapMode::State => Some(hash_color(&owner.state.value)),
+                MapMode::Culture => majority(pops.iter().map(|x| (x.culture.value.as_str(), x.size)))
+                    .and_then(|x| cultures.get(x))
+                    .map(|x| x.color.into()),
+                MapMode::Religion => majority(pops.iter().filter_map(|x| Some((x.religion(&cultures)?, x.size))))
+                    .and_then(|x| religions.get(x))
+                    .map(|x| x.color.into())
+            };
+
+            color.unwrap_or(UNKNOWN)
+        }).collect()
+    }
+}
+
+/// Owners of the provinces of the map
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Ownership {
+    /// Owner of every province, by index
+    pub owners: Vec<Option<ProvinceOwner>>,
+    /// Provinces of a state region without an owner
+    pub unowned: Vec<u32>,
+    /// Provinces with more than one owner
+    pub owned_twice: Vec<u32>
+}
+
+impl Ownership {
+    pub fn new (game: &Game, map: &ProvinceMap) -> Self {
+        let defs = game.states.defs.borrow();
+        let regions = game.states.regions.borrow();
+        let mut owners = vec![None; map.provinces().len()];
+        let mut owned_twice = Vec::new();
+
+        for (state, def) in defs.iter() {
+            for region in def.regions.iter() {
+                for province in region.owned_provinces.iter() {
+                    if let Some(idx) = map.index_of(*province) {
+                        if owners[idx as usize].replace(ProvinceOwner { state: state.clone(), country: region.country.clone() }).is_some() {
+                            owned_twice.push(idx);
+                        }
+                    }
+                }
+            }
+        }
+
+        let unowned = regions.values()
+            .flat_map(|x| x.provinces.iter().filter(|p| !x.impassable.contains(p)))
+            .filter_map(|x| map.index_of(*x))
+            .filter(|x| owners[*x as usize].is_none())
+            .collect();
+
+        owned_twice.sort_unstable();
+        owned_twice.dedup();
+        return Self { owners, unowned, owned_twice }
+    }
+
+    /// Gives the province at `idx` to `owner` alone, as [`GameState::assign_province`](crate::data::state::GameState::assign_province) does
+    pub fn assign (&mut self, idx: u32, owner: ProvinceOwner) {
+        self.owners[idx as usize] = Some(owner);
+        self.unowned.retain(|x| *x != idx);
+        self.owned_twice.retain(|x| *x != idx);
+    }
+}
+
+/// Lists the provinces that are left unowned or owned twice
+fn ownership_warnings (ui: &mut Ui, map: &ProvinceMap, ownership: &Ownership) {
+    for (name, provinces) in [("unowned", &ownership.unowned), ("owned twice", &ownership.owned_twice)] {
+        if provinces.is_empty() {
+            continue
+        }
+
+        let title = RichText::new(format!("{} provinces {name}", provinces.len())).color(Color32::YELLOW);
+        CollapsingHeader::new(title).id_source(("ownership_warnings", name)).show(ui, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                for idx in provinces.iter() {
+                    ui.monospace(map.provinces()[*idx as usize].to_string());
+                }
+            });
+        });
+    }
+}
+
+/// Downscaled image of the map, so that it fits in a texture
+fn render (map: &ProvinceMap, colors: &[Color32]) -> ColorImage {
+    const MAX_SIZE: u32 = 4096;
+    // at least 1, so that empty maps don't divide by zero
+    let scale = ((map.width().max(map.height()) + MAX_SIZE - 1) / MAX_SIZE).max(1);
+    let width = map.width() / scale;
+    let height = map.height() / scale;
+
+    let mut pixels = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let idx = map.index_at(x * scale, y * scale).unwrap_or_default();
+            pixels.push(colors.get(idx as usize).copied().unwrap_or(UNOWNED));
+        }
+    }
+
+    return ColorImage { size: [width as usize, height as usize], pixels }
+}
+
+/// Key with the biggest total size
+#[inline]
+fn majority<'a> (entries: impl IntoIterator<Item = (&'a str, u64)>) -> Option<&'a str> {
+    let mut totals = std::collections::BTreeMap::<&str, u64>::new();
+    for (key, size) in entries {
+        *totals.entry(key).or_default() += size;
+    }
+    return totals.into_iter().max_by_key(|(_, size)| *size).map(|(key, _)| key)
+}
+
+/// Arbitrary, but stable, color for `key`
+#[inline]
+fn hash_color (key: &str) -> Color32 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    let [r, g, b, ..] = hasher.finish().to_le_bytes();
+    return Color32::from_rgb(r, g, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use eframe::epaint::Color32;
+    use crate::data::{Ident, map::ProvinceMap};
+    use super::{render, Ownership, ProvinceOwner};
+
+    #[test]
+    fn renders_empty_map () {
+        assert_eq!(render(&ProvinceMap::from_rgb(0, 0, &[]), &[]).size, [0, 0]);
+
+        let image = render(&ProvinceMap::from_rgb(1, 2, &[0, 0, 1, 0, 0, 2]), &[Color32::RED, Color32::BLUE]);
+        assert_eq!(image.pixels, [Color32::RED, Color32::BLUE]);
+    }
+
+    #[test]
+    fn assigns_single_province () {
+        let owner = |state: &str, country: &str| Some(ProvinceOwner { state: Ident::from_str(state), country: Ident::from_str(country) });
+        let mut ownership = Ownership { owners: vec![owner("s:STATE_A", "c:GBR"), None, owner("s:STATE_A", "c:FRA")], unowned: vec![1], owned_twice: vec![2] };
+
+        ownership.assign(1, owner("s:STATE_A", "c:FRA").unwrap());
+        ownership.assign(2, owner("s:STATE_A", "c:GBR").unwrap());
+        assert_eq!(ownership, Ownership { owners: vec![owner("s:STATE_A", "c:GBR"), owner("s:STATE_A", "c:FRA"), owner("s:STATE_A", "c:GBR")], unowned: Vec::new(), owned_twice: Vec::new() });
+    }
+}
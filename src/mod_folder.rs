@@ -1,14 +1,30 @@
 use std::{pin::Pin};
 use eframe::{egui::*, App};
 use sis::self_referencing;
-use crate::{data::{Game, religion::{Religion}, culture::Culture, country::{CountryRank, CountryType}}, utils::list::List, states::States};
+use crate::{data::{Game, Ident, religion::{Religion}, culture::Culture, pop_type::PopType, country::{CountryRank, CountryType, CountryDefinition}, economy::{Goods, Building, ProductionMethod, ProductionMethodGroup}, law::{Law, LawGroup, GroupLaws}, politics::{InterestGroup, Ideology, PoliticalMovement}}, utils::list::List, states::States, map::MapView, tech_tree::TechTree, flag_preview::FlagPreview, localization_editor::LocalizationEditor, save_mod::SaveMod, problems::Problems};
 
 pub struct ModFolderLists<'this> {
     religions: List<'this, Religion>,
     cultures: List<'this, Culture>,
+    pop_types: List<'this, PopType>,
     country_ranks: List<'this, CountryRank>,
     country_types: List<'this, CountryType>,
-    states: States<'this>
+    countries: List<'this, CountryDefinition, Ident>,
+    flag: FlagPreview<'this>,
+    goods: List<'this, Goods>,
+    buildings: List<'this, Building>,
+    production_methods: List<'this, ProductionMethod>,
+    production_method_groups: List<'this, ProductionMethodGroup>,
+    law_groups: List<'this, LawGroup>,
+    group_laws: GroupLaws,
+    laws: List<'this, Law>,
+    interest_groups: List<'this, InterestGroup>,
+    ideologies: List<'this, Ideology>,
+    movements: List<'this, PoliticalMovement>,
+    states: States<'this>,
+    map: MapView<'this>,
+    tech_tree: TechTree<'this>,
+    localization: LocalizationEditor<'this>
 }
 
 impl<'this> ModFolderLists<'this> {
@@ -17,9 +33,25 @@ impl<'this> ModFolderLists<'this> {
         return Self {
             religions: List::new("Religions", &game.religions),
             cultures: List::new("Cultures", &game.cultures),
+            pop_types: List::new("Pop Types", &game.pop_types),
             country_ranks: List::new("Country Ranks", &game.countries.ranks),
             country_types: List::new("Country Types", &game.countries.tys),
-            states: States::new(game)
+            countries: List::new("Countries", &game.countries.definitions),
+            flag: FlagPreview::new(game),
+            goods: List::new("Goods", &game.economy.goods),
+            buildings: List::new("Buildings", &game.economy.buildings),
+            production_methods: List::new("Production Methods", &game.economy.production_methods),
+            production_method_groups: List::new("Production Method Groups", &game.economy.production_method_groups),
+            law_groups: List::new("Law Groups", &game.laws.groups),
+            group_laws: GroupLaws::default(),
+            laws: List::new("Laws", &game.laws.laws),
+            interest_groups: List::new("Interest Groups", &game.politics.interest_groups),
+            ideologies: List::new("Ideologies", &game.politics.ideologies),
+            movements: List::new("Political Movements", &game.politics.movements),
+            states: States::new(game),
+            map: MapView::new(game),
+            tech_tree: TechTree::new(game),
+            localization: LocalizationEditor::new(game)
         }
     }
 }
@@ -30,9 +62,25 @@ pub struct ModFolder {
     writeable: bool,
     show_cultures: bool,
     show_religions: bool,
+    show_pop_types: bool,
     show_country_ranks: bool,
     show_country_types: bool,
+    show_countries: bool,
+    show_goods: bool,
+    show_buildings: bool,
+    show_production_methods: bool,
+    show_production_method_groups: bool,
+    show_law_groups: bool,
+    show_laws: bool,
+    show_tech_tree: bool,
+    show_interest_groups: bool,
+    show_ideologies: bool,
+    show_movements: bool,
     show_states: bool,
+    show_map: bool,
+    show_localization: bool,
+    save_mod: SaveMod,
+    problems: Problems,
     #[borrows(game)]
     lists: ModFolderLists<'this>
 }
@@ -41,7 +89,7 @@ impl<'this> App for ModFolder<'this> {
     #[inline]
     fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
         let game = unsafe { Pin::new_unchecked(&mut self.game) };
-        let ModFolderLists { religions, cultures, country_ranks, country_types, states } = unsafe { self.lists.assume_init_mut() };
+        let ModFolderLists { religions, cultures, pop_types, country_ranks, country_types, countries, flag, goods, buildings, production_methods, production_method_groups, law_groups, group_laws, laws, interest_groups, ideologies, movements, states, map, tech_tree, localization } = unsafe { self.lists.assume_init_mut() };
         let _pin = unsafe { Pin::new_unchecked(&mut self._pin) };
 
         // Misc
@@ -57,6 +105,19 @@ impl<'this> App for ModFolder<'this> {
             .show(ctx, |ui| {
                 cultures.update(ui, &game);
             });
+        Window::new("Pop Types")
+            .open(&mut self.show_pop_types)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                pop_types.update(ui, &game);
+            });
+
+        Window::new("Localization")
+            .open(&mut self.show_localization)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                localization.update(ui);
+            });
 
         // Country
         Window::new("Country Ranks")
@@ -71,6 +132,95 @@ impl<'this> App for ModFolder<'this> {
             .show(ctx, |ui| {
                 country_types.update(ui, &game);
             });
+        Window::new("Countries")
+            .open(&mut self.show_countries)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                countries.update(ui, &game);
+                if let Some(key) = countries.current() {
+                    ui.separator();
+                    ui.heading("Flag");
+                    flag.update(ui, &key.value);
+                }
+                if let Some(history) = countries.current().and_then(|key| game.countries.history_of(&key.value)) {
+                    ui.separator();
+                    ui.heading("Starting setup");
+                    history.render(ui);
+                }
+            });
+
+        // Economy
+        Window::new("Goods")
+            .open(&mut self.show_goods)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                goods.update(ui, &game);
+            });
+        Window::new("Buildings")
+            .open(&mut self.show_buildings)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                buildings.update(ui, &game);
+            });
+        Window::new("Production Methods")
+            .open(&mut self.show_production_methods)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                production_methods.update(ui, &game);
+            });
+        Window::new("Production Method Groups")
+            .open(&mut self.show_production_method_groups)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                production_method_groups.update(ui, &game);
+            });
+
+        // Laws
+        Window::new("Law Groups")
+            .open(&mut self.show_law_groups)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                law_groups.update(ui, &game);
+                if let Some(group) = law_groups.current() {
+                    ui.separator();
+                    ui.heading("Laws");
+                    group_laws.update(ui, &game.laws.laws, group);
+                }
+            });
+        Window::new("Laws")
+            .open(&mut self.show_laws)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                laws.update(ui, &game);
+            });
+
+        // Politics
+        Window::new("Interest Groups")
+            .open(&mut self.show_interest_groups)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                interest_groups.update(ui, &game);
+            });
+        Window::new("Ideologies")
+            .open(&mut self.show_ideologies)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                ideologies.update(ui, &game);
+            });
+        Window::new("Political Movements")
+            .open(&mut self.show_movements)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                movements.update(ui, &game);
+            });
+
+        // Technologies
+        Window::new("Technologies")
+            .open(&mut self.show_tech_tree)
+            .default_size([900.0, 500.0])
+            .show(ctx, |ui| {
+                tech_tree.update(ui);
+            });
 
         // States
         Window::new("States")
@@ -79,6 +229,25 @@ impl<'this> App for ModFolder<'this> {
             .show(ctx, |ui| {
                 states.update(ui);
             });
+
+        // Map
+        let mut clicked_state = None;
+        Window::new("Map")
+            .open(&mut self.show_map)
+            .default_size([800.0, 450.0])
+            .show(ctx, |ui| {
+                clicked_state = map.update(ui);
+            });
+
+        if let Some(state) = clicked_state {
+            states.select(state);
+            self.show_states = true;
+        }
+
+        self.problems.update(ctx, &game);
+        self.save_mod.update(ctx, &game);
+        // once saved as a mod, edits can be written back to it
+        self.writeable |= self.save_mod.project.is_some();
         
         CentralPanel::default().show(ctx, |ui| {
             ui.horizontal(|ui| {
@@ -92,8 +261,13 @@ impl<'this> App for ModFolder<'this> {
                         format!("Religions ({})", game.religions.borrow().len())
                     );
         
+                    let pop_types = ui.button(
+                        format!("Pop Types ({})", game.pop_types.borrow().len())
+                    );
+
                     self.show_cultures ^= cultures.clicked();
                     self.show_religions ^= religions.clicked();
+                    self.show_pop_types ^= pop_types.clicked();
                 });
 
                 // Country info
@@ -106,8 +280,116 @@ impl<'this> App for ModFolder<'this> {
                         format!("Country Types ({})", game.countries.tys.borrow().len())
                     );
         
+                    let countries = ui.button(
+                        format!("Countries ({})", game.countries.definitions.borrow().len())
+                    );
+
                     self.show_country_ranks ^= ranks.clicked();
                     self.show_country_types ^= tys.clicked();
+                    self.show_countries ^= countries.clicked();
+                });
+
+                // Economy
+                ui.vertical(|ui| {
+                    let goods = ui.button(
+                        format!("Goods ({})", game.economy.goods.borrow().len())
+                    );
+
+                    let buildings = ui.button(
+                        format!("Buildings ({})", game.economy.buildings.borrow().len())
+                    );
+
+                    let methods = ui.button(
+                        format!("Production Methods ({})", game.economy.production_methods.borrow().len())
+                    );
+
+                    let groups = ui.button(
+                        format!("Production Method Groups ({})", game.economy.production_method_groups.borrow().len())
+                    );
+
+                    self.show_goods ^= goods.clicked();
+                    self.show_buildings ^= buildings.clicked();
+                    self.show_production_methods ^= methods.clicked();
+                    self.show_production_method_groups ^= groups.clicked();
+                });
+
+                // Laws
+                ui.vertical(|ui| {
+                    let law_groups = ui.button(
+                        format!("Law Groups ({})", game.laws.groups.borrow().len())
+                    );
+
+                    let laws = ui.button(
+                        format!("Laws ({})", game.laws.laws.borrow().len())
+                    );
+
+                    self.show_law_groups ^= law_groups.clicked();
+                    self.show_laws ^= laws.clicked();
+                });
+
+                // Politics
+                ui.vertical(|ui| {
+                    let interest_groups = ui.button(
+                        format!("Interest Groups ({})", game.politics.interest_groups.borrow().len())
+                    );
+
+                    let ideologies = ui.button(
+                        format!("Ideologies ({})", game.politics.ideologies.borrow().len())
+                    );
+
+                    let movements = ui.button(
+                        format!("Political Movements ({})", game.politics.movements.borrow().len())
+                    );
+
+                    self.show_interest_groups ^= interest_groups.clicked();
+                    self.show_ideologies ^= ideologies.clicked();
+                    self.show_movements ^= movements.clicked();
+                });
+
+                // Technologies
+                ui.vertical(|ui| {
+                    let techs = ui.button(
+                        format!("Technologies ({})", game.technologies.borrow().len())
+                    );
+
+                    self.show_tech_tree ^= techs.clicked();
+                });
+
+                // States
+                ui.vertical(|ui| {
+                    let states = ui.button(
+                        format!("States ({})", game.states.defs.borrow().len())
+                    );
+
+                    let map = ui.button("Map");
+
+                    self.show_states ^= states.clicked();
+                    self.show_map ^= map.clicked();
+                });
+
+                // Localization
+                ui.vertical(|ui| {
+                    let localization = ui.button(
+                        format!("Localization ({})", game.localization.borrow().languages.len())
+                    );
+
+                    self.show_localization ^= localization.clicked();
+                });
+
+                // Mod
+                ui.vertical(|ui| {
+                    let problems = ui.button(match self.problems.len() {
+                        Some(len) => format!("Problems ({len})"),
+                        None => "Problems".to_string()
+                    });
+                    self.problems.open ^= problems.clicked();
+
+                    if self.writeable && ui.button("Save").clicked() {
+                        self.save_mod.save(&game);
+                    }
+
+                    let save_as = ui.button("Save as mod");
+                    self.save_mod.open ^= save_as.clicked();
                 });
             });
         });
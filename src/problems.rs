@@ -0,0 +1,67 @@
+use eframe::egui::{Context, Window, RichText, Color32, ScrollArea, Grid};
+use crate::data::{Game, validate::{Diagnostic, Severity}};
+
+/// Lists the diagnostics of the last validation pass
+#[derive(Debug, Default)]
+pub struct Problems {
+    pub open: bool,
+    diagnostics: Option<Vec<Diagnostic>>
+}
+
+impl Problems {
+    #[inline]
+    pub fn len (&self) -> Option<usize> {
+        return self.diagnostics.as_ref().map(Vec::len)
+    }
+
+    #[inline]
+    pub fn validate (&mut self, game: &Game) {
+        self.diagnostics = Some(game.validate());
+    }
+
+    pub fn update (&mut self, ctx: &Context, game: &Game) {
+        let mut open = self.open;
+        Window::new("Problems")
+            .open(&mut open)
+            .vscroll(false)
+            .show(ctx, |ui| {
+                if self.diagnostics.is_none() || ui.button("Validate").clicked() {
+                    self.validate(game);
+                }
+
+                let diagnostics = match self.diagnostics {
+                    Some(ref x) => x,
+                    None => return
+                };
+
+                if diagnostics.is_empty() {
+                    ui.label("No problems found");
+                    return
+                }
+
+                ScrollArea::vertical().show(ui, |ui| {
+                    Grid::new("problems_grid").striped(true).show(ui, |ui| {
+                        for diagnostic in diagnostics.iter() {
+                            let severity = match diagnostic.severity {
+                                Severity::Error => RichText::new("Error").color(Color32::LIGHT_RED),
+                                Severity::Warning => RichText::new("Warning").color(Color32::YELLOW)
+                            };
+
+                            ui.label(severity);
+                            ui.label(diagnostic.category);
+                            ui.label(RichText::new(&diagnostic.key).strong());
+                            ui.label(diagnostic.field);
+                            ui.label(&diagnostic.message);
+
+                            let label = ui.label(diagnostic.source.as_ref().and_then(|x| x.file_name()).map(|x| x.to_string_lossy().into_owned()).unwrap_or_default());
+                            if let Some(ref source) = diagnostic.source {
+                                label.on_hover_text(source.file.display().to_string());
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+        self.open = open;
+    }
+}
@@ -0,0 +1,146 @@
+use std::path::Path;
+use eframe::egui::{Context, Window, RichText, Color32};
+use rfd::FileDialog;
+use crate::{runtime, data::{Game, Mod, ModMetadata}, utils::attribute_text};
+
+/// Form used to save the current edits as a separate mod folder
+#[derive(Debug, Default)]
+pub struct SaveMod {
+    pub open: bool,
+    name: String,
+    id: String,
+    version: String,
+    supported_game_version: String,
+    tags: String,
+    path: String,
+    message: Option<Result<String, String>>,
+    /// Mod that the edits were last saved to
+    pub project: Option<Mod>
+}
+
+impl SaveMod {
+    /// Saves into the last mod that was saved to, returning whether there was one
+    #[inline]
+    pub fn save (&mut self, game: &Game) -> bool {
+        let project = match self.project {
+            Some(ref project) => project,
+            None => return false
+        };
+
+        self.message = Some(match runtime().block_on(project.save(game)) {
+            Ok(_) => Ok(format!("Saved to {}", project.path.game().display())),
+            Err(e) => Err(e.to_string())
+        });
+        return true
+    }
+
+    pub fn update (&mut self, ctx: &Context, game: &Game) {
+        let mut open = self.open;
+        Window::new("Save as mod")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                attribute_text(ui, "Name", &mut self.name);
+                attribute_text(ui, "Id", &mut self.id);
+                attribute_text(ui, "Version", &mut self.version);
+                attribute_text(ui, "Supported game version", &mut self.supported_game_version);
+                attribute_text(ui, "Tags", &mut self.tags);
+
+                ui.horizontal(|ui| {
+                    if ui.button("Select mod folder").clicked() {
+                        self.select_path();
+                    }
+                    ui.text_edit_singleline(&mut self.path);
+                });
+
+                if ui.button("Save").clicked() {
+                    self.save_as(game);
+                }
+
+                match self.message {
+                    Some(Ok(ref msg)) => { ui.label(msg); },
+                    Some(Err(ref msg)) => { ui.label(RichText::new(msg).background_color(Color32::DARK_RED)); },
+                    None => {}
+                }
+            });
+        self.open = open;
+    }
+
+    fn save_as (&mut self, game: &Game) {
+        match self.metadata() {
+            Ok(metadata) => {
+                self.project = Some(Mod::new(self.path.clone(), metadata));
+                self.save(game);
+            },
+            Err(e) => self.message = Some(Err(e))
+        }
+    }
+
+    /// Metadata of the mod typed on the form, keeping the fields it doesn't show from the last mod saved to
+    fn metadata (&self) -> Result<ModMetadata, String> {
+        if self.name.is_empty() || self.path.is_empty() {
+            return Err("A mod name and folder must be specified".to_string())
+        }
+
+        let mut metadata = match self.project {
+            Some(ref project) => project.metadata.clone(),
+            None => ModMetadata::new(String::new(), String::new())
+        };
+
+        metadata.name = self.name.clone();
+        metadata.id = match self.id.is_empty() {
+            true => self.name.to_lowercase().replace(char::is_whitespace, "_"),
+            false => self.id.clone()
+        };
+        metadata.version = self.version.clone();
+        metadata.supported_game_version = self.supported_game_version.clone();
+        metadata.tags = self.tags.split(',')
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .map(ToString::to_string)
+            .collect();
+
+        return Ok(metadata)
+    }
+
+    #[inline]
+    fn select_path (&mut self) {
+        let mut builder = FileDialog::new();
+        if !self.path.is_empty() {
+            let path = <String as AsRef<Path>>::as_ref(&self.path);
+            if path.is_dir() {
+                builder = builder.set_directory(path);
+            }
+        }
+
+        match builder.pick_folder().map(|x| x.into_os_string().into_string()) {
+            Some(Ok(x)) => self.path = x,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{Mod, ModMetadata};
+    use super::SaveMod;
+
+    #[test]
+    fn metadata_from_form () {
+        let mut form = SaveMod { name: "My Mod".to_string(), tags: "Gameplay, ,Map".to_string(), ..Default::default() };
+        assert!(form.metadata().is_err());
+
+        form.path = "mods/my_mod".to_string();
+        let metadata = form.metadata().unwrap();
+        assert_eq!(metadata.id, "my_mod");
+        assert_eq!(metadata.tags, ["Gameplay", "Map"]);
+
+        // fields that the form doesn't show are kept from the mod saved to last
+        let mut previous = ModMetadata::new("Old", "old");
+        previous.short_description = "Kept".to_string();
+        form.project = Some(Mod::new("mods/my_mod", previous));
+        form.id = "custom".to_string();
+
+        let metadata = form.metadata().unwrap();
+        assert_eq!((metadata.name.as_str(), metadata.id.as_str(), metadata.short_description.as_str()), ("My Mod", "custom", "Kept"));
+    }
+}
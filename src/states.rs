@@ -1,18 +1,394 @@
-use eframe::egui::Ui;
-use crate::data::{Game};
+use std::collections::BTreeMap;
+use eframe::egui::{Ui, SidePanel, ScrollArea, RichText, Label, Sense, TextStyle, Grid, DragValue, CollapsingHeader, TextEdit, ComboBox, Color32};
+use crate::data::{Game, Ident, culture::Culture, religion::Religion, pop_type::{PopType, Strata}, state::{CreatePop, RegionDefinition, RegionPops, PopTotals, StateRegion}};
 
 pub struct States<'a> {
-    game: &'a Game
+    game: &'a Game,
+    current: Option<Ident>,
+    filter: String,
+    show_totals: bool,
+    /// Totals of all states, along with the generations of the pops and cultures they were summed from
+    all_totals: Option<([u64; 2], PopTotals)>
 }
 
 impl<'a> States<'a> {
     #[inline]
     pub fn new (game: &'a Game) -> Self {
-        return Self { game }
+        return Self { game, current: None, filter: String::new(), show_totals: false, all_totals: None }
     }
 
+    /// Selects `state` on the list
     #[inline]
+    pub fn select (&mut self, state: Ident) {
+        self.current = Some(state);
+    }
+
     pub fn update (&mut self, ui: &mut Ui) {
+        let defs = self.game.states.defs.borrow();
+
+        SidePanel::left("states_list").show_inside(ui, |ui| {
+            ui.checkbox(&mut self.show_totals, "Totals of all states");
+            ui.add(TextEdit::singleline(&mut self.filter).hint_text("Filter"));
+            let filter = self.filter.to_lowercase();
+            let names = defs.keys()
+                .filter(|x| x.value.to_lowercase().contains(&filter))
+                .collect::<Vec<_>>();
+
+            let height = ui.text_style_height(&TextStyle::Body);
+            ScrollArea::vertical().show_rows(ui, height, names.len(), |ui, range| {
+                for name in &names[range] {
+                    let mut text = RichText::new(&name.value);
+                    if self.current.as_ref() == Some(*name) {
+                        text = text.strong();
+                    }
+
+                    if ui.add(Label::new(text).sense(Sense::click())).clicked() {
+                        self.current = Some((*name).clone())
+                    }
+                }
+            });
+        });
+
+        let cultures = self.game.cultures.borrow();
+        let religions = self.game.religions.borrow();
+        let pop_types = self.game.pop_types.borrow();
+        let regions = self.game.states.regions.borrow();
+        let pops = self.game.states.pops.borrow();
+        let totals_source = [self.game.states.pops.generation(), self.game.cultures.generation()];
+
+        // the pops of the state are edited on a copy, which is only written back if it changed
+        let edited = ScrollArea::vertical().id_source("state_info").show(ui, |ui| {
+            if self.show_totals {
+                ui.heading("All states");
+                // only summed again after the pops are edited
+                if self.all_totals.as_ref().map_or(true, |(source, _)| *source != totals_source) {
+                    self.all_totals = Some((totals_source, PopTotals::new(pops.iter(), &cultures)));
+                }
+                if let Some((_, ref all)) = self.all_totals {
+                    totals(ui, "all_states", all, true);
+                }
+                ui.separator();
+            }
+
+            let (key, def) = self.current.as_ref().and_then(|key| defs.get_key_value(key))?;
+            ui.heading(&key.value);
 
+            match regions.get(&key.value) {
+                Some(map) => {
+                    CollapsingHeader::new(RichText::new("Geography").strong())
+                        .id_source((&key.value, "geography"))
+                        .show(ui, |ui| geography(ui, map));
+                },
+                None => { ui.label(RichText::new("No map data").weak()); }
+            }
+
+            if !def.homelands.is_empty() {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label(RichText::new("Homelands").strong());
+                    for homeland in def.homelands.iter() {
+                        ui.label(&homeland.value);
+                    }
+                });
+            }
+
+            let original = pops.get(key);
+            let mut state_pops = original.cloned();
+            for region in def.regions.iter() {
+                CollapsingHeader::new(RichText::new(region.country.to_string()).strong())
+                    .id_source((&key.value, &region.country.value))
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        region_info(ui, region);
+
+                        ui.separator();
+                        match state_pops.as_mut().and_then(|x| x.get_mut(&region.country.value)) {
+                            Some(list) => pop_list(ui, &region.country.value, list, &cultures, &religions, &pop_types),
+                            None if ui.button("Add pops").clicked() => {
+                                state_pops.get_or_insert_with(|| RegionPops { regions: Vec::new() })
+                                    .get_or_insert(&region.country.value);
+                            },
+                            None => {}
+                        }
+                    });
+            }
+
+            if let Some(ref state_pops) = state_pops {
+                ui.separator();
+                totals(ui, &key.value, &PopTotals::new([(key, state_pops)], &cultures), false);
+            }
+
+            return state_pops.filter(|x| Some(x) != original).map(|x| (key.clone(), x))
+        }).inner;
+
+        drop(pops);
+        if let Some((key, state_pops)) = edited {
+            self.game.states.pops.borrow_mut().insert(key, state_pops);
+        }
     }
-}
\ No newline at end of file
+}
+
+fn geography (ui: &mut Ui, map: &StateRegion) {
+    Grid::new(("geography", map.id)).show(ui, |ui| {
+        ui.label(RichText::new("Id").strong());
+        ui.label(map.id.to_string());
+        ui.end_row();
+
+        ui.label(RichText::new("Provinces").strong());
+        ui.label(map.provinces.len().to_string());
+        ui.end_row();
+
+        if let Some(ref building) = map.subsistence_building {
+            ui.label(RichText::new("Subsistence building").strong());
+            ui.label(building);
+            ui.end_row();
+        }
+
+        if !map.traits.is_empty() {
+            ui.label(RichText::new("Traits").strong());
+            ui.label(map.traits.join(", "));
+            ui.end_row();
+        }
+
+        for (kind, province) in map.hubs() {
+            ui.label(RichText::new(format!("{kind} hub")).strong());
+            ui.monospace(province.to_string());
+            ui.end_row();
+        }
+
+        ui.label(RichText::new("Arable land").strong());
+        ui.label(map.arable_land.to_string());
+        ui.end_row();
+
+        if !map.arable_resources.is_empty() {
+            ui.label(RichText::new("Arable resources").strong());
+            ui.label(map.arable_resources.join(", "));
+            ui.end_row();
+        }
+
+        for (resource, cap) in map.capped_resources.iter() {
+            ui.label(RichText::new(resource).strong());
+            ui.label(cap.to_string());
+            ui.end_row();
+        }
+
+        for resource in map.resources.iter() {
+            ui.label(RichText::new(&resource.ty).strong());
+            ui.label(format!("{} discovered, {} undiscovered", resource.discovered_amount, resource.undiscovered_amount));
+            ui.end_row();
+        }
+
+        if let Some(naval_exit) = map.naval_exit_id {
+            ui.label(RichText::new("Naval exit").strong());
+            ui.label(naval_exit.to_string());
+            ui.end_row();
+        }
+    });
+}
+
+#[inline]
+fn region_info (ui: &mut Ui, region: &RegionDefinition) {
+    ui.horizontal_wrapped(|ui| {
+        ui.label(RichText::new("Owned provinces").strong());
+        ui.label(region.owned_provinces.len().to_string());
+        for province in region.owned_provinces.iter() {
+            ui.monospace(province.to_string());
+        }
+        for province in region.owned_provinces.invalid.iter() {
+            ui.monospace(RichText::new(province).color(Color32::LIGHT_RED));
+        }
+    });
+
+    if !region.state_type.is_empty() {
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("State types").strong());
+            for ty in region.state_type.iter() {
+                ui.label(&ty.value);
+            }
+        });
+    }
+}
+
+/// Editable list of the pops of a region
+fn pop_list (ui: &mut Ui, region: &str, pops: &mut Vec<CreatePop>, cultures: &BTreeMap<String, Culture>, religions: &BTreeMap<String, Religion>, pop_types: &BTreeMap<String, PopType>) {
+    enum Action {
+        Remove (usize),
+        Split (usize)
+    }
+
+    let order = by_strata(pops, pop_types);
+    let mut action = None;
+    Grid::new(("pops", region)).striped(true).show(ui, |ui| {
+        ui.label(RichText::new("Pop type").strong());
+        ui.label(RichText::new("Culture").strong());
+        ui.label(RichText::new("Religion").strong());
+        ui.label(RichText::new("Size").strong());
+        ui.end_row();
+
+        let mut group = None;
+        for (strata, i) in order {
+            if group != Some(strata) {
+                group = Some(strata);
+                ui.label(RichText::new(strata.map_or("No strata", Strata::as_str)).italics());
+                ui.end_row();
+            }
+
+            let pop = &mut pops[i];
+            ComboBox::from_id_source(("pop_type", region, i))
+                .selected_text(pop.pop_type.as_ref().map_or("None", |x| x.value.as_str()))
+                .show_ui(ui, |ui| {
+                    if ui.selectable_label(pop.pop_type.is_none(), "None").clicked() {
+                        pop.pop_type = None;
+                    }
+                    for name in pop_types.keys() {
+                        let selected = pop.pop_type.as_ref().map_or(false, |x| x.value == *name);
+                        if ui.selectable_label(selected, name.as_str()).clicked() {
+                            pop.pop_type = Some(Ident::from_str(name));
+                        }
+                    }
+                });
+
+            ComboBox::from_id_source(("pop_culture", region, i))
+                .selected_text(pop.culture.value.as_str())
+                .show_ui(ui, |ui| {
+                    for name in cultures.keys() {
+                        if ui.selectable_label(pop.culture.value == *name, name.as_str()).clicked() {
+                            pop.culture = Ident::from_str(name);
+                        }
+                    }
+                });
+
+            let religion = match (&pop.religion, pop.religion(cultures)) {
+                (Some(religion), _) => RichText::new(&religion.value),
+                (None, Some(religion)) => RichText::new(religion).weak(),
+                (None, None) => RichText::new("None").weak()
+            };
+
+            ComboBox::from_id_source(("pop_religion", region, i))
+                .selected_text(religion)
+                .show_ui(ui, |ui| {
+                    if ui.selectable_label(pop.religion.is_none(), "Culture's religion").clicked() {
+                        pop.religion = None;
+                    }
+                    for name in religions.keys() {
+                        let selected = pop.religion.as_ref().map_or(false, |x| x.value == *name);
+                        if ui.selectable_label(selected, name.as_str()).clicked() {
+                            pop.religion = Some(Ident::from_str(name));
+                        }
+                    }
+                });
+
+            ui.add(DragValue::new(&mut pop.size).speed(100));
+            if ui.small_button("Split").on_hover_text("Split in two halves").clicked() {
+                action = Some(Action::Split(i));
+            }
+            if ui.small_button("x").clicked() {
+                action = Some(Action::Remove(i));
+            }
+            ui.end_row();
+        }
+
+        // changing the total scales every pop proportionally
+        let total = pops.iter().map(|x| x.size).sum::<u64>();
+        let mut new_total = total;
+        ui.label(RichText::new("Total").strong());
+        ui.label("");
+        ui.label("");
+        ui.add(DragValue::new(&mut new_total).speed(1000));
+        ui.end_row();
+
+        if new_total != total {
+            CreatePop::scale(pops, new_total);
+        }
+    });
+
+    match action {
+        Some(Action::Remove(i)) => { pops.remove(i); },
+        Some(Action::Split(i)) => {
+            let other = pops[i].split();
+            pops.insert(i + 1, other);
+        },
+        None => {}
+    }
+
+    if ui.button("Add pop").clicked() {
+        pops.push(CreatePop {
+            culture: cultures.keys().next().map(|x| Ident::from_str(x)).unwrap_or_default(),
+            religion: None,
+            pop_type: None,
+            size: 0
+        });
+    }
+}
+
+/// Indices of `pops` grouped by strata, with the ones without a known pop type first, keeping their order within each group
+fn by_strata (pops: &[CreatePop], pop_types: &BTreeMap<String, PopType>) -> Vec<(Option<Strata>, usize)> {
+    let mut order = pops.iter()
+        .map(|pop| pop.pop_type.as_ref().and_then(|x| pop_types.get(&x.value)).map(|x| x.strata))
+        .zip(0..)
+        .collect::<Vec<_>>();
+    order.sort_by_key(|(strata, _)| *strata);
+    return order
+}
+
+/// Breakdown of the population of a set of pops
+fn totals (ui: &mut Ui, id: &str, totals: &PopTotals, by_state: bool) {
+    ui.label(RichText::new(format!("Total population: {}", totals.total)).strong());
+
+    let mut groups = vec![
+        ("By country", &totals.by_country),
+        ("By culture", &totals.by_culture),
+        ("By religion", &totals.by_religion)
+    ];
+    if by_state {
+        groups.insert(0, ("By state", &totals.by_state));
+    }
+
+    for (name, group) in groups {
+        CollapsingHeader::new(name).id_source((id, name)).show(ui, |ui| {
+            let mut entries = group.iter().collect::<Vec<_>>();
+            entries.sort_by(|lhs, rhs| rhs.1.cmp(lhs.1));
+
+            Grid::new((id, name, "grid")).striped(true).show(ui, |ui| {
+                for (key, size) in entries {
+                    ui.label(key);
+                    ui.label(size.to_string());
+                    ui.label(format!("{:.2}%", 100.0 * *size as f64 / totals.total.max(1) as f64));
+                    ui.end_row();
+                }
+            });
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use crate::data::{parse_slice, Ident, pop_type::{PopType, Strata}, state::CreatePop};
+    use super::by_strata;
+
+    #[test]
+    fn groups_pops_by_strata () {
+        let pop_types = parse_slice::<BTreeMap<String, PopType>>(b"
+            aristocrats = { texture = a.dds color = { 0.5 0.5 0.5 } strata = rich }
+            clerks = { texture = b.dds color = { 0.5 0.5 0.5 } strata = middle }
+            peasants = { texture = c.dds color = { 0.5 0.5 0.5 } strata = poor }
+        ").unwrap();
+
+        let pop = |pop_type: Option<&str>| CreatePop {
+            culture: Ident::from_str("british"),
+            religion: None,
+            pop_type: pop_type.map(Ident::from_str),
+            size: 1000
+        };
+
+        let pops = [pop(Some("aristocrats")), pop(Some("peasants")), pop(None), pop(Some("clerks")), pop(Some("peasants")), pop(Some("unknown"))];
+        assert_eq!(by_strata(&pops, &pop_types), [
+            (None, 2),
+            (None, 5),
+            (Some(Strata::Poor), 1),
+            (Some(Strata::Poor), 4),
+            (Some(Strata::Middle), 3),
+            (Some(Strata::Rich), 0)
+        ]);
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::{BTreeMap, BTreeSet};
+use eframe::{egui::{Ui, ScrollArea, Sense, RichText, Slider, Align2, TextStyle, Stroke}, epaint::{Color32, Rect, pos2, vec2, Pos2}};
+use crate::data::{Game, technology::Technology};
+
+const NODE_SIZE: [f32; 2] = [180.0, 28.0];
+const SPACING: [f32; 2] = [60.0, 12.0];
+/// Space left between the categories of an era
+const BAND_GAP: f32 = 32.0;
+
+/// Dependency graph of the technologies, with a column per era and a band per category
+pub struct TechTree<'a> {
+    game: &'a Game,
+    zoom: f32,
+    /// Technology whose requirements are highlighted
+    selected: Option<String>,
+    analysis: Option<Analysis>
+}
+
+/// Problems and layout of the tree, which are only found again when the technologies change
+struct Analysis {
+    /// Generation of the technologies it was computed from
+    generation: u64,
+    cycles: Vec<Vec<String>>,
+    unreachable: BTreeSet<String>,
+    layout: Layout
+}
+
+impl<'a> TechTree<'a> {
+    #[inline]
+    pub fn new (game: &'a Game) -> Self {
+        return Self { game, zoom: 1.0, selected: None, analysis: None }
+    }
+
+    pub fn update (&mut self, ui: &mut Ui) {
+        let techs = self.game.technologies.borrow();
+        let generation = self.game.technologies.generation();
+        if self.analysis.as_ref().map_or(true, |x| x.generation != generation) {
+            self.analysis = Some(Analysis {
+                generation,
+                cycles: Technology::cycles(&techs).into_iter().map(|x| x.into_iter().map(str::to_string).collect()).collect(),
+                unreachable: Technology::unreachable(&techs).into_iter().map(str::to_string).collect(),
+                layout: layout(&techs)
+            });
+        }
+        let Analysis { cycles, unreachable, layout, .. } = self.analysis.as_ref().unwrap();
+
+        ui.horizontal(|ui| {
+            ui.add(Slider::new(&mut self.zoom, 0.25..=2.0).text("Zoom"));
+            if !cycles.is_empty() {
+                ui.label(RichText::new(format!("{} cycles", cycles.len())).color(Color32::LIGHT_RED));
+            }
+            if !unreachable.is_empty() {
+                ui.label(RichText::new(format!("{} unreachable technologies", unreachable.len())).color(Color32::YELLOW));
+            }
+        });
+
+        // requirements of the selected technology, direct or not
+        let mut required = BTreeSet::new();
+        if let Some(ref selected) = self.selected {
+            let mut pending = vec![selected.as_str()];
+            while let Some(name) = pending.pop() {
+                if let Some(tech) = techs.get(name) {
+                    for parent in tech.unlocking_technologies.iter() {
+                        if required.insert(parent.as_str()) {
+                            pending.push(parent);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut clicked = None;
+        ScrollArea::both().show(ui, |ui| {
+            let size = layout.size * self.zoom;
+            let (rect, response) = ui.allocate_exact_size(size, Sense::click());
+            let painter = ui.painter_at(rect);
+            let node_rect = |pos: Pos2| Rect::from_min_size(rect.min + pos.to_vec2() * self.zoom, vec2(NODE_SIZE[0], NODE_SIZE[1]) * self.zoom);
+
+            for (label, y) in layout.bands.iter() {
+                painter.text(rect.min + vec2(4.0, *y - BAND_GAP * 0.5) * self.zoom, Align2::LEFT_CENTER, label, TextStyle::Heading.resolve(ui.style()), ui.visuals().weak_text_color());
+            }
+
+            for (name, tech) in techs.iter() {
+                let to = match layout.nodes.get(name.as_str()) {
+                    Some(pos) => node_rect(*pos).left_center(),
+                    None => continue
+                };
+                for parent in tech.unlocking_technologies.iter() {
+                    if let Some(from) = layout.nodes.get(parent.as_str()) {
+                        let highlighted = self.selected.as_deref() == Some(name.as_str()) || required.contains(name.as_str());
+                        let color = if highlighted { Color32::LIGHT_BLUE } else { Color32::from_gray(90) };
+                        painter.line_segment([node_rect(*from).right_center(), to], Stroke::new(1.0, color));
+                    }
+                }
+            }
+
+            for (name, pos) in layout.nodes.iter() {
+                let node = node_rect(*pos);
+                let fill = if self.selected.as_deref() == Some(name.as_str()) {
+                    Color32::from_rgb(40, 70, 110)
+                } else if required.contains(name.as_str()) {
+                    Color32::from_rgb(30, 50, 80)
+                } else {
+                    ui.visuals().extreme_bg_color
+                };
+
+                let stroke = if cycles.iter().any(|x| x.contains(name)) {
+                    Color32::LIGHT_RED
+                } else if unreachable.contains(name) {
+                    Color32::YELLOW
+                } else {
+                    ui.visuals().widgets.noninteractive.bg_stroke.color
+                };
+
+                painter.rect(node, 3.0, fill, Stroke::new(1.0, stroke));
+                painter.text(node.center(), Align2::CENTER_CENTER, name, TextStyle::Small.resolve(ui.style()), ui.visuals().text_color());
+            }
+
+            if let Some(pointer) = response.hover_pos() {
+                let hovered = layout.nodes.iter().find(|(_, pos)| node_rect(**pos).contains(pointer)).map(|(name, _)| name.as_str());
+                if let Some(name) = hovered {
+                    if response.clicked() {
+                        clicked = Some(Some(name.to_string()));
+                    }
+                    if let Some(tech) = techs.get(name) {
+                        response.on_hover_text_at_pointer(tooltip(name, tech));
+                    }
+                } else if response.clicked() {
+                    clicked = Some(None);
+                }
+            }
+        });
+
+        if let Some(selected) = clicked {
+            self.selected = selected;
+        }
+    }
+}
+
+/// Positions of the technologies, relative to the top left of the graph
+struct Layout {
+    nodes: BTreeMap<String, Pos2>,
+    /// Name and top of every category's band
+    bands: Vec<(String, f32)>,
+    size: eframe::epaint::Vec2
+}
+
+/// Places every era on its own column and every category on its own band of rows, with the technologies of a cell one below the other
+fn layout (techs: &BTreeMap<String, Technology>) -> Layout {
+    let eras = techs.values().map(|x| x.era.as_str()).collect::<BTreeSet<_>>().into_iter().collect::<Vec<_>>();
+    let mut cells = BTreeMap::<(&str, &str), Vec<&str>>::new();
+    for (name, tech) in techs.iter() {
+        cells.entry((tech.category.as_str(), tech.era.as_str())).or_default().push(name);
+    }
+
+    let mut bands = Vec::new();
+    let mut nodes = BTreeMap::new();
+    let mut top = BAND_GAP;
+    let categories = cells.keys().map(|(category, _)| *category).collect::<BTreeSet<_>>();
+    for category in categories {
+        let mut rows = 0;
+        for (column, era) in eras.iter().enumerate() {
+            let cell = match cells.get(&(category, *era)) {
+                Some(x) => x,
+                None => continue
+            };
+
+            rows = rows.max(cell.len());
+            for (row, name) in cell.iter().enumerate() {
+                let x = SPACING[0] + column as f32 * (NODE_SIZE[0] + SPACING[0]);
+                let y = top + row as f32 * (NODE_SIZE[1] + SPACING[1]);
+                nodes.insert(name.to_string(), pos2(x, y));
+            }
+        }
+
+        bands.push((category.to_string(), top));
+        top += rows as f32 * (NODE_SIZE[1] + SPACING[1]) + BAND_GAP;
+    }
+
+    let width = SPACING[0] + eras.len() as f32 * (NODE_SIZE[0] + SPACING[0]);
+    return Layout { nodes, bands, size: vec2(width, top) }
+}
+
+#[inline]
+fn tooltip (name: &str, tech: &Technology) -> String {
+    let mut text = format!("{name}\n{} ({})", tech.era, tech.category);
+    if !tech.unlocking_technologies.is_empty() {
+        text.push_str(&format!("\nRequires: {}", tech.unlocking_technologies.join(", ")));
+    }
+    for (key, value) in tech.modifier.iter() {
+        text.push_str(&format!("\n{key} = {value}"));
+    }
+    return text
+}
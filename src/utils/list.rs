@@ -1,4 +1,4 @@
-use std::{collections::{BTreeMap}, fmt::Debug};
+use std::{collections::{BTreeMap}, fmt::{Debug, Display}, path::Path};
 use eframe::{epaint::{Color32}, egui::{SidePanel, ScrollArea, RichText, Ui, Id, Label, Sense, TextStyle}};
 use crate::data::Game;
 use super::refcell::RefCell;
@@ -6,17 +6,23 @@ use super::refcell::RefCell;
 pub trait ListEntry {
     fn color (&self) -> Option<Color32>;
     fn render_info (&mut self, ui: &mut Ui, game: &Game);
+
+    /// Icon shown next to the entry's name, relative to the game's root
+    #[inline]
+    fn texture (&self) -> Option<&Path> {
+        None
+    }
 }
 
-pub struct List<'this, T> {
+pub struct List<'this, T, K = String> {
     list_id: Id,
-    items: &'this RefCell<BTreeMap<String, T>>,
-    current: Option<String>
+    items: &'this RefCell<BTreeMap<K, T>>,
+    current: Option<K>
 }
 
-impl<'this, T: Debug + ListEntry> List<'this, T> {
+impl<'this, T: Debug + ListEntry, K: Ord + Clone + Display> List<'this, T, K> {
     #[inline]
-    pub fn new (id: &str, items: &'this RefCell<BTreeMap<String, T>>) -> Self {
+    pub fn new (id: &str, items: &'this RefCell<BTreeMap<K, T>>) -> Self {
         return Self {
             list_id: format!("{id}_list").into(),
             items,
@@ -24,40 +30,61 @@ impl<'this, T: Debug + ListEntry> List<'this, T> {
         }
     }
 
+    /// Key of the selected entry
+    #[inline]
+    pub fn current (&self) -> Option<&K> {
+        return self.current.as_ref()
+    }
+
+    /// Shows the list and the selected entry, which is only written back (bumping the cell's generation) if it was edited
     #[inline]
-    pub fn update (&mut self, ui: &mut Ui, game: &Game) {
-        let mut items = self.items.borrow_mut();
+    pub fn update (&mut self, ui: &mut Ui, game: &Game) where T: Clone + PartialEq {
+        let items = self.items.borrow();
+        let localization = game.localization.borrow();
 
         SidePanel::left(self.list_id).show_inside(ui, |ui| {
             let height = ui.text_style_height(&TextStyle::Body);
-            ScrollArea::vertical().show_rows(ui, height, items.len(), |ui, _range| {
-                for (name, info) in items.iter_mut() {
-                    let mut text = RichText::new(name.to_string());
+            // only the visible rows are laid out, so only their textures are asked for
+            ScrollArea::vertical().show_rows(ui, height, items.len(), |ui, range| {
+                for (name, info) in items.iter().skip(range.start).take(range.len()) {
+                    let key = name.to_string();
+                    let mut text = RichText::new(localization.name(&key).unwrap_or(&key));
                     if let Some(color) = info.color() {
                         text = text.color(color);
                     }
 
-                    if ui.add(Label::new(text).sense(Sense::click())).clicked() {
+                    let clicked = ui.horizontal(|ui| {
+                        if let Some(texture) = info.texture().and_then(|path| game.textures.borrow_mut().get(ui.ctx(), &game.layers, path)) {
+                            ui.image(texture.id(), [height, height]);
+                        }
+                        ui.add(Label::new(text).sense(Sense::click())).on_hover_text(&key).clicked()
+                    }).inner;
+
+                    if clicked {
                         self.current = Some(name.clone())
                     }
                 }
             });
         });
 
-        ui.vertical_centered(|ui| {
-            if let Some((info, name)) = self.current.as_ref().and_then(|key| Some((items.get_mut(key)?, key))) {
-                let name = &*name;
-                let info = &mut *info;
-    
-                let mut text = RichText::new(name.to_string());
-                if let Some(color) = info.color() {
-                    text = text.color(color);
-                }
-                
-                ui.heading(text);
-                info.render_info(ui, game);
+        let edited = ui.vertical_centered(|ui| {
+            let (name, info) = self.current.as_ref().and_then(|key| items.get_key_value(key))?;
+            let mut edited = info.clone();
+
+            let key = name.to_string();
+            let mut text = RichText::new(localization.name(&key).unwrap_or(&key));
+            if let Some(color) = edited.color() {
+                text = text.color(color);
             }
-        });
 
+            ui.heading(text).on_hover_text(&key);
+            edited.render_info(ui, game);
+            return (edited != *info).then(|| (name.clone(), edited))
+        }).inner;
+
+        drop(items);
+        if let Some((name, info)) = edited {
+            self.items.borrow_mut().insert(name, info);
+        }
     }
 }
\ No newline at end of file
@@ -6,14 +6,26 @@ use tokio::fs::{ReadDir, DirEntry};
 pub mod list;
 pub mod refcell;
 pub mod storage;
+pub mod texture_cache;
 pub mod window;
 
 pub mod serde_vec_map {
     use std::marker::PhantomData;
     use serde::{Serializer, ser::SerializeMap, Serialize, Deserialize, Deserializer, de::Visitor};
 
+    /// Serializes a list of entries as a map, keeping their order and any repeated keys
+    #[repr(transparent)]
+    pub struct VecMap<'a, K, V> (pub &'a [(K, V)]);
+
+    impl<'a, K: Serialize, V: Serialize> Serialize for VecMap<'a, K, V> {
+        #[inline]
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+            self::serialize(self.0, serializer)
+        }
+    }
+
     #[inline]
-    pub fn serialize<K: Serialize, V: Serialize, S> (this: &Vec<(K, V)>, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+    pub fn serialize<K: Serialize, V: Serialize, S> (this: &[(K, V)], serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
         let mut serializer = serializer.serialize_map(Some(this.len()))?;
         for (key, value) in this {
             serializer.serialize_entry(key, value)?;
@@ -1,12 +1,14 @@
-use std::{cell::{UnsafeCell, Cell}, ops::{Deref, DerefMut}, fmt::Debug};
+use std::{cell::{UnsafeCell, Cell}, ops::{Deref, DerefMut}, fmt::Debug, mem::ManuallyDrop};
 
-const CLEAR: u8 = 0;
-const READ: u8 = 1;
-const WRITE: u8 = 2;
+/// No borrows alive. Otherwise, the state is the number of shared borrows alive, or [`WRITE`]
+const CLEAR: usize = 0;
+const WRITE: usize = usize::MAX;
 
 pub struct RefCell<T> {
     #[cfg(debug_assertions)]
-    state: Cell<u8>,
+    state: Cell<usize>,
+    /// Number of mutable borrows so far, which caches of the value compare against to know when to recompute
+    generation: Cell<u64>,
     inner: UnsafeCell<T>
 }
 
@@ -16,27 +18,39 @@ impl<T> RefCell<T> {
         return Self {
             #[cfg(debug_assertions)]
             state: Cell::new(CLEAR),
+            generation: Cell::new(0),
             inner: UnsafeCell::new(t)
         }
     }
 
+    /// Changes every time the value is mutably borrowed, so values derived from it are up to date as long as it stays the same
+    #[inline]
+    pub fn generation (&self) -> u64 {
+        return self.generation.get()
+    }
+
     #[inline]
     pub fn borrow (&self) -> Ref<'_, T> {
         #[cfg(debug_assertions)]
         match self.state.get() {
             WRITE => panic!("The value is currently mutably borrowed"),
-            _ => self.state.set(READ)
+            readers => self.state.set(readers + 1)
+        }
+        return Ref {
+            #[cfg(debug_assertions)]
+            state: &self.state,
+            value: unsafe { &*self.inner.get() }
         }
-        return Ref { inner: self }
     }
 
     #[inline]
     pub fn borrow_mut (&self) -> RefMut<'_, T> {
         #[cfg(debug_assertions)]
         match self.state.get() {
-            READ => self.state.set(WRITE),
+            CLEAR => self.state.set(WRITE),
             _ => panic!("The value cannot be mutably borrowed currently")
         }
+        self.generation.set(self.generation.get() + 1);
         return RefMut { inner: self }
     }
 }
@@ -45,7 +59,7 @@ impl<T: Debug> Debug for RefCell<T> {
     #[inline]
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         #[cfg(debug_assertions)]
-        if self.state.get() & WRITE != 0 {
+        if self.state.get() == WRITE {
             return write!(f, "RefCell {{ BorrowedMut }}");
         }
         return f.debug_struct("RefCell")
@@ -56,24 +70,52 @@ impl<T: Debug> Debug for RefCell<T> {
 
 unsafe impl<T: Send> Send for RefCell<T> {}
 
-#[repr(transparent)]
-pub struct Ref<'a, T> {
-    inner: &'a RefCell<T>
+/// Shared borrow of a [`RefCell`], or of a part of its value
+pub struct Ref<'a, T: ?Sized> {
+    #[cfg(debug_assertions)]
+    state: &'a Cell<usize>,
+    value: &'a T
 }
 
-impl<'a, T> Ref<'a, T> {
+impl<'a, T: ?Sized> Ref<'a, T> {
+    /// Borrows a part of the value, keeping the cell borrowed
     #[inline]
-    pub fn parent (&self) -> &RefCell<T> {
-        return self.inner
+    pub fn map<U: ?Sized, F: FnOnce(&T) -> &U> (this: Self, f: F) -> Ref<'a, U> {
+        let this = ManuallyDrop::new(this);
+        return Ref {
+            #[cfg(debug_assertions)]
+            state: this.state,
+            value: f(this.value)
+        }
+    }
+
+    /// Borrows a part of the value, if there's one. The cell stays borrowed only if there is
+    #[inline]
+    pub fn filter_map<U: ?Sized, F: FnOnce(&T) -> Option<&U>> (this: Self, f: F) -> Option<Ref<'a, U>> {
+        let value = f(this.value)?;
+        let this = ManuallyDrop::new(this);
+        return Some(Ref {
+            #[cfg(debug_assertions)]
+            state: this.state,
+            value
+        })
     }
 }
 
-impl<'a, T> Deref for Ref<'a, T> {
+impl<'a, T: ?Sized> Deref for Ref<'a, T> {
     type Target = T;
 
     #[inline]
     fn deref(&self) -> &Self::Target {
-        unsafe { &*self.inner.inner.get() }
+        return self.value
+    }
+}
+
+impl<'a, T: ?Sized> Drop for Ref<'a, T> {
+    #[inline]
+    fn drop(&mut self) {
+        #[cfg(debug_assertions)]
+        self.state.set(self.state.get() - 1);
     }
 }
 
@@ -0,0 +1,160 @@
+use std::{collections::HashMap, path::{Path, PathBuf}, fmt::Debug};
+use futures::Future;
+use eframe::{egui::{Context, Ui, TextureHandle, TextureOptions, ColorImage, RichText, Spinner, Vec2}, epaint::Color32};
+use tokio::task::JoinHandle;
+use crate::{runtime, data::{dds, GameLayers}};
+
+/// Textures kept at most by default
+const DEFAULT_CAPACITY: usize = 256;
+
+enum TextureState {
+    Loading (JoinHandle<Result<ColorImage, String>>),
+    Loaded (TextureHandle),
+    Failed (String)
+}
+
+struct Entry {
+    state: TextureState,
+    last_used: u64,
+    /// Frame the texture was last asked for on, during which it can't be evicted
+    frame: u64
+}
+
+/// Least recently used cache of the textures of the game files (or drawn from them, like flags), which are decoded in the background
+pub struct TextureCache {
+    capacity: usize,
+    tick: u64,
+    /// By path relative to the game's root, or by the key they were drawn as
+    entries: HashMap<PathBuf, Entry>
+}
+
+impl TextureCache {
+    #[inline]
+    pub fn new (capacity: usize) -> Self {
+        return Self { capacity, tick: 0, entries: HashMap::new() }
+    }
+
+    /// Texture at `path` (relative to the game's root, e.g. `gfx/interface/icons/religion_icons/catholic.dds`).
+    /// Returns `None` while it's loading or if it couldn't be loaded
+    #[inline]
+    pub fn get (&mut self, ctx: &Context, layers: &GameLayers, path: &Path) -> Option<TextureHandle> {
+        return self.get_with(ctx, path, || {
+            let file = layers.resolve(path);
+            async move {
+                let image = dds::from_path(file).await.map_err(|e| e.to_string())?;
+                Ok(ColorImage::from_rgba_unmultiplied([image.width() as usize, image.height() as usize], image.as_raw()))
+            }
+        })
+    }
+
+    /// Texture stored as `key`, which the future returned by `load` produces on the background the first time it's asked for.
+    /// Returns `None` while it's loading or if it couldn't be loaded
+    pub fn get_with<F, Fut> (&mut self, ctx: &Context, key: &Path, load: F) -> Option<TextureHandle> where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<ColorImage, String>> + Send + 'static
+    {
+        self.tick += 1;
+        let frame = ctx.frame_nr();
+        if !self.entries.contains_key(key) {
+            self.evict(frame);
+            let task = runtime().spawn(load());
+            self.entries.insert(key.to_path_buf(), Entry { state: TextureState::Loading(task), last_used: 0, frame });
+        }
+
+        let entry = self.entries.get_mut(key)?;
+        entry.last_used = self.tick;
+        entry.frame = frame;
+
+        if let TextureState::Loading(ref task) = entry.state {
+            if !task.is_finished() {
+                ctx.request_repaint();
+                return None
+            }
+
+            let task = match std::mem::replace(&mut entry.state, TextureState::Failed(String::new())) {
+                TextureState::Loading(task) => task,
+                _ => return None
+            };
+
+            entry.state = match runtime().block_on(task) {
+                Ok(Ok(image)) => TextureState::Loaded(ctx.load_texture(key.to_string_lossy(), image, TextureOptions::LINEAR)),
+                Ok(Err(e)) => TextureState::Failed(e),
+                Err(e) => TextureState::Failed(e.to_string())
+            };
+        }
+
+        return match entry.state {
+            TextureState::Loaded(ref texture) => Some(texture.clone()),
+            _ => None
+        }
+    }
+
+    /// Why the texture at `path` couldn't be loaded, if it couldn't
+    #[inline]
+    pub fn error (&self, path: &Path) -> Option<&str> {
+        return match self.entries.get(path)?.state {
+            TextureState::Failed(ref e) => Some(e),
+            _ => None
+        }
+    }
+
+    /// Shows the texture at `path` scaled to `size`, a spinner while it loads or the reason it couldn't be loaded
+    #[inline]
+    pub fn show (&mut self, ui: &mut Ui, layers: &GameLayers, path: &Path, size: impl Into<Vec2>) {
+        let texture = self.get(ui.ctx(), layers, path);
+        self.show_texture(ui, path, texture, size.into());
+    }
+
+    /// Shows the texture stored as `key` like [`TextureCache::show`], producing it with `load` like [`TextureCache::get_with`]
+    #[inline]
+    pub fn show_with<F, Fut> (&mut self, ui: &mut Ui, key: &Path, size: impl Into<Vec2>, load: F) where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<ColorImage, String>> + Send + 'static
+    {
+        let texture = self.get_with(ui.ctx(), key, load);
+        self.show_texture(ui, key, texture, size.into());
+    }
+
+    fn show_texture (&self, ui: &mut Ui, key: &Path, texture: Option<TextureHandle>, size: Vec2) {
+        match texture {
+            Some(texture) => { ui.image(texture.id(), size).on_hover_text(key.display().to_string()); },
+            None => match self.error(key) {
+                Some(e) => { ui.label(RichText::new("Missing texture").color(Color32::LIGHT_RED)).on_hover_text(format!("{}: {e}", key.display())); },
+                None => { ui.add(Spinner::new().size(size.y)); }
+            }
+        }
+    }
+
+    /// Drops the least recently used texture once the cache is full.
+    /// Textures shown on `frame` are kept even if that goes over capacity, since dropping them would only load them again on the next one
+    fn evict (&mut self, frame: u64) {
+        if self.entries.len() < self.capacity {
+            return
+        }
+
+        let oldest = self.entries.iter()
+            .filter(|(_, x)| x.frame != frame && !matches!(x.state, TextureState::Loading(_)))
+            .min_by_key(|(_, x)| x.last_used)
+            .map(|(path, _)| path.clone());
+
+        if let Some(path) = oldest {
+            self.entries.remove(&path);
+        }
+    }
+}
+
+impl Default for TextureCache {
+    #[inline]
+    fn default() -> Self {
+        return Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl Debug for TextureCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TextureCache")
+            .field("capacity", &self.capacity)
+            .field("len", &self.entries.len())
+            .finish()
+    }
+}